@@ -0,0 +1,112 @@
+//! Notification of changes the backend makes to a running stream's configuration.
+
+use std::sync::{
+    Mutex, TryLockError,
+    atomic::{AtomicBool, Ordering},
+};
+
+use crate::StreamConfig;
+
+/// A change to a running stream's configuration, as reported to the listener set with
+/// [`StreamTrait::set_config_listener`].
+///
+/// [`StreamTrait::set_config_listener`]: crate::traits::StreamTrait::set_config_listener
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConfigChange {
+    /// The configuration the stream ran with until the change.
+    pub previous: StreamConfig,
+
+    /// The configuration the stream runs with from now on. Its `buffer_size` is
+    /// [`BufferSize::Fixed`](crate::BufferSize::Fixed) with the new period when the backend
+    /// knows it.
+    pub config: StreamConfig,
+}
+
+/// Listener called with each [`ConfigChange`].
+pub(crate) type ConfigListener = Box<dyn FnMut(ConfigChange) + Send + 'static>;
+
+/// The listener of a stream's configuration changes, notified from whichever thread observes
+/// one.
+#[derive(Default)]
+#[allow(dead_code)]
+pub(crate) struct ConfigTracker {
+    listener: Mutex<Option<ConfigListener>>,
+    // Set with the first listener. Listeners are replaced but never removed.
+    has_listener: AtomicBool,
+}
+
+#[allow(dead_code)]
+impl ConfigTracker {
+    /// Replaces the listener.
+    pub(crate) fn set_listener(&self, listener: ConfigListener) {
+        *self.listener.lock().unwrap_or_else(|e| e.into_inner()) = Some(listener);
+        self.has_listener.store(true, Ordering::Release);
+    }
+
+    /// Whether a listener has been set, even if it is being replaced right now.
+    pub(crate) fn has_listener(&self) -> bool {
+        self.has_listener.load(Ordering::Acquire)
+    }
+
+    /// Reports a change from `previous` to `config`, if they differ, returning whether a
+    /// listener was told about it.
+    ///
+    /// May be called from the audio thread, so a change raised while the listener is being
+    /// replaced, or from inside the listener itself, is dropped rather than waited for.
+    pub(crate) fn notify(&self, previous: StreamConfig, config: StreamConfig) -> bool {
+        if previous == config {
+            return false;
+        }
+        let mut listener = match self.listener.try_lock() {
+            Ok(listener) => listener,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return false,
+        };
+        match listener.as_mut() {
+            Some(listener) => {
+                listener(ConfigChange { previous, config });
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl std::fmt::Debug for ConfigTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigTracker").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BufferSize;
+    use std::sync::Arc;
+
+    #[test]
+    fn reports_only_actual_changes() {
+        let tracker = ConfigTracker::default();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+
+        let previous = StreamConfig {
+            channels: 2,
+            sample_rate: 48_000,
+            buffer_size: BufferSize::Fixed(256),
+        };
+        let config = StreamConfig {
+            buffer_size: BufferSize::Fixed(512),
+            ..previous
+        };
+        assert!(!tracker.notify(previous, config));
+        assert!(!tracker.has_listener());
+        tracker.set_listener(Box::new(move |change| sink.lock().unwrap().push(change)));
+
+        assert!(!tracker.notify(previous, previous));
+        assert!(tracker.notify(previous, config));
+        assert!(tracker.has_listener());
+
+        assert_eq!(*seen.lock().unwrap(), [ConfigChange { previous, config }]);
+    }
+}
@@ -13,29 +13,77 @@ use crate::ChannelCount;
 /// This type provides structured information about an audio device beyond just its name.
 /// Availability depends on the host implementation and platform capabilities.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DeviceDescription {
     /// Human-readable device name
     name: String,
 
     /// Device manufacturer or vendor name
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
     manufacturer: Option<String>,
 
     /// Driver name
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
     driver: Option<String>,
 
     /// Categorization of device type
+    #[cfg_attr(feature = "serde", serde(default))]
     device_type: DeviceType,
 
     /// Connection/interface type
+    #[cfg_attr(feature = "serde", serde(default))]
     interface_type: InterfaceType,
 
     /// Direction: input, output, or duplex
+    #[cfg_attr(feature = "serde", serde(default))]
     direction: DeviceDirection,
 
     /// Physical address or connection identifier
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
     address: Option<String>,
 
+    /// USB or PCI vendor ID of the hardware
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    vendor_id: Option<u16>,
+
+    /// USB or PCI product ID of the hardware
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    product_id: Option<u16>,
+
+    /// Serial number of the physical unit
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    serial_number: Option<String>,
+
+    /// Backend name that does not depend on enumeration order
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    stable_name: Option<String>,
+
     /// Additional description lines with non-structured, detailed information.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "<[String]>::is_empty")
+    )]
     extended: Box<[String]>,
 }
 
@@ -44,6 +92,11 @@ pub struct DeviceDescription {
 /// This describes the kind of audio device (speaker, microphone, headset, etc.)
 /// regardless of how it connects to the system.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 #[non_exhaustive]
 pub enum DeviceType {
     /// Speaker (built-in or external)
@@ -78,6 +131,7 @@ pub enum DeviceType {
 
     /// Unknown or unclassified device type
     #[default]
+    #[cfg_attr(feature = "serde", serde(other))]
     Unknown,
 }
 
@@ -86,6 +140,11 @@ pub enum DeviceType {
 /// This describes the physical or logical connection between the audio device
 /// and the computer system.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 #[non_exhaustive]
 pub enum InterfaceType {
     /// Built-in to the system (integrated audio chipset)
@@ -129,11 +188,17 @@ pub enum InterfaceType {
 
     /// Unknown connection type
     #[default]
+    #[cfg_attr(feature = "serde", serde(other))]
     Unknown,
 }
 
 /// The direction(s) that a device supports.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 #[non_exhaustive]
 pub enum DeviceDirection {
     /// Input only (capture/recording)
@@ -147,6 +212,7 @@ pub enum DeviceDirection {
 
     /// Direction unknown or not yet determined
     #[default]
+    #[cfg_attr(feature = "serde", serde(other))]
     Unknown,
 }
 
@@ -208,6 +274,33 @@ impl DeviceDescription {
         self.address.as_deref()
     }
 
+    /// Returns the USB or PCI vendor ID of the hardware if available.
+    pub fn vendor_id(&self) -> Option<u16> {
+        self.vendor_id
+    }
+
+    /// Returns the USB or PCI product ID of the hardware if available.
+    pub fn product_id(&self) -> Option<u16> {
+        self.product_id
+    }
+
+    /// Returns the serial number of the physical unit if available.
+    ///
+    /// The format is backend-specific, so serial numbers are only comparable between devices of
+    /// the same host.
+    pub fn serial_number(&self) -> Option<&str> {
+        self.serial_number.as_deref()
+    }
+
+    /// Returns a backend name for the device that survives reboots and re-plugging, if available.
+    ///
+    /// Unlike [`DeviceId`](crate::DeviceId), this does not depend on enumeration order: for ALSA
+    /// it is the PCM name with the card's ID in place of its index (e.g. `hw:CARD=PCH,DEV=0`),
+    /// for PipeWire the `node.name`, and for PulseAudio the sink or source name.
+    pub fn stable_name(&self) -> Option<&str> {
+        self.stable_name.as_deref()
+    }
+
     /// Returns additional description lines with detailed information.
     pub fn extended(&self) -> impl Iterator<Item = &str> {
         self.extended.iter().map(String::as_str)
@@ -297,6 +390,10 @@ pub struct DeviceDescriptionBuilder {
     interface_type: InterfaceType,
     direction: DeviceDirection,
     address: Option<String>,
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+    serial_number: Option<String>,
+    stable_name: Option<String>,
     extended: Vec<String>,
 }
 
@@ -311,6 +408,10 @@ impl DeviceDescriptionBuilder {
             interface_type: InterfaceType::default(),
             direction: DeviceDirection::default(),
             address: None,
+            vendor_id: None,
+            product_id: None,
+            serial_number: None,
+            stable_name: None,
             extended: Vec::new(),
         }
     }
@@ -351,6 +452,30 @@ impl DeviceDescriptionBuilder {
         self
     }
 
+    /// Sets the USB or PCI vendor ID.
+    pub fn vendor_id(mut self, vendor_id: u16) -> Self {
+        self.vendor_id = Some(vendor_id);
+        self
+    }
+
+    /// Sets the USB or PCI product ID.
+    pub fn product_id(mut self, product_id: u16) -> Self {
+        self.product_id = Some(product_id);
+        self
+    }
+
+    /// Sets the serial number.
+    pub fn serial_number(mut self, serial_number: impl AsRef<str>) -> Self {
+        self.serial_number = Some(serial_number.as_ref().to_owned());
+        self
+    }
+
+    /// Sets the stable name.
+    pub fn stable_name(mut self, stable_name: impl AsRef<str>) -> Self {
+        self.stable_name = Some(stable_name.as_ref().to_owned());
+        self
+    }
+
     /// Sets the description lines, replacing any previously added lines.
     pub fn extended<S: AsRef<str>>(mut self, lines: impl IntoIterator<Item = S>) -> Self {
         self.extended = lines.into_iter().map(|s| s.as_ref().to_owned()).collect();
@@ -373,6 +498,10 @@ impl DeviceDescriptionBuilder {
             interface_type: self.interface_type,
             direction: self.direction,
             address: self.address,
+            vendor_id: self.vendor_id,
+            product_id: self.product_id,
+            serial_number: self.serial_number,
+            stable_name: self.stable_name,
             extended: self.extended.into_boxed_slice(),
         }
     }
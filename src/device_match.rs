@@ -0,0 +1,213 @@
+//! Re-identifying a saved device after reboots and re-plugging.
+//!
+//! A [`DeviceId`] names a device the way the backend enumerates it, which is not always stable:
+//! an ALSA card index changes when USB cards enumerate in a different order. Matching a saved
+//! [`DeviceId`] together with its [`DeviceDescription`] against the current devices also weighs
+//! the hardware attributes that do not change, such as the USB serial number.
+
+use crate::{DeviceDescription, DeviceId};
+
+/// How confidently a device found by [`HostTrait::find_device`] is the one that was saved.
+///
+/// Variants are ordered from weakest to strongest, so they can be compared to reject weak
+/// matches:
+///
+/// ```
+/// use cpal::MatchConfidence;
+///
+/// assert!(MatchConfidence::Serial > MatchConfidence::Id);
+/// ```
+///
+/// [`HostTrait::find_device`]: crate::traits::HostTrait::find_device
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum MatchConfidence {
+    /// Only the device name matched.
+    Name,
+
+    /// The vendor and product IDs matched: the same hardware model, possibly a different unit.
+    Model,
+
+    /// The [`DeviceId`] matched and no stable attribute contradicts it.
+    Id,
+
+    /// The [`stable_name`](DeviceDescription::stable_name) matched.
+    StableName,
+
+    /// The serial number matched and the vendor and product IDs, where known, agree: the same
+    /// physical unit.
+    Serial,
+}
+
+/// A device found by [`HostTrait::find_device`].
+///
+/// [`HostTrait::find_device`]: crate::traits::HostTrait::find_device
+#[derive(Clone, Debug)]
+pub struct DeviceMatch<D> {
+    /// The matching device.
+    pub device: D,
+    /// How confidently `device` is the saved one.
+    pub confidence: MatchConfidence,
+}
+
+/// Ranks a candidate against the saved device; the highest score wins. Fields after
+/// `confidence` break ties between candidates, e.g. the several PCMs of one USB card that all
+/// share its serial number.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Score {
+    pub(crate) confidence: MatchConfidence,
+    stable_name: bool,
+    id: bool,
+    description: bool,
+    name: bool,
+}
+
+/// Scores `candidate` as a match for the saved device, or returns `None` if it is not one.
+///
+/// A candidate is rejected outright when a hardware attribute both sides report disagrees, or
+/// when it lacks a direction the saved device had.
+pub(crate) fn score(
+    saved_id: &DeviceId,
+    saved: &DeviceDescription,
+    candidate_id: &DeviceId,
+    candidate: &DeviceDescription,
+) -> Option<Score> {
+    let agree = |a: Option<u16>, b: Option<u16>| a.zip(b).map(|(a, b)| a == b);
+    let vendor = agree(saved.vendor_id(), candidate.vendor_id());
+    let product = agree(saved.product_id(), candidate.product_id());
+    let serial = saved
+        .serial_number()
+        .zip(candidate.serial_number())
+        .map(|(a, b)| a == b);
+    if [vendor, product, serial].contains(&Some(false)) {
+        return None;
+    }
+
+    let lost_direction = |saved_has: bool, candidate_has: bool| {
+        saved_has && !candidate_has && candidate.direction() != crate::DeviceDirection::Unknown
+    };
+    if lost_direction(saved.supports_input(), candidate.supports_input())
+        || lost_direction(saved.supports_output(), candidate.supports_output())
+    {
+        return None;
+    }
+
+    // Stable names that differ do not rule a candidate out: ALSA renames the card IDs of two
+    // identical cards when they swap enumeration order. They do discredit a matching `DeviceId`.
+    let stable_name = saved
+        .stable_name()
+        .zip(candidate.stable_name())
+        .map(|(a, b)| a == b);
+    let id = saved_id == candidate_id && stable_name != Some(false);
+    let name = saved.name() == candidate.name();
+
+    let confidence = if serial == Some(true) {
+        MatchConfidence::Serial
+    } else if stable_name == Some(true) {
+        MatchConfidence::StableName
+    } else if id {
+        MatchConfidence::Id
+    } else if vendor == Some(true) && product == Some(true) {
+        MatchConfidence::Model
+    } else if name {
+        MatchConfidence::Name
+    } else {
+        return None;
+    };
+
+    Some(Score {
+        confidence,
+        stable_name: stable_name == Some(true),
+        id,
+        description: saved == candidate,
+        name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ALL_HOSTS, DeviceDescriptionBuilder, DeviceDirection};
+
+    fn usb_pcm(pcm: &str, card_id: &str, serial: &str) -> (DeviceId, DeviceDescription) {
+        let card_index_id = DeviceId::new(ALL_HOSTS[0], pcm.replace(card_id, "1"));
+        let description = DeviceDescriptionBuilder::new("Scarlett 2i2 USB, USB Audio")
+            .direction(DeviceDirection::Duplex)
+            .vendor_id(0x1235)
+            .product_id(0x8210)
+            .serial_number(serial)
+            .stable_name(pcm)
+            .build();
+        (card_index_id, description)
+    }
+
+    #[test]
+    fn serial_outranks_reused_id() {
+        let (saved_id, saved) = usb_pcm("hw:CARD=USB,DEV=0", "USB", "Y8X4");
+        // Re-plugged: the same unit is now card 2, another unit took card index 1 and ALSA
+        // renamed the card IDs.
+        let (other_id, other) = usb_pcm("hw:CARD=USB,DEV=0", "USB", "Z111");
+        let (_, moved) = usb_pcm("hw:CARD=USB_1,DEV=0", "USB_1", "Y8X4");
+        let moved_id = DeviceId::new(ALL_HOSTS[0], "hw:CARD=2,DEV=0");
+
+        assert_eq!(other_id, saved_id);
+        assert_eq!(score(&saved_id, &saved, &other_id, &other), None);
+        assert_eq!(
+            score(&saved_id, &saved, &moved_id, &moved).map(|s| s.confidence),
+            Some(MatchConfidence::Serial)
+        );
+    }
+
+    #[test]
+    fn ties_prefer_the_same_endpoint() {
+        let (saved_id, saved) = usb_pcm("hw:CARD=USB,DEV=0", "USB", "Y8X4");
+        let (plug_id, plug) = usb_pcm("plughw:CARD=USB,DEV=0", "USB", "Y8X4");
+
+        let exact = score(&saved_id, &saved, &saved_id, &saved).unwrap();
+        let sibling = score(&saved_id, &saved, &plug_id, &plug).unwrap();
+        assert_eq!(sibling.confidence, MatchConfidence::Serial);
+        assert!(exact > sibling);
+    }
+
+    #[test]
+    fn id_without_stable_attributes() {
+        let id = DeviceId::new(ALL_HOSTS[0], "default");
+        let saved = DeviceDescriptionBuilder::new("Default Audio Device").build();
+        let renamed = DeviceDescriptionBuilder::new("Default").build();
+        assert_eq!(
+            score(&id, &saved, &id, &renamed).map(|s| s.confidence),
+            Some(MatchConfidence::Id)
+        );
+
+        let other_id = DeviceId::new(ALL_HOSTS[0], "pulse");
+        assert_eq!(
+            score(&id, &saved, &other_id, &saved).map(|s| s.confidence),
+            Some(MatchConfidence::Name)
+        );
+        assert_eq!(score(&id, &saved, &other_id, &renamed), None);
+    }
+
+    #[test]
+    fn stable_name_mismatch_discredits_id() {
+        let id = DeviceId::new(ALL_HOSTS[0], "hw:CARD=0,DEV=0");
+        let saved = DeviceDescriptionBuilder::new("HDA Intel PCH")
+            .stable_name("hw:CARD=PCH,DEV=0")
+            .build();
+        let now = DeviceDescriptionBuilder::new("HDA NVidia")
+            .stable_name("hw:CARD=NVidia,DEV=0")
+            .build();
+        assert_eq!(score(&id, &saved, &id, &now), None);
+    }
+
+    #[test]
+    fn direction_must_be_kept() {
+        let id = DeviceId::new(ALL_HOSTS[0], "hw:CARD=0,DEV=0");
+        let saved = DeviceDescriptionBuilder::new("Mic")
+            .direction(DeviceDirection::Input)
+            .build();
+        let output = DeviceDescriptionBuilder::new("Mic")
+            .direction(DeviceDirection::Output)
+            .build();
+        assert_eq!(score(&id, &saved, &id, &output), None);
+    }
+}
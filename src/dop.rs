@@ -0,0 +1,432 @@
+//! DSD-over-PCM (DoP) packing for playing DSD on PCM-only devices.
+//!
+//! DoP v1.1 carries 16 DSD bits per channel in the low 16 bits of a 24-bit PCM sample. The
+//! top byte holds a marker that alternates between `0x05` and `0xFA` on every PCM frame, which
+//! a DoP-capable DAC uses to tell DSD apart from PCM. The PCM carrier rate is therefore the
+//! DSD bit rate divided by 16: DSD64 (2.8224 MHz) is carried at 176.4 kHz, DSD128 at 352.8 kHz,
+//! and so on.
+//!
+//! Use [`DeviceTrait::build_dop_output_stream`] to open a stream that accepts DSD bytes, or
+//! drive a [`DopEncoder`] from your own [`build_output_stream_raw`] callback.
+//!
+//! [`DeviceTrait::build_dop_output_stream`]: crate::traits::DeviceTrait::build_dop_output_stream
+//! [`build_output_stream_raw`]: crate::traits::DeviceTrait::build_output_stream_raw
+
+use std::{
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::{
+    ChannelCount, ClockMapping, ConfigChange, Data, Error, ErrorKind, FrameCount, SampleFormat,
+    StreamInstant, StreamPosition, StreamState, XrunPolicy,
+    host::equilibrium::DSD_EQUILIBRIUM_BYTE, traits::StreamTrait,
+};
+
+/// The pair of marker bytes that alternate in the top byte of consecutive DoP frames.
+pub const DOP_MARKERS: [u8; 2] = [0x05, 0xFA];
+
+/// Number of DSD bytes packed into each PCM sample.
+const DSD_BYTES_PER_SAMPLE: usize = 2;
+
+/// Number of periods of DoP silence written before the underlying stream is paused.
+const PAUSE_SILENCE_PERIODS: u32 = 2;
+
+/// Longest a pause waits for the silence periods, in case the host stops calling back.
+const PAUSE_SILENCE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Packs DSD bytes into DoP PCM samples.
+///
+/// The encoder tracks the marker phase across calls, so the same encoder must be used for the
+/// lifetime of a stream: restarting the phase mid-stream makes the DAC drop out of DSD mode.
+///
+/// DSD input is byte-interleaved, oldest bit first in the MSB, matching
+/// [`SampleFormat::DsdU8`]: `ch0, ch1, ..., ch0, ch1, ...`. Each PCM frame consumes two DSD
+/// bytes per channel.
+#[derive(Clone, Debug)]
+pub struct DopEncoder {
+    channels: usize,
+    marker: usize,
+}
+
+impl DopEncoder {
+    /// Creates an encoder for a stream with the given number of channels.
+    pub fn new(channels: ChannelCount) -> Self {
+        Self {
+            channels: channels.max(1) as usize,
+            marker: 0,
+        }
+    }
+
+    /// Returns `true` if `sample_format` can carry DoP.
+    ///
+    /// DoP needs at least 24 significant bits, so only [`SampleFormat::I24`] and
+    /// [`SampleFormat::I32`] qualify. With `I32` the DoP word occupies the upper 24 bits.
+    pub fn supports_format(sample_format: SampleFormat) -> bool {
+        matches!(sample_format, SampleFormat::I24 | SampleFormat::I32)
+    }
+
+    /// Returns the number of DSD bytes needed to fill `samples` PCM samples.
+    pub fn dsd_len(samples: usize) -> usize {
+        samples * DSD_BYTES_PER_SAMPLE
+    }
+
+    /// Packs `dsd` into `pcm`.
+    ///
+    /// `dsd` should hold [`dsd_len(pcm.len())`](Self::dsd_len) bytes. If it is shorter, the
+    /// remaining PCM frames are filled with DoP silence; surplus bytes are ignored.
+    ///
+    /// Does nothing if `pcm` is not in a format accepted by
+    /// [`supports_format`](Self::supports_format).
+    pub fn encode(&mut self, dsd: &[u8], pcm: &mut Data) {
+        let sample_format = pcm.sample_format();
+        if !Self::supports_format(sample_format) {
+            return;
+        }
+        let channels = self.channels;
+        let frame_bytes = channels * DSD_BYTES_PER_SAMPLE;
+        let full_frames = dsd.len() / frame_bytes;
+        // `I24` and `I32` share the same native-endian 4-byte `i32` storage.
+        let sample_size = sample_format.sample_size();
+        for (frame_index, frame) in pcm
+            .bytes_mut()
+            .chunks_mut(channels * sample_size)
+            .enumerate()
+        {
+            let marker = self.next_marker();
+            let bytes = (frame_index < full_frames).then(|| {
+                let base = frame_index * frame_bytes;
+                &dsd[base..base + frame_bytes]
+            });
+            for (ch, sample) in frame.chunks_exact_mut(sample_size).enumerate() {
+                let (older, newer) = match bytes {
+                    Some(b) => (b[ch], b[channels + ch]),
+                    None => (DSD_EQUILIBRIUM_BYTE, DSD_EQUILIBRIUM_BYTE),
+                };
+                sample.copy_from_slice(&pack(marker, older, newer, sample_format).to_ne_bytes());
+            }
+        }
+    }
+
+    /// Fills `pcm` with DoP-encoded DSD silence.
+    ///
+    /// Unlike PCM zeros, this keeps the markers running so the DAC stays locked in DSD mode.
+    pub fn fill_silence(&mut self, pcm: &mut Data) {
+        self.encode(&[], pcm);
+    }
+
+    fn next_marker(&mut self) -> u8 {
+        let marker = DOP_MARKERS[self.marker];
+        self.marker ^= 1;
+        marker
+    }
+}
+
+/// Builds one DoP sample: `marker` in bits 23..16, then the older and newer DSD bytes.
+#[inline]
+fn pack(marker: u8, older: u8, newer: u8, sample_format: SampleFormat) -> i32 {
+    let word = u32::from_be_bytes([0, marker, older, newer]);
+    match sample_format {
+        // Sign-extend the 24-bit word into the `i32` container.
+        SampleFormat::I24 => ((word << 8) as i32) >> 8,
+        _ => (word << 8) as i32,
+    }
+}
+
+/// Switches a DoP callback to silence and counts the silent periods it has written since.
+#[derive(Debug, Default)]
+pub(crate) struct Silence {
+    requested: AtomicBool,
+    periods: Mutex<u32>,
+    written: Condvar,
+}
+
+impl Silence {
+    /// Asks for silence from the next period on and waits until `periods` silent periods have
+    /// been written or `timeout` has passed.
+    fn request(&self, periods: u32, timeout: Duration) {
+        let mut written = self.periods.lock().unwrap_or_else(|e| e.into_inner());
+        *written = 0;
+        self.requested.store(true, Ordering::Release);
+        if periods == 0 {
+            return;
+        }
+        let _ = self
+            .written
+            .wait_timeout_while(written, timeout, |written| *written < periods)
+            .unwrap_or_else(|e| e.into_inner());
+    }
+
+    fn cancel(&self) {
+        self.requested.store(false, Ordering::Release);
+    }
+
+    fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Acquire)
+    }
+
+    /// Counts a silent period written by the data callback.
+    fn report(&self) {
+        let mut written = self.periods.lock().unwrap_or_else(|e| e.into_inner());
+        *written = written.saturating_add(1);
+        self.written.notify_all();
+    }
+}
+
+/// An output stream that plays DSD through a PCM device using DoP.
+///
+/// Created by [`DeviceTrait::build_dop_output_stream`]. When paused, the stream first emits a
+/// short run of DoP-encoded silence so the DAC mutes cleanly instead of dropping out of DSD mode
+/// mid-sample.
+///
+/// [`DeviceTrait::build_dop_output_stream`]: crate::traits::DeviceTrait::build_dop_output_stream
+pub struct DopStream<S> {
+    stream: S,
+    silence: Arc<Silence>,
+    running: AtomicBool,
+}
+
+impl<S> DopStream<S> {
+    pub(crate) fn new(stream: S, silence: Arc<Silence>) -> Self {
+        Self {
+            stream,
+            silence,
+            running: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the underlying PCM stream.
+    pub fn as_inner(&self) -> &S {
+        &self.stream
+    }
+
+    /// Consumes the wrapper and returns the underlying PCM stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: StreamTrait> DopStream<S> {
+    /// Switches the callback to DoP silence and, while the stream runs, waits for the callback
+    /// to have written the silent periods.
+    fn mute(&self) {
+        // Blocking is not available on the main thread of `wasm32-unknown-unknown`.
+        let periods = if cfg!(all(target_arch = "wasm32", target_os = "unknown"))
+            || !self.running.swap(false, Ordering::AcqRel)
+        {
+            0
+        } else {
+            PAUSE_SILENCE_PERIODS
+        };
+        self.silence.request(periods, PAUSE_SILENCE_TIMEOUT);
+    }
+}
+
+impl<S: StreamTrait> StreamTrait for DopStream<S> {
+    fn start(&self) -> Result<(), Error> {
+        self.silence.cancel();
+        self.stream.start()?;
+        self.running.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    fn pause(&self) -> Result<(), Error> {
+        self.mute();
+        self.stream.pause()
+    }
+
+    fn stop(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        // Audio already queued is drained as-is; only newly requested periods turn silent.
+        self.running.store(false, Ordering::Release);
+        self.silence.request(0, Duration::ZERO);
+        self.stream.stop(timeout)
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        self.stream.flush()
+    }
+
+    fn position(&self) -> Result<StreamPosition, Error> {
+        self.stream.position()
+    }
+
+    fn now(&self) -> StreamInstant {
+        self.stream.now()
+    }
+
+    fn clock_mapping(&self) -> Result<ClockMapping, Error> {
+        self.stream.clock_mapping()
+    }
+
+    fn buffer_size(&self) -> Result<FrameCount, Error> {
+        self.stream.buffer_size()
+    }
+
+    fn set_buffer_size(&self, frames: FrameCount) -> Result<(), Error> {
+        self.stream.set_buffer_size(frames)
+    }
+
+    fn state(&self) -> Result<StreamState, Error> {
+        self.stream.state()
+    }
+
+    fn set_state_listener(
+        &self,
+        listener: Box<dyn FnMut(StreamState) + Send + 'static>,
+    ) -> Result<(), Error> {
+        self.stream.set_state_listener(listener)
+    }
+
+    fn set_xrun_policy(&self, policy: XrunPolicy) -> Result<(), Error> {
+        self.stream.set_xrun_policy(policy)
+    }
+
+    fn set_config_listener(
+        &self,
+        listener: Box<dyn FnMut(ConfigChange) + Send + 'static>,
+    ) -> Result<(), Error> {
+        self.stream.set_config_listener(listener)
+    }
+}
+
+/// Wraps a DSD data callback into a PCM output callback that encodes DoP.
+pub(crate) fn output_callback<D>(
+    channels: ChannelCount,
+    silence: Arc<Silence>,
+    mut data_callback: D,
+) -> impl FnMut(&mut Data, &crate::CallbackInfo) + Send + 'static
+where
+    D: FnMut(&mut [u8], &crate::CallbackInfo) + Send + 'static,
+{
+    let mut encoder = DopEncoder::new(channels);
+    let mut dsd = Vec::new();
+    move |data, info| {
+        if silence.is_requested() {
+            encoder.fill_silence(data);
+            silence.report();
+            return;
+        }
+        let len = DopEncoder::dsd_len(data.len());
+        // Grows only when the host hands over a larger period than any before it.
+        if dsd.len() < len {
+            dsd.resize(len, DSD_EQUILIBRIUM_BYTE);
+        }
+        let dsd = &mut dsd[..len];
+        dsd.fill(DSD_EQUILIBRIUM_BYTE);
+        data_callback(dsd, info);
+        encoder.encode(dsd, data);
+    }
+}
+
+/// Validates that a PCM stream configuration can carry DoP.
+pub(crate) fn check_format(sample_format: SampleFormat) -> Result<(), Error> {
+    if DopEncoder::supports_format(sample_format) {
+        Ok(())
+    } else {
+        Err(Error::with_message(
+            ErrorKind::UnsupportedConfig,
+            format!("Sample format {sample_format} cannot carry DoP; use i24 or i32"),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data_of(samples: &mut [i32], sample_format: SampleFormat) -> Data {
+        unsafe { Data::from_parts(samples.as_mut_ptr().cast(), samples.len(), sample_format) }
+    }
+
+    #[test]
+    fn markers_alternate_per_frame() {
+        let mut pcm = [0i32; 6];
+        let mut encoder = DopEncoder::new(2);
+        encoder.encode(&[0u8; 12], &mut data_of(&mut pcm, SampleFormat::I32));
+        let markers: Vec<u8> = pcm.iter().map(|s| (*s as u32 >> 24) as u8).collect();
+        assert_eq!(markers, [0x05, 0x05, 0xFA, 0xFA, 0x05, 0x05]);
+
+        // The phase continues across calls.
+        let mut pcm = [0i32; 2];
+        encoder.encode(&[0u8; 4], &mut data_of(&mut pcm, SampleFormat::I32));
+        assert_eq!((pcm[0] as u32 >> 24) as u8, 0xFA);
+    }
+
+    #[test]
+    fn packs_interleaved_bytes() {
+        // Two channels, one PCM frame: ch0 gets 0x11 then 0x22, ch1 gets 0xAA then 0xBB.
+        let dsd = [0x11, 0xAA, 0x22, 0xBB];
+        let mut pcm = [0i32; 2];
+        DopEncoder::new(2).encode(&dsd, &mut data_of(&mut pcm, SampleFormat::I32));
+        assert_eq!(pcm[0] as u32, 0x0511_2200);
+        assert_eq!(pcm[1] as u32, 0x05AA_BB00);
+    }
+
+    #[test]
+    fn i24_is_sign_extended() {
+        let mut pcm = [0i32; 2];
+        DopEncoder::new(1).encode(
+            &[0x12, 0x34, 0x56, 0x78],
+            &mut data_of(&mut pcm, SampleFormat::I24),
+        );
+        assert_eq!(pcm[0], 0x05_1234);
+        // 0xFA in the top byte of a 24-bit word is negative.
+        assert_eq!(pcm[1], (0xFA_5678u32 | 0xFF00_0000) as i32);
+    }
+
+    #[test]
+    fn short_input_is_padded_with_silence() {
+        let mut pcm = [0i32; 2];
+        DopEncoder::new(1).encode(&[0x00, 0x00], &mut data_of(&mut pcm, SampleFormat::I32));
+        assert_eq!(pcm[0] as u32, 0x0500_0000);
+        assert_eq!(pcm[1] as u32, 0xFA69_6900);
+    }
+
+    #[test]
+    fn rejects_narrow_formats() {
+        assert!(DopEncoder::supports_format(SampleFormat::I24));
+        assert!(DopEncoder::supports_format(SampleFormat::I32));
+        assert!(!DopEncoder::supports_format(SampleFormat::I16));
+        assert!(!DopEncoder::supports_format(SampleFormat::F32));
+        assert!(check_format(SampleFormat::U24).is_err());
+    }
+
+    #[test]
+    fn silence_request_waits_for_the_callback() {
+        let silence = Arc::new(Silence::default());
+        let mut callback = output_callback(1, silence.clone(), |dsd: &mut [u8], _| dsd.fill(0));
+        let done = Arc::new(AtomicBool::new(false));
+        let audio_thread = std::thread::spawn({
+            let done = done.clone();
+            move || {
+                let info = crate::CallbackInfo::new(
+                    crate::StreamTimestamp {
+                        callback: StreamInstant::ZERO,
+                        device: StreamInstant::ZERO,
+                    },
+                    false,
+                );
+                let mut last = [0i32; 4];
+                while !done.load(Ordering::Acquire) {
+                    callback(&mut data_of(&mut last, SampleFormat::I32), &info);
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                last
+            }
+        });
+
+        silence.request(PAUSE_SILENCE_PERIODS, Duration::from_secs(5));
+        assert!(*silence.periods.lock().unwrap() >= PAUSE_SILENCE_PERIODS);
+        done.store(true, Ordering::Release);
+        let last = audio_thread.join().unwrap();
+        assert!(last.iter().all(|s| (*s as u32 >> 8) & 0xFFFF == 0x6969));
+
+        // Nothing calls back any more, so only the timeout ends the wait.
+        let started = std::time::Instant::now();
+        silence.request(1, Duration::from_millis(20));
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,219 @@
+//! Clock drift estimation between two streams.
+//!
+//! Two devices rarely share a crystal, so audio captured from one and played to another drifts
+//! apart by tens of parts per million. A [`DriftEstimator`] measures each device's true sample
+//! rate from its callback timestamps and reports the ratio between them, ready to drive a
+//! resampler bridging the two streams.
+
+use std::{
+    f64::consts::{PI, SQRT_2},
+    sync::{Arc, Mutex},
+};
+
+use crate::{CallbackInfo, SampleRate, StreamInstant};
+
+/// Timing errors larger than this many periods are treated as a discontinuity, such as a pause
+/// or a dropout, rather than jitter.
+const MAX_ERROR_PERIODS: f64 = 2.0;
+
+/// The latest rate measured by a [`DriftTracker`], shared with its [`DriftEstimator`].
+type PublishedRate = Arc<Mutex<Option<f64>>>;
+
+/// Estimates the ratio between the actual sample rates of two streams.
+///
+/// Created together with a [`DriftTracker`] for each stream: the source, whose audio is to be
+/// resampled, and the sink it is resampled for. Each tracker is moved into its stream's data
+/// callback and fed every [`CallbackInfo`] along with the number of frames in the buffer.
+///
+/// Rates are measured against the stream clocks, so both streams must come from hosts whose
+/// timestamps advance with the system clock, as all hosts' do except the web ones (see
+/// [`ClockMapping`](crate::ClockMapping)). Their origins may differ.
+///
+/// ```no_run
+/// use cpal::DriftEstimator;
+///
+/// // Both devices nominally run at 48 kHz; smooth over a few seconds.
+/// let (estimator, mut source, mut sink) = DriftEstimator::new(48_000, 48_000, 0.2);
+/// // In the input callback: `source.update(info, data.len() / channels)`.
+/// // In the output callback: `sink.update(info, data.len() / channels)`, then resample by
+/// // `estimator.ratio().unwrap_or(1.0)` output frames per input frame.
+/// # let _ = (estimator, &mut source, &mut sink);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DriftEstimator {
+    source: PublishedRate,
+    sink: PublishedRate,
+}
+
+impl DriftEstimator {
+    /// Creates an estimator and the trackers for its source and sink streams.
+    ///
+    /// `bandwidth` is the loop bandwidth of the filters, in Hz. Lower values smooth out more
+    /// timing jitter but take longer, roughly `1 / bandwidth` seconds, to settle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either sample rate is zero or `bandwidth` is not positive.
+    pub fn new(
+        source_rate: SampleRate,
+        sink_rate: SampleRate,
+        bandwidth: f64,
+    ) -> (Self, DriftTracker, DriftTracker) {
+        let source = DriftTracker::new(source_rate, bandwidth);
+        let sink = DriftTracker::new(sink_rate, bandwidth);
+        let estimator = Self {
+            source: source.published.clone(),
+            sink: sink.published.clone(),
+        };
+        (estimator, source, sink)
+    }
+
+    /// Returns the sink's measured sample rate divided by the source's, i.e. how many sink
+    /// frames correspond to one source frame, or `None` until both streams have delivered at
+    /// least two callbacks.
+    ///
+    /// This briefly locks the rates the trackers publish, which never wait for it in return.
+    pub fn ratio(&self) -> Option<f64> {
+        let source = (*self.source.lock().unwrap_or_else(|e| e.into_inner()))?;
+        let sink = (*self.sink.lock().unwrap_or_else(|e| e.into_inner()))?;
+        Some(sink / source)
+    }
+}
+
+/// Measures one stream's actual sample rate from its callback timestamps with a delay-locked
+/// loop, publishing it to the [`DriftEstimator`] it was created with.
+#[derive(Debug)]
+pub struct DriftTracker {
+    /// Loop bandwidth in Hz.
+    bandwidth: f64,
+    /// Estimated seconds per frame.
+    frame_period: f64,
+    /// Timestamp the loop's times are measured from.
+    origin: Option<StreamInstant>,
+    /// Predicted time of the next callback's first frame, in seconds since `origin`.
+    predicted: f64,
+    /// Frames delivered by the last callback.
+    frames: u64,
+    /// Whether the loop has run at least once.
+    measured: bool,
+    published: PublishedRate,
+}
+
+impl DriftTracker {
+    fn new(sample_rate: SampleRate, bandwidth: f64) -> Self {
+        assert!(sample_rate > 0, "sample rate must be positive");
+        assert!(bandwidth > 0.0, "bandwidth must be positive");
+        Self {
+            bandwidth,
+            frame_period: 1.0 / f64::from(sample_rate),
+            origin: None,
+            predicted: 0.0,
+            frames: 0,
+            measured: false,
+            published: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Feeds the timestamp of a data callback delivering `frames` frames.
+    ///
+    /// Xruns, pauses and other gaps restart the timing without discarding the rate measured so
+    /// far.
+    pub fn update(&mut self, info: &CallbackInfo, frames: usize) {
+        self.update_at(info.timestamp().device, info.xrun(), frames as u64);
+    }
+
+    /// Returns the stream's measured sample rate, or `None` until it has delivered at least two
+    /// callbacks.
+    pub fn rate(&self) -> Option<f64> {
+        self.measured.then(|| 1.0 / self.frame_period)
+    }
+
+    fn update_at(&mut self, device: StreamInstant, xrun: bool, frames: u64) {
+        let Some(origin) = self.origin.filter(|_| !xrun && self.frames > 0) else {
+            self.restart(device, frames);
+            return;
+        };
+        let Some(elapsed) = device.checked_duration_since(origin) else {
+            self.restart(device, frames);
+            return;
+        };
+
+        let period = self.frames as f64 * self.frame_period;
+        let error = elapsed.as_secs_f64() - self.predicted;
+        if error.abs() > MAX_ERROR_PERIODS * period {
+            self.restart(device, frames);
+            return;
+        }
+
+        // Second-order loop, critically damped: see F. Adriaensen, "Using a DLL to filter time".
+        let omega = 2.0 * PI * self.bandwidth * period;
+        let now = self.predicted + SQRT_2 * omega * error;
+        self.frame_period += omega * omega * error / self.frames as f64;
+        self.predicted = now + self.frame_period * frames as f64;
+        self.frames = frames;
+        self.measured = true;
+
+        if let Ok(mut published) = self.published.try_lock() {
+            *published = Some(1.0 / self.frame_period);
+        }
+    }
+
+    /// Re-anchors the loop at `device`, keeping the rate estimate.
+    fn restart(&mut self, device: StreamInstant, frames: u64) {
+        self.origin = Some(device);
+        self.predicted = self.frame_period * frames as f64;
+        self.frames = frames;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Feeds `periods` callbacks of `frames` frames from a device running at `rate`, with the
+    /// timestamps jittered by up to `jitter` seconds.
+    fn run(tracker: &mut DriftTracker, rate: f64, frames: u64, periods: u64, jitter: f64) {
+        let start = StreamInstant::new(5, 0);
+        for period in 0..periods {
+            let ideal = (period * frames) as f64 / rate;
+            // Deterministic, zero-mean jitter.
+            let noise = jitter * ((period * 7919 % 200) as f64 / 100.0 - 1.0);
+            let at = start + Duration::from_secs_f64(ideal + jitter + noise);
+            tracker.update_at(at, false, frames);
+        }
+    }
+
+    #[test]
+    fn converges_on_the_drift_between_streams() {
+        let (estimator, mut source, mut sink) = DriftEstimator::new(48_000, 48_000, 0.5);
+        assert_eq!(estimator.ratio(), None);
+
+        // The source runs 50 ppm fast, the sink is exact; one minute of 256-frame periods.
+        run(&mut source, 48_000.0 * (1.0 + 50e-6), 256, 11_250, 200e-6);
+        run(&mut sink, 48_000.0, 256, 11_250, 200e-6);
+
+        let ratio = estimator.ratio().unwrap();
+        let expected = 1.0 / (1.0 + 50e-6);
+        assert!(
+            (ratio - expected).abs() < 2e-6,
+            "ratio {ratio} too far from {expected}"
+        );
+    }
+
+    #[test]
+    fn gaps_restart_timing_but_keep_the_rate() {
+        let (_, mut tracker, _) = DriftEstimator::new(48_000, 48_000, 0.5);
+        tracker.update_at(StreamInstant::ZERO, false, 480);
+        assert_eq!(tracker.rate(), None);
+        run(&mut tracker, 48_100.0, 480, 2_000, 0.0);
+        let rate = tracker.rate().unwrap();
+        assert!((rate - 48_100.0).abs() < 0.5, "rate {rate}");
+
+        // Resuming after a pause, far from where the loop predicted.
+        tracker.update_at(StreamInstant::new(1_000, 0), false, 480);
+        assert_eq!(tracker.rate(), Some(rate));
+        tracker.update_at(StreamInstant::new(1_000, 10_000_000), true, 480);
+        assert_eq!(tracker.rate(), Some(rate));
+    }
+}
@@ -2,6 +2,8 @@ use std::{
     borrow::Cow,
     error::Error as StdError,
     fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
+    sync::Arc,
 };
 
 /// A list specifying general categories of CPAL error.
@@ -19,7 +21,12 @@ pub enum ErrorKind {
     /// it. A stream built from a specific device does not follow a replacement; if that device
     /// disappears, it reports [`DeviceNotAvailable`] instead.
     ///
+    /// Changes to the stream's sample rate, channel count or buffer size are described by a
+    /// [`ConfigChange`] passed to the listener set with [`StreamTrait::set_config_listener`].
+    ///
     /// [`DeviceNotAvailable`]: ErrorKind::DeviceNotAvailable
+    /// [`ConfigChange`]: crate::ConfigChange
+    /// [`StreamTrait::set_config_listener`]: crate::traits::StreamTrait::set_config_listener
     DeviceChanged,
 
     /// The requested audio device is not available.
@@ -74,6 +81,15 @@ pub enum ErrorKind {
     /// not implemented by the backend.
     UnsupportedOperation,
 
+    /// Audio was lost to a buffer overrun or underrun, or to the system suspending the device,
+    /// and the stream recovered. The stream remains active and no rebuild is required.
+    ///
+    /// Only reported for streams whose [`XrunPolicy`] asks for [`XrunRecovery::Notify`].
+    ///
+    /// [`XrunPolicy`]: crate::XrunPolicy
+    /// [`XrunRecovery::Notify`]: crate::XrunRecovery::Notify
+    Xrun,
+
     /// The underlying platform audio API returned an error that CPAL cannot map to a more
     /// specific error kind.
     BackendError,
@@ -121,6 +137,9 @@ impl Display for ErrorKind {
                 "The requested stream configuration is not supported by the device.",
             ),
             Self::UnsupportedOperation => f.write_str("The requested operation is not supported."),
+            Self::Xrun => f.write_str(
+                "Audio was lost to a buffer overrun or underrun. The stream recovered and keeps running.",
+            ),
             Self::BackendError => f.write_str(
                 "The audio backend returned an unclassified error.",
             ),
@@ -130,10 +149,16 @@ impl Display for ErrorKind {
 }
 
 /// Error type for all CPAL operations.
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+///
+/// Errors converted from a backend keep the backend's error as their
+/// [`source`](StdError::source) and, where it has one, its numeric code as the
+/// [`backend_code`](Self::backend_code). Comparing and hashing errors ignores the source.
+#[derive(Clone, Debug)]
 pub struct Error {
     kind: ErrorKind,
     message: Option<Cow<'static, str>>,
+    source: Option<Arc<dyn StdError + Send + Sync + 'static>>,
+    backend_code: Option<i64>,
 }
 
 impl Error {
@@ -142,6 +167,8 @@ impl Error {
         Self {
             kind,
             message: None,
+            source: None,
+            backend_code: None,
         }
     }
 
@@ -150,9 +177,25 @@ impl Error {
         Self {
             kind,
             message: Some(message.into()),
+            source: None,
+            backend_code: None,
         }
     }
 
+    /// Attaches the underlying error that caused this one, returned by
+    /// [`source`](StdError::source).
+    pub fn with_source(mut self, source: impl StdError + Send + Sync + 'static) -> Self {
+        self.source = Some(Arc::new(source));
+        self
+    }
+
+    /// Attaches the backend's numeric code for the failure, returned by
+    /// [`backend_code`](Self::backend_code).
+    pub fn with_backend_code(mut self, code: impl Into<i64>) -> Self {
+        self.backend_code = Some(code.into());
+        self
+    }
+
     /// Returns the error kind.
     pub fn kind(&self) -> ErrorKind {
         self.kind
@@ -162,6 +205,40 @@ impl Error {
     pub fn message(&self) -> Option<&str> {
         self.message.as_deref()
     }
+
+    /// Returns the backend's numeric code for the failure, if it has one, for grouping failures
+    /// without parsing messages.
+    ///
+    /// Codes are only meaningful together with the host that produced them:
+    ///
+    /// | Host | Code |
+    /// | ---- | ---- |
+    /// | ALSA | `errno` |
+    /// | CoreAudio | `OSStatus` |
+    /// | JACK | `jack_status_t` bits, for client errors |
+    /// | PulseAudio | `PA_ERR_*` value for server errors, or `errno` for I/O errors |
+    /// | WASAPI | `HRESULT` |
+    pub fn backend_code(&self) -> Option<i64> {
+        self.backend_code
+    }
+}
+
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.message == other.message
+            && self.backend_code == other.backend_code
+    }
+}
+
+impl Eq for Error {}
+
+impl Hash for Error {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+        self.message.hash(state);
+        self.backend_code.hash(state);
+    }
 }
 
 impl Display for Error {
@@ -173,7 +250,13 @@ impl Display for Error {
     }
 }
 
-impl StdError for Error {}
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn StdError + 'static))
+    }
+}
 
 impl From<ErrorKind> for Error {
     fn from(kind: ErrorKind) -> Self {
@@ -210,15 +293,78 @@ impl From<audio_thread_priority::AudioThreadPriorityError> for Error {
 #[allow(dead_code)]
 pub(crate) trait ResultExt<T> {
     /// Converts the error via [`Into<cpal::Error>`] and prepends `msg`, yielding
-    /// `"<msg>: <original error>"` as the message.
+    /// `"<msg>: <original error>"` as the message. The source and backend code are kept.
     fn context(self, msg: impl Display) -> Result<T, Error>;
 }
 
 impl<T, E: Into<Error>> ResultExt<T> for Result<T, E> {
     fn context(self, msg: impl Display) -> Result<T, Error> {
         self.map_err(|e| {
-            let e = e.into();
-            Error::with_message(e.kind(), format!("{msg}: {e}"))
+            let mut e = e.into();
+            e.message = Some(format!("{msg}: {e}").into());
+            e
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::hash_map::DefaultHasher, io};
+
+    use super::*;
+
+    fn hash_of(err: &Error) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        err.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn io_error() -> io::Error {
+        io::Error::new(io::ErrorKind::NotFound, "no such device")
+    }
+
+    #[test]
+    fn source_and_backend_code() {
+        let err = Error::with_message(ErrorKind::DeviceNotAvailable, "device unplugged")
+            .with_source(io_error())
+            .with_backend_code(-19);
+        assert_eq!(err.kind(), ErrorKind::DeviceNotAvailable);
+        assert_eq!(err.message(), Some("device unplugged"));
+        assert_eq!(err.backend_code(), Some(-19));
+        assert_eq!(err.to_string(), "device unplugged");
+
+        let source = err.source().expect("source attached");
+        assert_eq!(source.to_string(), "no such device");
+        let io = source.downcast_ref::<io::Error>().unwrap();
+        assert_eq!(io.kind(), io::ErrorKind::NotFound);
+
+        let plain = Error::new(ErrorKind::DeviceNotAvailable);
+        assert!(plain.source().is_none());
+        assert_eq!(plain.backend_code(), None);
+    }
+
+    #[test]
+    fn equality_and_hash_ignore_the_source() {
+        let plain = Error::with_message(ErrorKind::BackendError, "failed").with_backend_code(5);
+        let sourced = plain.clone().with_source(io_error());
+        assert_eq!(plain, sourced);
+        assert_eq!(hash_of(&plain), hash_of(&sourced));
+
+        // The code still tells errors apart.
+        let other_code =
+            Error::with_message(ErrorKind::BackendError, "failed").with_backend_code(6);
+        assert_ne!(plain, other_code);
+    }
+
+    #[test]
+    fn context_keeps_source_and_code() {
+        let result: Result<(), Error> = Err(Error::with_message(ErrorKind::DeviceBusy, "in use")
+            .with_source(io_error())
+            .with_backend_code(-16));
+        let err = result.context("Failed to open the device").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DeviceBusy);
+        assert_eq!(err.message(), Some("Failed to open the device: in use"));
+        assert_eq!(err.backend_code(), Some(-16));
+        assert_eq!(err.source().unwrap().to_string(), "no such device");
+    }
+}
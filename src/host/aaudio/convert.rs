@@ -58,7 +58,7 @@ pub fn input_stream_instant(stream: &ndk::audio::AudioStream, sample_rate: u32)
 impl From<ndk::audio::AudioError> for Error {
     fn from(error: ndk::audio::AudioError) -> Self {
         use ndk::audio::AudioError::*;
-        match error {
+        let converted = match error {
             Disconnected | Unavailable | NoService | InvalidHandle => {
                 Error::with_message(ErrorKind::DeviceNotAvailable, error.to_string())
             }
@@ -79,6 +79,7 @@ impl From<ndk::audio::AudioError> for Error {
                 Error::with_message(ErrorKind::UnsupportedOperation, error.to_string())
             }
             _ => Error::with_message(ErrorKind::BackendError, error.to_string()),
-        }
+        };
+        converted.with_source(error)
     }
 }
@@ -212,6 +212,7 @@ fn default_supported_configs() -> VecIntoIter<SupportedStreamConfigRange> {
                     channels: *channel_count as ChannelCount,
                     min_sample_rate: *sample_rate as SampleRate,
                     max_sample_rate: *sample_rate as SampleRate,
+                    sample_rates: None,
                     buffer_size,
                     sample_format: *sample_format,
                 });
@@ -257,6 +258,7 @@ fn device_supported_configs(device: &AudioDeviceInfo) -> VecIntoIter<SupportedSt
                     channels,
                     min_sample_rate: *sample_rate as SampleRate,
                     max_sample_rate: *sample_rate as SampleRate,
+                    sample_rates: None,
                     buffer_size,
                     sample_format: *format,
                 });
@@ -1,7 +1,7 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, ffi::CString, fs};
 
-use super::{Device, Host, alsa};
-use crate::{DeviceDirection, Error};
+use super::{Device, Host, alsa, canonical_pcm_id};
+use crate::{DeviceDescriptionBuilder, DeviceDirection, Error};
 
 const HW_PREFIX: &str = "hw";
 const PLUGHW_PREFIX: &str = "plughw";
@@ -147,6 +147,64 @@ fn physical_devices() -> Vec<PhysicalDevice> {
     devices
 }
 
+/// Adds the attributes identifying the card behind `pcm_id` that survive re-enumeration: the
+/// PCM name keyed by card ID rather than index, and for USB cards the vendor, product and
+/// serial number. PCMs that do not name a card, such as `default`, are left as they are.
+pub(super) fn with_card_identity(
+    mut builder: DeviceDescriptionBuilder,
+    pcm_id: &str,
+) -> DeviceDescriptionBuilder {
+    let pcm_id = canonical_pcm_id(pcm_id);
+    let Some((prefix, params)) = pcm_id.split_once(':') else {
+        return builder;
+    };
+    let Some(card) = params
+        .split(',')
+        .find_map(|param| param.trim().strip_prefix("CARD="))
+    else {
+        return builder;
+    };
+    // Accepts either the card's index or its ID.
+    let Some(index) = CString::new(card)
+        .ok()
+        .and_then(|card| alsa::card::Card::from_str(&card).ok())
+        .map(|card| card.get_index())
+    else {
+        return builder;
+    };
+    let card_id = alsa::Ctl::new(&format!("{HW_PREFIX}:{index}"), false)
+        .ok()
+        .and_then(|ctl| {
+            ctl.card_info()
+                .ok()
+                .and_then(|info| info.get_id().ok().map(str::to_owned))
+        });
+    if let Some(card_id) = card_id {
+        let params = params.replacen(&format!("CARD={card}"), &format!("CARD={card_id}"), 1);
+        builder = builder.stable_name(format!("{prefix}:{params}"));
+    }
+
+    // USB cards only, e.g. "1235:8210".
+    let usb_id = fs::read_to_string(format!("/proc/asound/card{index}/usbid")).ok();
+    if let Some((vendor, product)) = usb_id.as_deref().and_then(|id| id.trim().split_once(':')) {
+        if let (Ok(vendor), Ok(product)) = (
+            u16::from_str_radix(vendor, 16),
+            u16::from_str_radix(product, 16),
+        ) {
+            builder = builder.vendor_id(vendor).product_id(product);
+        }
+        // The card's sysfs device is the USB interface; the serial belongs to its parent.
+        let serial = fs::canonicalize(format!("/sys/class/sound/card{index}/device"))
+            .ok()
+            .and_then(|interface| fs::read_to_string(interface.parent()?.join("serial")).ok());
+        if let Some(serial) = serial.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            builder = builder.serial_number(serial);
+        }
+    }
+
+    builder
+}
+
 impl From<alsa::Direction> for DeviceDirection {
     fn from(direction: alsa::Direction) -> Self {
         match direction {
@@ -22,18 +22,23 @@ use std::{
 use self::alsa::poll::Descriptors;
 pub use self::enumerate::Devices;
 use crate::{
-    BufferSize, COMMON_SAMPLE_RATES, CallbackInfo, ChannelCount, Data, DeviceDescription,
-    DeviceDescriptionBuilder, DeviceDirection, DeviceId, Error, ErrorKind, FrameCount,
-    SampleFormat, SampleRate, StreamConfig, StreamInstant, StreamTimestamp, SupportedBufferSize,
-    SupportedStreamConfig, SupportedStreamConfigRange,
+    ALaw, AlsaStreamOptions, BufferSize, COMMON_SAMPLE_RATES, CallbackInfo, ChannelCount,
+    ClockMapping, Data, DeviceDescription, DeviceDescriptionBuilder, DeviceDirection, DeviceId,
+    Error, ErrorKind, FrameCount, MuLaw, SampleFormat, SampleRate, StreamConfig, StreamInstant,
+    StreamOptions, StreamPosition, StreamState, StreamTimestamp, SupportedBufferSize,
+    SupportedStreamConfig, SupportedStreamConfigRange, UnderrunFill, XrunPolicy, XrunRecovery,
     host::{
         Notify,
         equilibrium::{DSD_EQUILIBRIUM_BYTE, U8_EQUILIBRIUM_BYTE, fill_equilibrium},
+        fade::{self, Fader},
         frames_to_duration,
         latch::Latch,
     },
     iter::{SupportedInputConfigs, SupportedOutputConfigs},
+    position::PositionTracker,
+    stream_state::{StateListener, StateTracker},
     traits::{DeviceTrait, HostTrait, StreamTrait},
+    xrun::XrunPolicyCell,
 };
 
 mod enumerate;
@@ -264,6 +269,31 @@ impl DeviceTrait for Device {
         error_callback: E,
         timeout: Option<Duration>,
     ) -> Result<Self::Stream, Error>
+    where
+        D: FnMut(&Data, &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        let options = StreamOptions {
+            timeout,
+            ..Default::default()
+        };
+        self.build_input_stream_with_options(
+            conf,
+            sample_format,
+            &options,
+            data_callback,
+            error_callback,
+        )
+    }
+
+    fn build_input_stream_with_options<D, E>(
+        &self,
+        conf: StreamConfig,
+        sample_format: SampleFormat,
+        options: &StreamOptions,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, Error>
     where
         D: FnMut(&Data, &CallbackInfo) + Send + 'static,
         E: FnMut(Error) + Send + 'static,
@@ -272,12 +302,12 @@ impl DeviceTrait for Device {
         // can make it jump up enough to pull `capture` backward.
         let data_callback = crate::host::monotonic_input_callback(data_callback);
         let stream_inner =
-            self.build_stream_inner(conf, sample_format, alsa::Direction::Capture)?;
+            self.build_stream_inner(conf, sample_format, alsa::Direction::Capture, &options.alsa)?;
         let stream = Self::Stream::new_input(
             Arc::new(stream_inner),
             data_callback,
             error_callback,
-            timeout,
+            options.timeout,
         );
         Ok(stream)
     }
@@ -294,16 +324,50 @@ impl DeviceTrait for Device {
         D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
         E: FnMut(Error) + Send + 'static,
     {
+        let options = StreamOptions {
+            timeout,
+            ..Default::default()
+        };
+        self.build_output_stream_with_options(
+            conf,
+            sample_format,
+            &options,
+            data_callback,
+            error_callback,
+        )
+    }
+
+    fn build_output_stream_with_options<D, E>(
+        &self,
+        conf: StreamConfig,
+        sample_format: SampleFormat,
+        options: &StreamOptions,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, Error>
+    where
+        D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        let fader = Arc::new(Fader::new(conf.channels, conf.sample_rate));
         // Keep `playback` monotonic: avail_delay() varies between cycles, and a playback
         // underrun can drain the buffer enough to pull `playback` backward.
-        let data_callback = crate::host::monotonic_output_callback(data_callback);
-        let stream_inner =
-            self.build_stream_inner(conf, sample_format, alsa::Direction::Playback)?;
+        let data_callback = crate::host::monotonic_output_callback(fade::output_callback(
+            fader.clone(),
+            data_callback,
+        ));
+        let stream_inner = self.build_stream_inner(
+            conf,
+            sample_format,
+            alsa::Direction::Playback,
+            &options.alsa,
+        )?;
         let stream = Self::Stream::new_output(
             Arc::new(stream_inner),
+            fader,
             data_callback,
             error_callback,
-            timeout,
+            options.timeout,
         );
         Ok(stream)
     }
@@ -401,12 +465,23 @@ impl Device {
         conf: StreamConfig,
         sample_format: SampleFormat,
         stream_type: alsa::Direction,
+        options: &AlsaStreamOptions,
     ) -> Result<StreamInner, Error> {
         crate::validate_stream_config(&conf)?;
+        let periods = match options.periods {
+            None => DEFAULT_PERIODS,
+            Some(periods) if periods >= 2 => periods as alsa::pcm::Frames,
+            Some(periods) => {
+                return Err(Error::with_message(
+                    ErrorKind::InvalidInput,
+                    format!("{periods} periods requested; at least 2 are required"),
+                ));
+            }
+        };
 
         let handle = open_pcm(&self.pcm_id, stream_type)?;
 
-        let hw_params = set_hw_params_from_format(&handle, conf, sample_format)?;
+        let hw_params = set_hw_params_from_format(&handle, conf, sample_format, periods)?;
         let (buffer_size, period_size) = set_sw_params_from_format(&handle, stream_type)?;
         if buffer_size == 0 || period_size == 0 {
             return Err(ErrorKind::DeviceNotAvailable.into());
@@ -429,6 +504,12 @@ impl Device {
             TimestampMode::SystemClock
         };
         drop(hw_params);
+        let tstamp_clock = match handle.sw_params_current()?.get_tstamp_type()? {
+            #[cfg(target_os = "linux")]
+            alsa::pcm::TstampType::MonotonicRaw => libc::CLOCK_MONOTONIC_RAW,
+            alsa::pcm::TstampType::Gettimeofday => libc::CLOCK_REALTIME,
+            _ => libc::CLOCK_MONOTONIC,
+        };
 
         let period_size = period_size as usize;
         let frame_size = sample_format.sample_size() * conf.channels as usize;
@@ -448,8 +529,12 @@ impl Device {
             equilibrium: EquilibriumFill::new(sample_format, period_size * frame_size),
             timestamp_mode,
             creation_ts,
+            tstamp_clock,
             creation_instant: std::time::Instant::now(),
             pending_xrun: AtomicBool::new(false),
+            xrun_policy: XrunPolicyCell::default(),
+            state: StateTracker::new(),
+            position: PositionTracker::new(conf.sample_rate),
             _context: self._context.clone(),
         };
 
@@ -471,7 +556,7 @@ impl Device {
             builder = builder.extended(desc.lines().map(|l| l.trim()).filter(|l| !l.is_empty()));
         }
 
-        Ok(builder.build())
+        Ok(enumerate::with_card_identity(builder, &self.pcm_id).build())
     }
 
     fn id(&self) -> Result<DeviceId, Error> {
@@ -489,7 +574,7 @@ impl Device {
         // Test both LE and BE formats to detect what the hardware actually supports.
         // LE is listed first as it's the common case for most audio hardware.
         // Hardware reports its supported formats regardless of CPU endianness.
-        const FORMATS: [(SampleFormat, alsa::pcm::Format); 23] = [
+        const FORMATS: [(SampleFormat, alsa::pcm::Format); 25] = [
             (SampleFormat::I8, alsa::pcm::Format::S8),
             (SampleFormat::U8, alsa::pcm::Format::U8),
             (SampleFormat::I16, alsa::pcm::Format::S16LE),
@@ -513,10 +598,10 @@ impl Device {
             (SampleFormat::DsdU16, alsa::pcm::Format::DSDU16BE),
             (SampleFormat::DsdU32, alsa::pcm::Format::DSDU32LE),
             (SampleFormat::DsdU32, alsa::pcm::Format::DSDU32BE),
+            (SampleFormat::MuLaw, alsa::pcm::Format::MuLaw),
+            (SampleFormat::ALaw, alsa::pcm::Format::ALaw),
             //SND_PCM_FORMAT_IEC958_SUBFRAME_LE,
             //SND_PCM_FORMAT_IEC958_SUBFRAME_BE,
-            //SND_PCM_FORMAT_MU_LAW,
-            //SND_PCM_FORMAT_A_LAW,
             //SND_PCM_FORMAT_IMA_ADPCM,
             //SND_PCM_FORMAT_MPEG,
             //SND_PCM_FORMAT_GSM,
@@ -538,9 +623,11 @@ impl Device {
         let min_rate = hw_params.get_rate_min()?;
         let max_rate = hw_params.get_rate_max()?;
 
-        let sample_rates = if min_rate == max_rate || hw_params.test_rate(min_rate + 1).is_ok() {
+        let sample_rates: Option<Arc<[SampleRate]>> = if min_rate == max_rate
+            || hw_params.test_rate(min_rate + 1).is_ok()
+        {
             // Fixed rate or continuous range.
-            vec![(min_rate, max_rate)]
+            None
         } else {
             // Discrete rates: probe the standard list plus the hardware's own min and max so
             // that rates outside `COMMON_SAMPLE_RATES` are not missed.
@@ -549,11 +636,15 @@ impl Device {
             probe.push(max_rate);
             probe.sort_unstable();
             probe.dedup();
-            probe
-                .into_iter()
-                .filter(|&r| (min_rate..=max_rate).contains(&r) && hw_params.test_rate(r).is_ok())
-                .map(|r| (r, r))
-                .collect()
+            probe.retain(|&r| (min_rate..=max_rate).contains(&r) && hw_params.test_rate(r).is_ok());
+            if probe.is_empty() {
+                return Ok(Vec::new().into_iter());
+            }
+            Some(probe.into())
+        };
+        let (min_rate, max_rate) = match sample_rates.as_deref() {
+            Some(rates) => (rates[0], rates[rates.len() - 1]),
+            None => (min_rate, max_rate),
         };
 
         let min_channels = hw_params.get_channels_min()?;
@@ -576,8 +667,7 @@ impl Device {
                     .collect()
             };
 
-        let mut output =
-            Vec::with_capacity(FORMATS.len() * supported_channels.len() * sample_rates.len());
+        let mut output = Vec::with_capacity(FORMATS.len() * supported_channels.len());
         let mut seen_formats: Vec<SampleFormat> = Vec::with_capacity(FORMATS.len());
 
         // Key: (channels, physical width in bits) with 4 physical widths (8/16/32/64 bits)
@@ -597,18 +687,22 @@ impl Device {
                     *buffer_size_cache
                         .entry((channels, width))
                         .or_insert_with(|| {
-                            supported_period_size_range(&hw_params, alsa_format, channels)
+                            supported_period_size_range(
+                                &hw_params,
+                                alsa_format,
+                                channels,
+                                DEFAULT_PERIODS,
+                            )
                         });
 
-                for &(min_rate, max_rate) in sample_rates.iter() {
-                    output.push(SupportedStreamConfigRange {
-                        channels,
-                        min_sample_rate: min_rate,
-                        max_sample_rate: max_rate,
-                        buffer_size,
-                        sample_format,
-                    });
-                }
+                output.push(SupportedStreamConfigRange {
+                    channels,
+                    min_sample_rate: min_rate,
+                    max_sample_rate: max_rate,
+                    sample_rates: sample_rates.clone(),
+                    buffer_size,
+                    sample_format,
+                });
             }
         }
 
@@ -689,6 +783,10 @@ impl EquilibriumFill {
             Self::Byte(U8_EQUILIBRIUM_BYTE)
         } else if sample_format.is_dsd() {
             Self::Byte(DSD_EQUILIBRIUM_BYTE)
+        } else if sample_format == SampleFormat::ALaw {
+            Self::Byte(ALaw::EQUILIBRIUM.0)
+        } else if sample_format == SampleFormat::MuLaw {
+            Self::Byte(MuLaw::EQUILIBRIUM.0)
         } else {
             // Multi-byte unsigned integer formats require a fill equal to the midpoint of their
             // range.
@@ -764,6 +862,9 @@ struct StreamInner {
     // Used as the creation-time anchor for SystemClock and AudioLink calculations.
     creation_ts: alsa::timespec,
 
+    // Clock the kernel takes htstamp values from.
+    tstamp_clock: libc::clockid_t,
+
     // Monotonic instant captured at stream creation. Timestamp origin for CreationInstant
     // mode and last-resort fallback if the status query in now() fails.
     creation_instant: std::time::Instant,
@@ -771,6 +872,15 @@ struct StreamInner {
     // Xrun pending delivery to the data callback.
     pending_xrun: AtomicBool,
 
+    // How xruns and suspends are handled, set by StreamTrait::set_xrun_policy().
+    xrun_policy: XrunPolicyCell,
+
+    // Transport state reported by StreamTrait::state().
+    state: StateTracker,
+
+    // Device position reported by StreamTrait::position().
+    position: PositionTracker,
+
     // Keep ALSA context alive to prevent premature ALSA config cleanup.
     _context: Arc<AlsaContext>,
 }
@@ -796,6 +906,9 @@ pub struct Stream {
 
     /// Latch that blocks the worker thread until `play()` is called for the first time.
     latch: Latch,
+
+    /// Gain ramps around transport changes; `None` for input streams.
+    fader: Option<Arc<Fader>>,
 }
 
 impl StreamInner {
@@ -844,6 +957,25 @@ impl StreamInner {
         }
     }
 
+    // Queries the PCM status with the audio timestamp type matching the timestamp mode.
+    fn timestamp_status(&self) -> Result<alsa::pcm::Status, alsa::Error> {
+        let audio_ts_type = match self.timestamp_mode {
+            TimestampMode::AudioLink => alsa::pcm::AudioTstampType::LinkSynchronized,
+            TimestampMode::SystemClock | TimestampMode::CreationInstant => {
+                alsa::pcm::AudioTstampType::Compat
+            }
+        };
+        alsa::pcm::StatusBuilder::new()
+            .audio_htstamp_config(audio_ts_type, false)
+            .build(&self.handle)
+    }
+
+    // Reports the end of a suspend once the PCM has been resumed or is being re-prepared.
+    fn resume_state(&self) {
+        self.state
+            .replace(StreamState::Suspended, StreamState::Running);
+    }
+
     #[cfg(feature = "realtime")]
     fn is_rt_eligible(&self) -> bool {
         use alsa_sys::*;
@@ -923,7 +1055,12 @@ impl StreamInner {
 struct StreamWorkerContext {
     descriptors: Box<[libc::pollfd]>,
     transfer_buffer: Box<[u8]>,
+    // The last period an output stream handed to ALSA in full, kept for `UnderrunFill::RepeatLast`.
+    last_period: Box<[u8]>,
+    has_last_period: bool,
     poll_timeout: i32,
+    // Frames read from or written to the PCM since the worker started.
+    frames_transferred: u64,
 }
 
 impl StreamWorkerContext {
@@ -938,6 +1075,7 @@ impl StreamWorkerContext {
 
         // Pre-allocate a period-sized working buffer. Contents are overwritten each callback.
         let transfer_buffer = vec![0u8; stream.period_size * stream.frame_size].into_boxed_slice();
+        let last_period = transfer_buffer.clone();
 
         // Pre-allocate and initialize descriptors vector: 1 for self-pipe + ALSA descriptors.
         // The descriptor count is constant for the lifetime of stream parameters, and
@@ -971,7 +1109,10 @@ impl StreamWorkerContext {
         Self {
             descriptors,
             transfer_buffer,
+            last_period,
+            has_last_period: false,
             poll_timeout,
+            frames_transferred: 0,
         }
     }
 }
@@ -1011,6 +1152,7 @@ fn input_stream_worker(
             }) => process_input(
                 stream,
                 &mut ctxt.transfer_buffer,
+                &mut ctxt.frames_transferred,
                 status,
                 delay_frames,
                 data_callback,
@@ -1019,7 +1161,8 @@ fn input_stream_worker(
         };
         if let Err(err) = result {
             match err.kind() {
-                ErrorKind::DeviceNotAvailable => {
+                ErrorKind::DeviceNotAvailable | ErrorKind::StreamInvalidated => {
+                    stream.state.set(StreamState::Invalidated);
                     error_callback(err);
                     stream.signal_worker_exit();
                     return;
@@ -1059,22 +1202,17 @@ fn output_stream_worker(
         }
         let result = match poll_for_period(&rx, stream, &mut ctxt) {
             Ok(Poll::Pending) => continue,
-            Ok(Poll::Recover) => recover_output(stream),
+            Ok(Poll::Recover) => recover_output(stream, &mut ctxt),
             Ok(Poll::Ready {
                 status,
                 delay_frames,
-            }) => process_output(
-                stream,
-                &mut ctxt.transfer_buffer,
-                status,
-                delay_frames,
-                data_callback,
-            ),
+            }) => process_output(stream, &mut ctxt, status, delay_frames, data_callback),
             Err(err) => Err(err),
         };
         if let Err(err) = result {
             match err.kind() {
-                ErrorKind::DeviceNotAvailable => {
+                ErrorKind::DeviceNotAvailable | ErrorKind::StreamInvalidated => {
+                    stream.state.set(StreamState::Invalidated);
                     error_callback(err);
                     stream.signal_worker_exit();
                     return;
@@ -1087,12 +1225,17 @@ fn output_stream_worker(
 
 /// Attempt hardware resume from a suspend event (`ESTRPIPE`).
 fn try_resume(stream: &StreamInner) -> Result<Poll, Error> {
+    let recovery = check_recovery(stream, "Device suspended by the system")?;
     let handle = &stream.handle;
+    stream
+        .state
+        .replace(StreamState::Running, StreamState::Suspended);
 
     let hw_params = handle.hw_params_current()?;
     if !hw_params.can_resume() {
         // Hardware doesn't support suspend/resume: fall back to full recovery.
         stream.pending_xrun.store(true, Ordering::Relaxed);
+        stream.resume_state();
         return Ok(Poll::Recover);
     }
 
@@ -1112,6 +1255,8 @@ fn try_resume(stream: &StreamInner) -> Result<Poll, Error> {
                     }
                 }
             }
+            stream.resume_state();
+            notify_recovery(recovery, "Device resumed after a system suspend")?;
             Ok(Poll::Pending)
         }
         // device is still resuming; poll again until it is ready.
@@ -1119,6 +1264,7 @@ fn try_resume(stream: &StreamInner) -> Result<Poll, Error> {
         // hardware does not support soft resume: fall back to full recovery.
         Err(e) if e.errno() == libc::ENOSYS => {
             stream.pending_xrun.store(true, Ordering::Relaxed);
+            stream.resume_state();
             Ok(Poll::Recover)
         }
         Err(e) => Err(e.into()),
@@ -1217,17 +1363,9 @@ fn poll_for_period(
         return Ok(Poll::Pending);
     }
 
-    let audio_ts_type = match stream.timestamp_mode {
-        TimestampMode::AudioLink => alsa::pcm::AudioTstampType::LinkSynchronized,
-        TimestampMode::SystemClock | TimestampMode::CreationInstant => {
-            alsa::pcm::AudioTstampType::Compat
-        }
-    };
     // From the guard above we know that this poll is not a spurious wakeup,
     // so we also know we can query the device in a stable state.
-    let status = alsa::pcm::StatusBuilder::new()
-        .audio_htstamp_config(audio_ts_type, false)
-        .build(&stream.handle)?;
+    let status = stream.timestamp_status()?;
 
     Ok(Poll::Ready {
         status,
@@ -1235,18 +1373,38 @@ fn poll_for_period(
     })
 }
 
-// Full input underrun recovery: mark the xrun, then prepare + start the stream.
+// Refuses to recover from an xrun or suspend when the policy asks to stop the stream instead;
+// the worker exits on the `StreamInvalidated` error.
+fn check_recovery(stream: &StreamInner, cause: &'static str) -> Result<XrunRecovery, Error> {
+    match stream.xrun_policy.get().recovery {
+        XrunRecovery::Stop => Err(Error::with_message(ErrorKind::StreamInvalidated, cause)),
+        recovery => Ok(recovery),
+    }
+}
+
+// Reports a completed recovery to the error callback when the policy asks for it. The worker
+// forwards the `Xrun` error and keeps running.
+fn notify_recovery(recovery: XrunRecovery, cause: &'static str) -> Result<(), Error> {
+    match recovery {
+        XrunRecovery::Notify => Err(Error::with_message(ErrorKind::Xrun, cause)),
+        _ => Ok(()),
+    }
+}
+
+// Full input overrun recovery: mark the xrun, then prepare + start the stream.
 fn recover_input(stream: &StreamInner) -> Result<(), Error> {
     stream.pending_xrun.store(true, Ordering::Relaxed);
+    let recovery = check_recovery(stream, "Buffer overrun")?;
     stream.handle.prepare()?;
     stream.handle.start()?;
-    Ok(())
+    notify_recovery(recovery, "Buffer overrun")
 }
 
 // Read input data from ALSA and deliver it to the user.
 fn process_input(
     stream: &StreamInner,
     buffer: &mut [u8],
+    frames_transferred: &mut u64,
     status: alsa::pcm::Status,
     delay_frames: usize,
     data_callback: &mut (dyn FnMut(&Data, &CallbackInfo) + Send + 'static),
@@ -1278,10 +1436,18 @@ fn process_input(
             Err(err) => return Err(err.into()),
         }
     }
+    let callback_instant = stream.callback_instant(&status);
+    // Frames still waiting to be read were captured before the status was taken.
+    stream.position.report(
+        *frames_transferred + delay_frames as u64,
+        callback_instant,
+        u64::MAX,
+    );
+    *frames_transferred += frames_read as u64;
+
     if !stream.draining.load(Ordering::Relaxed) {
         let data = buffer.as_mut_ptr() as *mut ();
         let data = unsafe { Data::from_parts(data, stream.period_samples, stream.sample_format) };
-        let callback_instant = stream.callback_instant(&status);
         let delay_duration = frames_to_duration(delay_frames as FrameCount, stream.sample_rate);
         let capture = callback_instant
             .checked_sub(delay_duration)
@@ -1301,27 +1467,55 @@ fn process_input(
 // Full output underrun recovery: mark the xrun, then prepare the stream. No need to call
 // start(): ALSA automatically restarts output streams once the buffer is refilled and
 // triggered again.
-fn recover_output(stream: &StreamInner) -> Result<(), Error> {
+//
+// The last period handed to ALSA in full is queued again when the policy asks to repeat it.
+// Otherwise the device restarts with whatever the data callback writes next. Either way, the
+// unwritten rest of an interrupted period is dropped.
+fn recover_output(stream: &StreamInner, ctxt: &mut StreamWorkerContext) -> Result<(), Error> {
     stream.pending_xrun.store(true, Ordering::Relaxed);
+    let policy = stream.xrun_policy.get();
+    let recovery = check_recovery(stream, "Buffer underrun")?;
     stream.handle.prepare()?;
-    Ok(())
+
+    // A draining stream must not play its last period twice.
+    if policy.underrun_fill == UnderrunFill::RepeatLast
+        && ctxt.has_last_period
+        && !stream.draining.load(Ordering::Relaxed)
+    {
+        let mut frames_written = 0;
+        while frames_written < stream.period_size {
+            match stream
+                .handle
+                .io_bytes()
+                .writei(&ctxt.last_period[frames_written * stream.frame_size..])
+            {
+                Ok(n) => frames_written += n,
+                Err(err) if err.errno() == libc::EAGAIN => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        ctxt.frames_transferred += frames_written as u64;
+    }
+
+    notify_recovery(recovery, "Buffer underrun")
 }
 
 fn process_output(
     stream: &StreamInner,
-    buffer: &mut [u8],
+    ctxt: &mut StreamWorkerContext,
     status: alsa::pcm::Status,
     delay_frames: usize,
     data_callback: &mut (dyn FnMut(&mut Data, &CallbackInfo) + Send + 'static),
 ) -> Result<(), Error> {
+    let buffer = &mut ctxt.transfer_buffer;
     // Pre-fill buffer with equilibrium; user callback overwrites what it wants.
     stream.equilibrium.fill(buffer);
 
+    let callback_instant = stream.callback_instant(&status);
     if !stream.draining.load(Ordering::Relaxed) {
         let data = buffer.as_mut_ptr() as *mut ();
         let mut data =
             unsafe { Data::from_parts(data, stream.period_samples, stream.sample_format) };
-        let callback_instant = stream.callback_instant(&status);
         let delay_duration = frames_to_duration(delay_frames as FrameCount, stream.sample_rate);
         let playback = callback_instant + delay_duration;
         let timestamp = StreamTimestamp {
@@ -1348,17 +1542,28 @@ fn process_output(
             // hardware doesn't support it. BSD compat: check via PCM state rather than the Linux-specific ESTRPIPE errno.
             Err(_) if matches!(stream.handle.state(), alsa::pcm::State::Suspended) => {
                 return match try_resume(stream)? {
-                    Poll::Recover => recover_output(stream),
+                    Poll::Recover => recover_output(stream, ctxt),
                     _ => Ok(()),
                 };
             }
             // EAGAIN with partial progress, or EPIPE: full underrun recovery required.
             Err(err) if err.errno() == libc::EAGAIN || err.errno() == libc::EPIPE => {
-                return recover_output(stream);
+                return recover_output(stream, ctxt);
             }
             Err(err) => return Err(err.into()),
         }
     }
+    if stream.xrun_policy.get().underrun_fill == UnderrunFill::RepeatLast {
+        ctxt.last_period.copy_from_slice(buffer);
+        ctxt.has_last_period = true;
+    }
+    // Frames still queued when the status was taken have not been played yet.
+    stream.position.report(
+        ctxt.frames_transferred.saturating_sub(delay_frames as u64),
+        callback_instant,
+        ctxt.frames_transferred + frames_written as u64,
+    );
+    ctxt.frames_transferred += frames_written as u64;
     Ok(())
 }
 
@@ -1435,11 +1640,13 @@ impl Stream {
             trigger: tx,
             _rx: rx,
             latch,
+            fader: None,
         }
     }
 
     fn new_output<D, E>(
         inner: Arc<StreamInner>,
+        fader: Arc<Fader>,
         mut data_callback: D,
         mut error_callback: E,
         timeout: Option<Duration>,
@@ -1478,6 +1685,7 @@ impl Stream {
             trigger: tx,
             _rx: rx,
             latch,
+            fader: Some(fader),
         }
     }
 
@@ -1508,11 +1716,23 @@ impl Stream {
     fn discard_pcm(&self) -> Result<(), Error> {
         self.park_worker();
         let result = if self.inner.handle.state() != alsa::pcm::State::Setup {
-            self.inner
+            // Dropped playback is never played; dropped capture was recorded but is never read.
+            // `Frames` is a `c_long`, narrower than `i64` on 32-bit targets.
+            #[allow(clippy::unnecessary_cast)]
+            let queued = self.inner.handle.delay().unwrap_or(0).max(0) as i64;
+            let result = self
+                .inner
                 .handle
                 .drop()
                 .and_then(|_| self.inner.handle.prepare())
-                .map_err(Error::from)
+                .map_err(Error::from);
+            if result.is_ok() {
+                self.inner.position.offset(match self.inner.direction {
+                    DeviceDirection::Output => -queued,
+                    _ => queued,
+                });
+            }
+            result
         } else {
             Ok(())
         };
@@ -1602,6 +1822,9 @@ impl Stream {
 
 impl Drop for Stream {
     fn drop(&mut self) {
+        if let Some(fader) = &self.fader {
+            fader.fade_out(true, None);
+        }
         // Unblock the worker in case the stream is dropped before start() was called.
         // Idempotent: no effect if the worker is already running.
         self.latch.release();
@@ -1634,38 +1857,78 @@ impl StreamTrait for Stream {
             }
             _ => {}
         }
+        if let Some(fader) = &self.fader {
+            fader.fade_in();
+        }
+        self.inner.state.set(StreamState::Running);
         Ok(())
     }
 
     fn pause(&self) -> Result<(), Error> {
+        if let Some(fader) = &self.fader {
+            fader.fade_out(true, None);
+        }
         self.inner.draining.store(true, Ordering::Relaxed);
-        self.suspend_pcm()
+        self.suspend_pcm()?;
+        self.inner.state.set(StreamState::Paused);
+        Ok(())
     }
 
     fn stop(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        // The ramp is drained with the rest of the buffered audio, so only wait for it to be
+        // written.
+        let spent = self
+            .fader
+            .as_ref()
+            .map_or(Duration::ZERO, |fader| fader.fade_out(false, timeout));
+        let timeout = timeout.map(|timeout| timeout.saturating_sub(spent));
         self.inner.draining.store(true, Ordering::Relaxed);
 
         if self.inner.direction != DeviceDirection::Output {
             // Unlike pause(), stop() discards rather than preserves buffered samples.
-            return self.discard_pcm();
+            self.discard_pcm()?;
+            self.inner.state.set(StreamState::Paused);
+            return Ok(());
         }
 
         self.park_worker();
+        if timeout != Some(Duration::ZERO) {
+            self.inner.state.set(StreamState::Draining);
+        }
         let result = self.drain_output(timeout);
         self.inner.unpark_worker();
+        self.inner.state.set(StreamState::Paused);
         result
     }
 
+    fn flush(&self) -> Result<(), Error> {
+        // Nothing has been queued before the worker is first released.
+        if !self.latch.is_released() {
+            return Ok(());
+        }
+        // Cutting into queued audio clicks as well, so ramp out before and back in after.
+        let fader = self.fader.as_ref().filter(|fader| fader.is_running());
+        if let Some(fader) = fader {
+            fader.fade_out(true, None);
+        }
+        let result = self.discard_pcm();
+        if let Some(fader) = fader {
+            fader.fade_in();
+        }
+        result?;
+        // A running playback PCM restarts once the worker refills it, but capture must be
+        // restarted explicitly.
+        if self.inner.direction == DeviceDirection::Input
+            && !self.inner.draining.load(Ordering::Relaxed)
+        {
+            self.inner.handle.start()?;
+        }
+        Ok(())
+    }
+
     fn now(&self) -> StreamInstant {
         if self.inner.timestamp_mode != TimestampMode::CreationInstant {
-            let audio_ts_type = match self.inner.timestamp_mode {
-                TimestampMode::AudioLink => alsa::pcm::AudioTstampType::LinkSynchronized,
-                _ => alsa::pcm::AudioTstampType::Compat,
-            };
-            if let Ok(status) = alsa::pcm::StatusBuilder::new()
-                .audio_htstamp_config(audio_ts_type, false)
-                .build(&self.inner.handle)
-            {
+            if let Ok(status) = self.inner.timestamp_status() {
                 return self.inner.callback_instant(&status);
             }
         }
@@ -1674,15 +1937,62 @@ impl StreamTrait for Stream {
         StreamInstant::new(d.as_secs(), d.subsec_nanos())
     }
 
+    fn clock_mapping(&self) -> Result<ClockMapping, Error> {
+        if self.inner.timestamp_mode == TimestampMode::CreationInstant {
+            return Ok(ClockMapping::capture(|| self.now()));
+        }
+        // htstamp is the tstamp clock reading for the moment the status describes, which is what
+        // callback_instant() derives the stream instant from in either hardware mode.
+        let status = self.inner.timestamp_status()?;
+        let htstamp = status.get_htstamp();
+        let reading = Duration::from_nanos(timespec_to_nanos(htstamp).max(0) as u64);
+        Ok(ClockMapping::from_clock_reading(
+            self.inner.callback_instant(&status),
+            self.inner.tstamp_clock,
+            reading,
+        ))
+    }
+
     fn buffer_size(&self) -> Result<FrameCount, Error> {
         Ok(self.inner.period_size as FrameCount)
     }
+
+    fn position(&self) -> Result<StreamPosition, Error> {
+        let advancing = matches!(
+            self.inner.state.get(),
+            StreamState::Running | StreamState::Draining
+        );
+        Ok(self.inner.position.get(self.now(), advancing))
+    }
+
+    fn state(&self) -> Result<StreamState, Error> {
+        Ok(self.inner.state.get())
+    }
+
+    fn set_state_listener(&self, listener: StateListener) -> Result<(), Error> {
+        self.inner.state.set_listener(listener);
+        Ok(())
+    }
+
+    fn set_xrun_policy(&self, policy: XrunPolicy) -> Result<(), Error> {
+        self.inner.xrun_policy.set(policy);
+        Ok(())
+    }
+
+    fn set_fade(&self, fade: Duration) -> Result<(), Error> {
+        let fader = self.fader.as_ref().ok_or_else(|| {
+            Error::with_message(ErrorKind::UnsupportedOperation, "input streams do not fade")
+        })?;
+        fader.set_fade(fade);
+        Ok(())
+    }
 }
 
 fn supported_period_size_range(
     hw_params: &alsa::pcm::HwParams<'_>,
     alsa_format: alsa::pcm::Format,
     channels: ChannelCount,
+    periods: alsa::pcm::Frames,
 ) -> SupportedBufferSize {
     let p = hw_params.clone();
     if p.set_access(alsa::pcm::Access::RWInterleaved).is_err()
@@ -1695,10 +2005,11 @@ fn supported_period_size_range(
         return SupportedBufferSize::Unknown;
     };
     let min_frames = min.max(1);
-    // cpal double-buffers (ring = DEFAULT_PERIODS × period), so the achievable
-    // period maximum is also bounded by max_buffer / DEFAULT_PERIODS.
+    // The ring holds `periods` periods, so the achievable period maximum is also bounded by
+    // max_buffer / periods. Configurations are enumerated without stream options and use
+    // DEFAULT_PERIODS; set_hw_params_from_format enforces the bound for the periods asked for.
     let effective_max = match p.get_buffer_size_max() {
-        Ok(max_buf) if max_buf > 0 => max.min(max_buf / DEFAULT_PERIODS),
+        Ok(max_buf) if max_buf > 0 => max.min(max_buf / periods),
         _ => max,
     };
     if effective_max >= min_frames {
@@ -1789,6 +2100,8 @@ fn sample_format_to_alsa_format(
         #[cfg(target_endian = "big")]
         SampleFormat::F64 => (Format::Float64BE, Format::Float64LE),
         SampleFormat::DsdU8 => return Ok(Format::DSDU8),
+        SampleFormat::ALaw => return Ok(Format::ALaw),
+        SampleFormat::MuLaw => return Ok(Format::MuLaw),
         #[cfg(target_endian = "little")]
         SampleFormat::DsdU16 => (Format::DSDU16LE, Format::DSDU16BE),
         #[cfg(target_endian = "big")]
@@ -1825,12 +2138,14 @@ fn set_hw_params_from_format(
     pcm_handle: &alsa::pcm::PCM,
     config: StreamConfig,
     sample_format: SampleFormat,
+    periods: alsa::pcm::Frames,
 ) -> Result<alsa::pcm::HwParams<'_>, Error> {
     let hw_params = init_hw_params(pcm_handle, config, sample_format)?;
 
     // When BufferSize::Fixed(x) is specified, we configure double-buffering with
-    // buffer_size = 2x and period_size = x. This provides consistent low-latency
-    // behavior across different ALSA implementations and hardware.
+    // buffer_size = 2x and period_size = x, or as many periods as the stream options ask
+    // for. This provides consistent low-latency behavior across different ALSA
+    // implementations and hardware.
     if let BufferSize::Fixed(period_size) = config.buffer_size {
         let period_size = period_size as alsa::pcm::Frames;
 
@@ -1849,10 +2164,10 @@ fn set_hw_params_from_format(
             }
         }
 
-        let buffer_size = DEFAULT_PERIODS * period_size;
+        let buffer_size = periods * period_size;
         if let Ok(max_buffer) = hw_params.get_buffer_size_max() {
             if max_buffer > 0 && buffer_size > max_buffer {
-                let effective_max = max_buffer / DEFAULT_PERIODS;
+                let effective_max = max_buffer / periods;
                 return Err(Error::with_message(
                     ErrorKind::UnsupportedConfig,
                     format!(
@@ -1877,9 +2192,10 @@ fn set_hw_params_from_format(
             // Re-initialize hw_params to clear previous constraints
             let hw_params = init_hw_params(pcm_handle, config, sample_format)?;
 
-            // Set both period (to device's chosen value) and buffer (to 2 periods)
+            // Set both period (to device's chosen value) and buffer (to 2 periods, unless the
+            // stream options ask for more)
             hw_params.set_period_size_near(period_size, alsa::ValueOr::Nearest)?;
-            hw_params.set_buffer_size_near(DEFAULT_PERIODS * period_size)?;
+            hw_params.set_buffer_size_near(periods * period_size)?;
 
             // Re-apply with new constraints
             pcm_handle.hw_params(&hw_params)?;
@@ -1942,13 +2258,14 @@ fn canonical_pcm_id(pcm_id: &str) -> String {
 
 impl From<alsa::Error> for Error {
     fn from(err: alsa::Error) -> Self {
-        match err.errno() {
+        let error = match err.errno() {
             libc::ENODEV | libc::ENOENT | LIBC_ENOTSUPP => ErrorKind::DeviceNotAvailable.into(),
             libc::EPERM | libc::EACCES => ErrorKind::PermissionDenied.into(),
             libc::EBUSY | libc::EAGAIN => ErrorKind::DeviceBusy.into(),
             libc::EINVAL => ErrorKind::UnsupportedConfig.into(),
             libc::ENOSYS => ErrorKind::UnsupportedOperation.into(),
             _ => Error::with_message(ErrorKind::BackendError, err.to_string()),
-        }
+        };
+        error.with_backend_code(err.errno()).with_source(err)
     }
 }
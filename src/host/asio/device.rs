@@ -116,6 +116,7 @@ impl Device {
                     channels,
                     min_sample_rate: rate,
                     max_sample_rate: rate,
+                    sample_rates: None,
                     buffer_size: default.buffer_size,
                     sample_format: default.sample_format,
                 });
@@ -162,6 +162,7 @@ fn supported_configs() -> Vec<SupportedStreamConfigRange> {
                     channels,
                     min_sample_rate: rate,
                     max_sample_rate: rate,
+                    sample_rates: None,
                     buffer_size: supported_render_quantum_range(rate),
                     sample_format: SUPPORTED_SAMPLE_FORMAT,
                 })
@@ -533,6 +533,7 @@ fn get_supported_stream_configs(is_input: bool) -> std::vec::IntoIter<SupportedS
             channels,
             min_sample_rate: sample_rate,
             max_sample_rate: sample_rate,
+            sample_rates: None,
             buffer_size,
             sample_format: SUPPORTED_SAMPLE_FORMAT,
         })
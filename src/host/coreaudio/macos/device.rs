@@ -583,6 +583,7 @@ impl Device {
                     channels: n_channels as ChannelCount,
                     min_sample_rate: range.mMinimum as u32,
                     max_sample_rate: range.mMaximum as u32,
+                    sample_rates: None,
                     buffer_size,
                     sample_format,
                 })
@@ -88,7 +88,7 @@ impl From<coreaudio::Error> for Error {
     fn from(err: coreaudio::Error) -> Self {
         use coreaudio::error::{AudioCodecError, AudioError, AudioFormatError, AudioUnitError};
         let msg = format!("{err}");
-        match err {
+        let error = match err {
             coreaudio::Error::RenderCallbackBufferFormatDoesNotMatchAudioUnitStreamFormat
             | coreaudio::Error::NoKnownSubtype
             | coreaudio::Error::UnsupportedSampleRate
@@ -139,7 +139,8 @@ impl From<coreaudio::Error> for Error {
             }
 
             _ => Error::with_message(ErrorKind::BackendError, msg),
-        }
+        };
+        error.with_backend_code(err.as_os_status()).with_source(err)
     }
 }
 
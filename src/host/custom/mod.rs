@@ -7,8 +7,11 @@ use core::time::Duration;
 use std::fmt;
 
 use crate::{
-    CallbackInfo, Data, DeviceDescription, DeviceId, Error, ErrorKind, FrameCount, SampleFormat,
-    StreamConfig, StreamInstant, SupportedStreamConfig, SupportedStreamConfigRange,
+    CallbackInfo, ClockMapping, Data, DeviceDescription, DeviceId, Error, ErrorKind, FrameCount,
+    SampleFormat, StreamConfig, StreamInstant, StreamOptions, StreamPosition, StreamState,
+    SupportedStreamConfig, SupportedStreamConfigRange, XrunPolicy,
+    config_change::ConfigListener,
+    stream_state::StateListener,
     traits::{DeviceTrait, HostTrait, StreamTrait},
 };
 
@@ -180,21 +183,21 @@ trait DeviceErased: Send + Sync {
     fn supported_output_configs(&self) -> Result<SupportedConfigs, Error>;
     fn default_input_config(&self) -> Result<SupportedStreamConfig, Error>;
     fn default_output_config(&self) -> Result<SupportedStreamConfig, Error>;
-    fn build_input_stream_raw(
+    fn build_input_stream_with_options(
         &self,
         config: StreamConfig,
         sample_format: SampleFormat,
+        options: &StreamOptions,
         data_callback: InputCallback,
         error_callback: ErrorCallback,
-        timeout: Option<Duration>,
     ) -> Result<Stream, Error>;
-    fn build_output_stream_raw(
+    fn build_output_stream_with_options(
         &self,
         config: StreamConfig,
         sample_format: SampleFormat,
+        options: &StreamOptions,
         data_callback: OutputCallback,
         error_callback: ErrorCallback,
-        timeout: Option<Duration>,
     ) -> Result<Stream, Error>;
     // Required because `DeviceInner` is clone
     fn clone(&self) -> Device;
@@ -204,8 +207,17 @@ trait StreamErased: Send + Sync {
     fn start(&self) -> Result<(), Error>;
     fn pause(&self) -> Result<(), Error>;
     fn stop(&self, timeout: Option<Duration>) -> Result<(), Error>;
+    fn flush(&self) -> Result<(), Error>;
+    fn position(&self) -> Result<StreamPosition, Error>;
     fn now(&self) -> StreamInstant;
+    fn clock_mapping(&self) -> Result<ClockMapping, Error>;
     fn buffer_size(&self) -> Result<FrameCount, Error>;
+    fn set_buffer_size(&self, frames: FrameCount) -> Result<(), Error>;
+    fn state(&self) -> Result<StreamState, Error>;
+    fn set_state_listener(&self, listener: StateListener) -> Result<(), Error>;
+    fn set_xrun_policy(&self, policy: XrunPolicy) -> Result<(), Error>;
+    fn set_fade(&self, fade: Duration) -> Result<(), Error>;
+    fn set_config_listener(&self, listener: ConfigListener) -> Result<(), Error>;
 }
 
 fn device_to_erased(d: impl DeviceErased + 'static) -> Device {
@@ -282,40 +294,40 @@ where
         <T as DeviceTrait>::default_output_config(self)
     }
 
-    fn build_input_stream_raw(
+    fn build_input_stream_with_options(
         &self,
         config: StreamConfig,
         sample_format: SampleFormat,
+        options: &StreamOptions,
         data_callback: InputCallback,
         error_callback: ErrorCallback,
-        timeout: Option<Duration>,
     ) -> Result<Stream, Error> {
-        <T as DeviceTrait>::build_input_stream_raw(
+        <T as DeviceTrait>::build_input_stream_with_options(
             self,
             config,
             sample_format,
+            options,
             data_callback,
             error_callback,
-            timeout,
         )
         .map(stream_to_erased)
     }
 
-    fn build_output_stream_raw(
+    fn build_output_stream_with_options(
         &self,
         config: StreamConfig,
         sample_format: SampleFormat,
+        options: &StreamOptions,
         data_callback: OutputCallback,
         error_callback: ErrorCallback,
-        timeout: Option<Duration>,
     ) -> Result<Stream, Error> {
-        <T as DeviceTrait>::build_output_stream_raw(
+        <T as DeviceTrait>::build_output_stream_with_options(
             self,
             config,
             sample_format,
+            options,
             data_callback,
             error_callback,
-            timeout,
         )
         .map(stream_to_erased)
     }
@@ -341,13 +353,49 @@ where
         <T as StreamTrait>::stop(self, timeout)
     }
 
+    fn flush(&self) -> Result<(), Error> {
+        <T as StreamTrait>::flush(self)
+    }
+
+    fn position(&self) -> Result<StreamPosition, Error> {
+        <T as StreamTrait>::position(self)
+    }
+
     fn now(&self) -> StreamInstant {
         <T as StreamTrait>::now(self)
     }
 
+    fn clock_mapping(&self) -> Result<ClockMapping, Error> {
+        <T as StreamTrait>::clock_mapping(self)
+    }
+
     fn buffer_size(&self) -> Result<FrameCount, Error> {
         <T as StreamTrait>::buffer_size(self)
     }
+
+    fn set_buffer_size(&self, frames: FrameCount) -> Result<(), Error> {
+        <T as StreamTrait>::set_buffer_size(self, frames)
+    }
+
+    fn state(&self) -> Result<StreamState, Error> {
+        <T as StreamTrait>::state(self)
+    }
+
+    fn set_state_listener(&self, listener: StateListener) -> Result<(), Error> {
+        <T as StreamTrait>::set_state_listener(self, listener)
+    }
+
+    fn set_xrun_policy(&self, policy: XrunPolicy) -> Result<(), Error> {
+        <T as StreamTrait>::set_xrun_policy(self, policy)
+    }
+
+    fn set_fade(&self, fade: Duration) -> Result<(), Error> {
+        <T as StreamTrait>::set_fade(self, fade)
+    }
+
+    fn set_config_listener(&self, listener: ConfigListener) -> Result<(), Error> {
+        <T as StreamTrait>::set_config_listener(self, listener)
+    }
 }
 
 // implementations of HostTrait, DeviceTrait, and StreamTrait for custom versions
@@ -424,12 +472,37 @@ impl DeviceTrait for Device {
         D: FnMut(&Data, &CallbackInfo) + Send + 'static,
         E: FnMut(Error) + Send + 'static,
     {
-        self.0.build_input_stream_raw(
+        let options = StreamOptions {
+            timeout,
+            ..Default::default()
+        };
+        self.0.build_input_stream_with_options(
             config,
             sample_format,
+            &options,
+            Box::new(data_callback),
+            Box::new(error_callback),
+        )
+    }
+
+    fn build_input_stream_with_options<D, E>(
+        &self,
+        config: StreamConfig,
+        sample_format: SampleFormat,
+        options: &StreamOptions,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, Error>
+    where
+        D: FnMut(&Data, &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        self.0.build_input_stream_with_options(
+            config,
+            sample_format,
+            options,
             Box::new(data_callback),
             Box::new(error_callback),
-            timeout,
         )
     }
 
@@ -445,12 +518,37 @@ impl DeviceTrait for Device {
         D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
         E: FnMut(Error) + Send + 'static,
     {
-        self.0.build_output_stream_raw(
+        let options = StreamOptions {
+            timeout,
+            ..Default::default()
+        };
+        self.0.build_output_stream_with_options(
             config,
             sample_format,
+            &options,
+            Box::new(data_callback),
+            Box::new(error_callback),
+        )
+    }
+
+    fn build_output_stream_with_options<D, E>(
+        &self,
+        config: StreamConfig,
+        sample_format: SampleFormat,
+        options: &StreamOptions,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, Error>
+    where
+        D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        self.0.build_output_stream_with_options(
+            config,
+            sample_format,
+            options,
             Box::new(data_callback),
             Box::new(error_callback),
-            timeout,
         )
     }
 }
@@ -468,11 +566,77 @@ impl StreamTrait for Stream {
         self.0.stop(timeout)
     }
 
+    fn flush(&self) -> Result<(), Error> {
+        self.0.flush()
+    }
+
+    fn position(&self) -> Result<StreamPosition, Error> {
+        self.0.position()
+    }
+
     fn now(&self) -> StreamInstant {
         self.0.now()
     }
 
+    fn clock_mapping(&self) -> Result<ClockMapping, Error> {
+        self.0.clock_mapping()
+    }
+
     fn buffer_size(&self) -> Result<FrameCount, Error> {
         self.0.buffer_size()
     }
+
+    fn set_buffer_size(&self, frames: FrameCount) -> Result<(), Error> {
+        self.0.set_buffer_size(frames)
+    }
+
+    fn state(&self) -> Result<StreamState, Error> {
+        self.0.state()
+    }
+
+    fn set_state_listener(&self, listener: StateListener) -> Result<(), Error> {
+        self.0.set_state_listener(listener)
+    }
+
+    fn set_xrun_policy(&self, policy: XrunPolicy) -> Result<(), Error> {
+        self.0.set_xrun_policy(policy)
+    }
+
+    fn set_fade(&self, fade: Duration) -> Result<(), Error> {
+        self.0.set_fade(fade)
+    }
+
+    fn set_config_listener(&self, listener: ConfigListener) -> Result<(), Error> {
+        self.0.set_config_listener(listener)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream_builder::tests::{RecordingDevice, config};
+    use crate::{AlsaStreamOptions, PipeWireStreamOptions};
+
+    #[test]
+    fn stream_options_reach_the_wrapped_device() {
+        let inner = RecordingDevice {
+            supports_fade: true,
+            ..Default::default()
+        };
+        let device = Device::from_device(Clone::clone(&inner));
+        let builder = device
+            .stream_builder(config())
+            .stream_name("Music")
+            .fade(Duration::from_millis(10))
+            .alsa(AlsaStreamOptions::default().periods(4))
+            .pipewire(PipeWireStreamOptions::default().force_quantum(128));
+        let expected = builder.options().clone();
+        builder
+            .build_output::<f32, _, _>(|_, _| {}, |_| {})
+            .unwrap();
+
+        let recorded = inner.recorded.lock().unwrap();
+        assert_eq!(recorded.options.as_ref(), Some(&expected));
+        assert_eq!(recorded.fade, Some(Duration::from_millis(10)));
+    }
 }
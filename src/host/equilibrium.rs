@@ -1,4 +1,4 @@
-use crate::{Sample, SampleFormat, U24};
+use crate::{ALaw, MuLaw, Sample, SampleFormat, U24};
 
 pub const DSD_EQUILIBRIUM_BYTE: u8 = 0x69;
 pub const U8_EQUILIBRIUM_BYTE: u8 = 0x80;
@@ -40,6 +40,10 @@ pub fn fill_equilibrium(buffer: &mut [u8], sample_format: SampleFormat) {
         buffer.fill(U8_EQUILIBRIUM_BYTE);
     } else if sample_format.is_dsd() {
         buffer.fill(DSD_EQUILIBRIUM_BYTE);
+    } else if sample_format == SampleFormat::ALaw {
+        buffer.fill(ALaw::EQUILIBRIUM.0);
+    } else if sample_format == SampleFormat::MuLaw {
+        buffer.fill(MuLaw::EQUILIBRIUM.0);
     } else {
         // Multi-byte unsigned integer formats require a fill equal to the midpoint of their range.
         debug_assert!(sample_format.is_uint());
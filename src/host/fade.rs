@@ -0,0 +1,339 @@
+//! Gain ramps that fade output in and out around transport changes.
+//!
+//! Halting a stream mid-waveform leaves the speaker cone to snap back to rest, which is heard as
+//! a click. Hosts wrap their output data callback with [`output_callback`] and ask the shared
+//! [`Fader`] to ramp down before pausing, stopping, flushing or dropping the stream, and back up
+//! once it is started again. The ramp length is set with [`StreamTrait::set_fade`].
+//!
+//! [`StreamTrait::set_fade`]: crate::traits::StreamTrait::set_fade
+
+use std::{
+    sync::{
+        Condvar, Mutex,
+        atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    CallbackInfo, ChannelCount, Data, I24, Sample, SampleFormat, SampleRate, SizedSample,
+    StreamInstant, U24,
+    host::{equilibrium::fill_equilibrium, frames_to_duration},
+};
+
+/// How long past the ramp to wait for the data callback to write it and the device to play it,
+/// covering a few periods on hosts with large buffers.
+const COMPLETION_MARGIN: Duration = Duration::from_millis(250);
+
+/// What the data callback has done with the ramp down most recently asked for.
+#[derive(Debug, Default)]
+struct Progress {
+    written: bool,
+    played: bool,
+}
+
+/// Fade state shared between a stream's transport methods and its wrapped data callback.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub(crate) struct Fader {
+    // Length of the ramp in microseconds. Zero disables fading.
+    fade_micros: AtomicU32,
+    channels: AtomicU16,
+    sample_rate: AtomicU32,
+    // Set to ramp down to silence, cleared to ramp up to full gain.
+    muted: AtomicBool,
+    // Whether the host is running the data callback, so a ramp down will be written.
+    running: AtomicBool,
+    // Set while a ramp down is waited for, so the callback only takes the lock then.
+    pending: AtomicBool,
+    progress: Mutex<Progress>,
+    done: Condvar,
+}
+
+#[allow(dead_code)]
+impl Fader {
+    pub(crate) fn new(channels: ChannelCount, sample_rate: SampleRate) -> Self {
+        Self {
+            fade_micros: AtomicU32::new(0),
+            channels: AtomicU16::new(channels),
+            sample_rate: AtomicU32::new(sample_rate),
+            muted: AtomicBool::new(false),
+            running: AtomicBool::new(false),
+            pending: AtomicBool::new(false),
+            progress: Mutex::default(),
+            done: Condvar::new(),
+        }
+    }
+
+    /// Sets the length of the ramps from now on. [`Duration::ZERO`] disables fading.
+    pub(crate) fn set_fade(&self, fade: Duration) {
+        let micros = u32::try_from(fade.as_micros()).unwrap_or(u32::MAX);
+        self.fade_micros.store(micros, Ordering::Relaxed);
+    }
+
+    fn fade(&self) -> Duration {
+        Duration::from_micros(self.fade_micros.load(Ordering::Relaxed).into())
+    }
+
+    /// Ramps at `sample_rate` over `channels` from now on, after the stream followed a format
+    /// change.
+    pub(crate) fn set_format(&self, channels: ChannelCount, sample_rate: SampleRate) {
+        self.channels.store(channels, Ordering::Relaxed);
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    /// Whether the stream was started and has not been faded out since.
+    pub(crate) fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// Ramps back up to full gain. Called once the host runs the data callback again.
+    pub(crate) fn fade_in(&self) {
+        self.muted.store(false, Ordering::Release);
+        self.running.store(true, Ordering::Relaxed);
+    }
+
+    /// Ramps down to silence and blocks until the data callback has written the ramp or, if
+    /// `played` is set, until the device has played it too. Returns the time spent waiting.
+    ///
+    /// Returns at once if fading is disabled or the stream is not running. Otherwise waits at
+    /// most the ramp length plus a margin for the callback to come round, or `limit` if that is
+    /// shorter.
+    pub(crate) fn fade_out(&self, played: bool, limit: Option<Duration>) -> Duration {
+        let fade = self.fade();
+        if !self.running.swap(false, Ordering::Relaxed) || fade.is_zero() {
+            return Duration::ZERO;
+        }
+        let start = Instant::now();
+        let mut progress = self.progress.lock().unwrap_or_else(|e| e.into_inner());
+        *progress = Progress::default();
+        self.pending.store(true, Ordering::Release);
+        self.muted.store(true, Ordering::Release);
+
+        let cap = fade.saturating_add(COMPLETION_MARGIN);
+        let timeout = limit.map_or(cap, |limit| limit.min(cap));
+        let (_progress, _) = self
+            .done
+            .wait_timeout_while(progress, timeout, |progress| {
+                !progress.written || (played && !progress.played)
+            })
+            .unwrap_or_else(|e| e.into_inner());
+        self.pending.store(false, Ordering::Relaxed);
+        start.elapsed()
+    }
+
+    /// Reports progress on the pending ramp down from the data callback.
+    fn report(&self, played: bool) {
+        let mut progress = self.progress.lock().unwrap_or_else(|e| e.into_inner());
+        progress.written = true;
+        progress.played |= played;
+        self.done.notify_all();
+    }
+}
+
+/// Applies one callback's worth of the ramp to `samples`, moving `gain` towards `target` by
+/// `step` per frame. Returns how many frames it took to reach the target, if it did.
+fn ramp<T: SizedSample>(
+    samples: &mut [T],
+    channels: usize,
+    gain: &mut f32,
+    target: f32,
+    step: f32,
+) -> Option<usize> {
+    let mut frames = samples.chunks_mut(channels).enumerate();
+    let mut reached = None;
+    for (index, frame) in frames.by_ref() {
+        *gain = if target < *gain {
+            (*gain - step).max(target)
+        } else {
+            (*gain + step).min(target)
+        };
+        let amp = <T::Float as Sample>::from_sample(*gain);
+        for sample in frame.iter_mut() {
+            *sample = sample.mul_amp(amp);
+        }
+        if *gain == target {
+            reached = Some(index + 1);
+            break;
+        }
+    }
+    if target == 0.0 {
+        for (_, frame) in frames {
+            frame.fill(T::EQUILIBRIUM);
+        }
+    }
+    reached
+}
+
+/// Wraps an output data callback to apply the ramps requested through `fader`.
+///
+/// Once a ramp down completes the buffer is filled with equilibrium without calling
+/// `data_callback`, so a paused source resumes where it faded out. Formats without a linear
+/// amplitude, such as DSD or G.711, switch between silence and the callback's output at once.
+pub(crate) fn output_callback<D>(
+    fader: std::sync::Arc<Fader>,
+    mut data_callback: D,
+) -> impl FnMut(&mut Data, &CallbackInfo) + Send + 'static
+where
+    D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
+{
+    let mut gain = 0.0f32;
+    // When the last frame of the latest ramp down reaches the device.
+    let mut ramp_end: Option<StreamInstant> = None;
+    move |data, info| {
+        let sample_format = data.sample_format();
+        let muted = fader.muted.load(Ordering::Acquire);
+        let target = if muted { 0.0 } else { 1.0 };
+        let fade_micros = fader.fade_micros.load(Ordering::Relaxed);
+        let sample_rate = fader.sample_rate.load(Ordering::Relaxed);
+        if !muted {
+            ramp_end = None;
+        }
+
+        if muted && gain == 0.0 {
+            fill_equilibrium(data.bytes_mut(), sample_format);
+        } else {
+            data_callback(data, info);
+        }
+
+        if gain != target {
+            let ramp_frames = u64::from(fade_micros) * u64::from(sample_rate) / 1_000_000;
+            let step = 1.0 / ramp_frames.max(1) as f32;
+            let channels = usize::from(fader.channels.load(Ordering::Relaxed).max(1));
+            macro_rules! ramp_as {
+                ($($format:ident => $t:ty),*) => {
+                    match sample_format {
+                        $(SampleFormat::$format => data
+                            .as_slice_mut::<$t>()
+                            .and_then(|samples| ramp(samples, channels, &mut gain, target, step)),)*
+                        _ => {
+                            if muted {
+                                fill_equilibrium(data.bytes_mut(), sample_format);
+                            }
+                            gain = target;
+                            Some(0)
+                        }
+                    }
+                };
+            }
+            let reached = ramp_as!(
+                I8 => i8, I16 => i16, I24 => I24, I32 => i32, I64 => i64,
+                U8 => u8, U16 => u16, U24 => U24, U32 => u32, U64 => u64,
+                F32 => f32, F64 => f64
+            );
+            if let (true, Some(frames)) = (muted, reached) {
+                let frames = u32::try_from(frames).unwrap_or(u32::MAX);
+                ramp_end = info
+                    .timestamp()
+                    .device
+                    .checked_add(frames_to_duration(frames, sample_rate));
+            }
+        }
+
+        if muted && gain == 0.0 && fader.pending.load(Ordering::Acquire) {
+            let played = ramp_end.is_none_or(|end| info.timestamp().callback >= end);
+            fader.report(played);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StreamTimestamp;
+    use std::sync::Arc;
+
+    fn info(millis: u64) -> CallbackInfo {
+        let callback = StreamInstant::from_millis(millis);
+        let timestamp = StreamTimestamp {
+            callback,
+            device: callback + Duration::from_millis(5),
+        };
+        CallbackInfo::new(timestamp, false)
+    }
+
+    fn call(callback: &mut impl FnMut(&mut Data, &CallbackInfo), buffer: &mut [f32], at: u64) {
+        // SAFETY: the buffer outlives the `Data` and holds `len` `f32` samples.
+        let mut data = unsafe {
+            Data::from_parts(buffer.as_mut_ptr().cast(), buffer.len(), SampleFormat::F32)
+        };
+        callback(&mut data, &info(at));
+    }
+
+    #[test]
+    fn ramps_up_then_down() {
+        // A 4-frame ramp at 1 kHz, mono.
+        let fader = Arc::new(Fader::new(1, 1000));
+        fader.set_fade(Duration::from_millis(4));
+        let mut callback = output_callback(fader.clone(), |data: &mut Data, _: &CallbackInfo| {
+            data.as_slice_mut::<f32>().unwrap().fill(1.0)
+        });
+
+        let mut buffer = [0.0f32; 6];
+        fader.fade_in();
+        call(&mut callback, &mut buffer, 10);
+        assert_eq!(buffer, [0.25, 0.5, 0.75, 1.0, 1.0, 1.0]);
+
+        fader.muted.store(true, Ordering::Relaxed);
+        fader.pending.store(true, Ordering::Relaxed);
+        call(&mut callback, &mut buffer, 16);
+        assert_eq!(buffer, [0.75, 0.5, 0.25, 0.0, 0.0, 0.0]);
+        // Written, but its last frame reaches the device at 16 + 5 + 4 ms.
+        let progress = fader.progress.lock().unwrap();
+        assert!(progress.written && !progress.played);
+        drop(progress);
+
+        call(&mut callback, &mut buffer, 25);
+        assert!(fader.progress.lock().unwrap().played);
+    }
+
+    #[test]
+    fn fade_out_waits_for_the_ramp_to_play() {
+        let fader = Arc::new(Fader::new(2, 48_000));
+        fader.set_fade(Duration::from_millis(1));
+        fader.fade_in();
+        let mut callback = output_callback(fader.clone(), |data: &mut Data, _: &CallbackInfo| {
+            data.as_slice_mut::<f32>().unwrap().fill(1.0)
+        });
+        let mut buffer = [0.0f32; 256];
+        call(&mut callback, &mut buffer, 0);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let audio = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                // Callbacks 10 ms apart, each playing 5 ms after it runs.
+                let mut at = 10;
+                while !stop.load(Ordering::Relaxed) {
+                    call(&mut callback, &mut buffer, at);
+                    at += 10;
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                buffer
+            })
+        };
+        let waited = fader.fade_out(true, None);
+        stop.store(true, Ordering::Relaxed);
+        let buffer = audio.join().unwrap();
+
+        assert!(waited < COMPLETION_MARGIN);
+        assert!(fader.progress.lock().unwrap().played);
+        assert_eq!(buffer, [0.0; 256]);
+        // Not running any more, so a second fade out returns at once.
+        assert_eq!(fader.fade_out(true, None), Duration::ZERO);
+    }
+
+    #[test]
+    fn silent_stream_skips_the_data_callback() {
+        let fader = Arc::new(Fader::new(2, 48_000));
+        fader.set_fade(Duration::from_millis(10));
+        fader.muted.store(true, Ordering::Relaxed);
+        let mut callback = output_callback(fader, |_: &mut Data, _: &CallbackInfo| {
+            panic!("data callback called while silent")
+        });
+
+        let mut buffer = [1.0f32; 4];
+        call(&mut callback, &mut buffer, 0);
+        assert_eq!(buffer, [0.0; 4]);
+    }
+}
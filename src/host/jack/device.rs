@@ -1,28 +1,43 @@
 use std::{
+    collections::BTreeMap,
     fmt,
     hash::{Hash, Hasher},
     time::Duration,
 };
 
-use super::{JACK_SAMPLE_FORMAT, stream::Stream};
+use super::{
+    JACK_SAMPLE_FORMAT, OwnClients,
+    stream::{MAX_BUFFER_SIZE, Stream},
+};
 pub use crate::iter::{SupportedInputConfigs, SupportedOutputConfigs};
 use crate::{
     BufferSize, CallbackInfo, ChannelCount, Data, DeviceDescription, DeviceDescriptionBuilder,
-    DeviceDirection, DeviceId, Error, ErrorKind, SampleFormat, SampleRate, StreamConfig,
-    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange, traits::DeviceTrait,
+    DeviceDirection, DeviceId, Error, ErrorKind, FrameCount, SampleFormat, SampleRate,
+    StreamConfig, StreamOptions, SupportedBufferSize, SupportedStreamConfig,
+    SupportedStreamConfigRange, traits::DeviceTrait,
 };
 
 const DEFAULT_NUM_CHANNELS: ChannelCount = 2;
 
+/// The server sets one period for all clients, and streams follow it as it changes, up to the
+/// size their buffers are allocated for. Any period in this range is one a stream can run at.
+const SUPPORTED_BUFFER_SIZE: SupportedBufferSize = SupportedBufferSize::Range {
+    min: 1,
+    max: MAX_BUFFER_SIZE as FrameCount,
+};
+
 #[derive(Clone, Debug)]
 pub struct Device {
     name: String,
     sample_rate: SampleRate,
-    buffer_size: SupportedBufferSize,
     max_channels: ChannelCount,
     direction: DeviceDirection,
     start_server_automatically: bool,
     connect_ports_automatically: bool,
+    /// The other JACK client whose ports this device stands for, or `None` for the system ports.
+    peer: Option<String>,
+    /// The clients of the host the device came from, which its streams' clients join.
+    own_clients: OwnClients,
 }
 
 impl Device {
@@ -62,14 +77,12 @@ impl Device {
             // supplied e.g. if there is a name collision
             name: client.name().to_owned(),
             sample_rate: client.sample_rate(),
-            buffer_size: SupportedBufferSize::Range {
-                min: client.buffer_size(),
-                max: client.buffer_size(),
-            },
             max_channels,
             direction,
             start_server_automatically,
             connect_ports_automatically,
+            peer: None,
+            own_clients: OwnClients::default(),
         })
     }
 
@@ -77,8 +90,13 @@ impl Device {
         // `self.name` carries the process ID (see `Host::new`) so that concurrent cpal
         // instances get distinct JACK client names. It must not leak into `DeviceId`,
         // which callers persist across restarts: a synthetic device's direction is the
-        // only part of its identity that's actually stable.
-        let id = if self.is_input() { "input" } else { "output" };
+        // only part of its identity that's actually stable, plus the peer client's name for a
+        // device standing for another client.
+        let direction = if self.is_input() { "input" } else { "output" };
+        let id = match &self.peer {
+            Some(peer) => format!("{direction}:{peer}"),
+            None => direction.to_owned(),
+        };
         Ok(DeviceId::new(crate::platform::HostId::Jack, id))
     }
 
@@ -110,10 +128,53 @@ impl Device {
         )
     }
 
+    /// Makes the device's streams join `own_clients`, so that the host that hands the device
+    /// out does not list them as devices.
+    pub(crate) fn with_own_clients(mut self, own_clients: &OwnClients) -> Self {
+        self.own_clients = own_clients.clone();
+        self
+    }
+
+    /// Lists a device for each other JACK client with audio ports, as seen by `client`: an input
+    /// device recording from its output ports and an output device playing to its input ports.
+    ///
+    /// The `system` client is left out, as the default devices already stand for it, and so
+    /// are the clients in `own_clients`, which the host and its streams opened.
+    pub(crate) fn client_devices(
+        client: &jack::Client,
+        name: &str,
+        own_clients: &OwnClients,
+        connect_ports_automatically: bool,
+        start_server_automatically: bool,
+    ) -> Vec<Self> {
+        let audio_type = jack::AudioIn::default();
+        let audio_type = jack::PortSpec::jack_port_type(&audio_type);
+
+        let mut devices = Vec::new();
+        for (direction, flags, suffix) in [
+            (DeviceDirection::Input, jack::PortFlags::IS_OUTPUT, "in"),
+            (DeviceDirection::Output, jack::PortFlags::IS_INPUT, "out"),
+        ] {
+            let ports = client.ports(None, Some(audio_type), flags);
+            let port_counts = peer_port_counts(&ports, own_clients);
+            devices.extend(port_counts.into_iter().map(|(peer, count)| Self {
+                name: format!("{name}_{suffix}"),
+                sample_rate: client.sample_rate(),
+                max_channels: count,
+                direction,
+                start_server_automatically,
+                connect_ports_automatically,
+                peer: Some(peer.to_owned()),
+                own_clients: own_clients.clone(),
+            }));
+        }
+        devices
+    }
+
     pub fn default_config(&self) -> Result<SupportedStreamConfig, Error> {
-        let channels = DEFAULT_NUM_CHANNELS;
+        let channels = DEFAULT_NUM_CHANNELS.min(self.max_channels);
         let sample_rate = self.sample_rate;
-        let buffer_size = self.buffer_size;
+        let buffer_size = SUPPORTED_BUFFER_SIZE;
         // The sample format for JACK audio ports is always "32-bit float mono audio" in the current implementation.
         // Custom formats are allowed within JACK, but this is of niche interest.
         // The format can be found programmatically by calling jack::PortSpec::port_type() on a created port.
@@ -137,6 +198,7 @@ impl Device {
                 channels,
                 min_sample_rate: f.sample_rate,
                 max_sample_rate: f.sample_rate,
+                sample_rates: None,
                 buffer_size: f.buffer_size,
                 sample_format: f.sample_format,
             })
@@ -150,6 +212,21 @@ impl Device {
     pub fn is_output(&self) -> bool {
         matches!(self.direction, DeviceDirection::Output)
     }
+
+    /// Matches the ports of the device's peer client, if it has one.
+    fn peer_pattern(&self) -> Option<String> {
+        let peer = self.peer.as_ref()?;
+        let mut pattern = String::from("^");
+        for c in peer.chars() {
+            // JACK matches port names with POSIX extended regular expressions.
+            if "\\.[]()*+?{}|^$".contains(c) {
+                pattern.push('\\');
+            }
+            pattern.push(c);
+        }
+        pattern.push(':');
+        Some(pattern)
+    }
 }
 
 impl DeviceTrait for Device {
@@ -160,10 +237,10 @@ impl DeviceTrait for Device {
     fn description(&self) -> Result<DeviceDescription, Error> {
         // Not `self.name`: that's the JACK client name, which carries a process ID
         // uniquifier (see `Host::new`) and isn't meant as a user-facing device label.
-        let name = if self.is_input() {
-            "JACK Input"
-        } else {
-            "JACK Output"
+        let name = match &self.peer {
+            Some(peer) => peer.as_str(),
+            None if self.is_input() => "JACK Input",
+            None => "JACK Output",
         };
         Ok(DeviceDescriptionBuilder::new(name)
             .direction(self.direction)
@@ -204,6 +281,31 @@ impl DeviceTrait for Device {
         error_callback: E,
         timeout: Option<Duration>,
     ) -> Result<Self::Stream, Error>
+    where
+        D: FnMut(&Data, &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        let options = StreamOptions {
+            timeout,
+            ..Default::default()
+        };
+        self.build_input_stream_with_options(
+            conf,
+            sample_format,
+            &options,
+            data_callback,
+            error_callback,
+        )
+    }
+
+    fn build_input_stream_with_options<D, E>(
+        &self,
+        conf: StreamConfig,
+        sample_format: SampleFormat,
+        options: &StreamOptions,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, Error>
     where
         D: FnMut(&Data, &CallbackInfo) + Send + 'static,
         E: FnMut(Error) + Send + 'static,
@@ -215,6 +317,13 @@ impl DeviceTrait for Device {
             ));
         }
         crate::validate_stream_config(&conf)?;
+        check_fixed_buffer_size(conf.buffer_size)?;
+        let port_names = port_names(options, conf.channels)?;
+        let connect_to = options
+            .jack
+            .connect_to
+            .clone()
+            .or_else(|| self.peer_pattern());
         if sample_format != JACK_SAMPLE_FORMAT {
             return Err(Error::with_message(
                 ErrorKind::UnsupportedConfig,
@@ -227,9 +336,13 @@ impl DeviceTrait for Device {
         // Keep `capture` monotonic: re-patching cpal's ports to a different hardware port
         // can raise the capture port's latency, pulling `capture` backward.
         let data_callback = crate::host::monotonic_input_callback(data_callback);
-        let name = self.name.clone();
+        let name = options
+            .application_name
+            .clone()
+            .unwrap_or_else(|| self.name.clone());
         let start_server_automatically = self.start_server_automatically;
         let connect_ports_automatically = self.connect_ports_automatically;
+        let own_clients = self.own_clients.clone();
 
         let build = move || -> Result<Stream, Error> {
             let client_options = super::get_client_options(start_server_automatically);
@@ -244,26 +357,23 @@ impl DeviceTrait for Device {
                     ),
                 ));
             }
-            if let BufferSize::Fixed(size) = conf.buffer_size {
-                if size != client.buffer_size() {
-                    return Err(Error::with_message(
-                        ErrorKind::UnsupportedConfig,
-                        format!(
-                            "Buffer size {size} does not match the server buffer size {}",
-                            client.buffer_size()
-                        ),
-                    ));
-                }
-            }
-            let mut stream =
-                Stream::new_input(client, conf.channels, data_callback, error_callback)?;
-            if connect_ports_automatically {
+            let stream = Stream::new_input(
+                client,
+                &own_clients,
+                conf.channels,
+                &port_names,
+                data_callback,
+                error_callback,
+            )?;
+            if let Some(pattern) = &connect_to {
+                stream.connect_to_pattern(pattern)?;
+            } else if connect_ports_automatically {
                 stream.connect_to_system_inputs()?;
             }
             Ok(stream)
         };
 
-        if let Some(dur) = timeout {
+        if let Some(dur) = options.timeout {
             let (tx, rx) = std::sync::mpsc::channel();
             std::thread::spawn(move || {
                 tx.send(build()).ok();
@@ -288,6 +398,31 @@ impl DeviceTrait for Device {
         error_callback: E,
         timeout: Option<Duration>,
     ) -> Result<Self::Stream, Error>
+    where
+        D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        let options = StreamOptions {
+            timeout,
+            ..Default::default()
+        };
+        self.build_output_stream_with_options(
+            conf,
+            sample_format,
+            &options,
+            data_callback,
+            error_callback,
+        )
+    }
+
+    fn build_output_stream_with_options<D, E>(
+        &self,
+        conf: StreamConfig,
+        sample_format: SampleFormat,
+        options: &StreamOptions,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, Error>
     where
         D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
         E: FnMut(Error) + Send + 'static,
@@ -299,6 +434,13 @@ impl DeviceTrait for Device {
             ));
         }
         crate::validate_stream_config(&conf)?;
+        check_fixed_buffer_size(conf.buffer_size)?;
+        let port_names = port_names(options, conf.channels)?;
+        let connect_to = options
+            .jack
+            .connect_to
+            .clone()
+            .or_else(|| self.peer_pattern());
         if sample_format != JACK_SAMPLE_FORMAT {
             return Err(Error::with_message(
                 ErrorKind::UnsupportedConfig,
@@ -311,9 +453,13 @@ impl DeviceTrait for Device {
         // Keep `playback` monotonic: re-patching cpal's ports to a different hardware port
         // can lower the playback port's latency, pulling `playback` backward.
         let data_callback = crate::host::monotonic_output_callback(data_callback);
-        let name = self.name.clone();
+        let name = options
+            .application_name
+            .clone()
+            .unwrap_or_else(|| self.name.clone());
         let start_server_automatically = self.start_server_automatically;
         let connect_ports_automatically = self.connect_ports_automatically;
+        let own_clients = self.own_clients.clone();
 
         let build = move || -> Result<Stream, Error> {
             // Create a fresh client to validate against live server state.
@@ -329,26 +475,23 @@ impl DeviceTrait for Device {
                     ),
                 ));
             }
-            if let BufferSize::Fixed(size) = conf.buffer_size {
-                if size != client.buffer_size() {
-                    return Err(Error::with_message(
-                        ErrorKind::UnsupportedConfig,
-                        format!(
-                            "Buffer size {size} does not match the server buffer size {}",
-                            client.buffer_size()
-                        ),
-                    ));
-                }
-            }
-            let mut stream =
-                Stream::new_output(client, conf.channels, data_callback, error_callback)?;
-            if connect_ports_automatically {
+            let stream = Stream::new_output(
+                client,
+                &own_clients,
+                conf.channels,
+                &port_names,
+                data_callback,
+                error_callback,
+            )?;
+            if let Some(pattern) = &connect_to {
+                stream.connect_to_pattern(pattern)?;
+            } else if connect_ports_automatically {
                 stream.connect_to_system_outputs()?;
             }
             Ok(stream)
         };
 
-        if let Some(dur) = timeout {
+        if let Some(dur) = options.timeout {
             let (tx, rx) = std::sync::mpsc::channel();
             std::thread::spawn(move || {
                 tx.send(build()).ok();
@@ -366,6 +509,61 @@ impl DeviceTrait for Device {
     }
 }
 
+/// The short names of a stream's ports: those given explicitly, `<stream name>_<n>` for a named
+/// stream, or none to let the stream pick its defaults.
+fn port_names(options: &StreamOptions, channels: ChannelCount) -> Result<Vec<String>, Error> {
+    let port_names = &options.jack.port_names;
+    if !port_names.is_empty() {
+        if port_names.len() != channels as usize {
+            return Err(Error::with_message(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} port names given for {channels} channels",
+                    port_names.len()
+                ),
+            ));
+        }
+        return Ok(port_names.clone());
+    }
+    Ok(match &options.stream_name {
+        Some(stream_name) => (0..channels)
+            .map(|i| format!("{stream_name}_{i}"))
+            .collect(),
+        None => Vec::new(),
+    })
+}
+
+/// Checks that a stream can run at a fixed buffer size. The stream runs at the server's period,
+/// whatever it is, so the size does not have to match the current one.
+fn check_fixed_buffer_size(buffer_size: BufferSize) -> Result<(), Error> {
+    match buffer_size {
+        BufferSize::Fixed(size) if size > MAX_BUFFER_SIZE as FrameCount => {
+            Err(Error::with_message(
+                ErrorKind::UnsupportedConfig,
+                format!("Buffer size {size} exceeds the supported maximum of {MAX_BUFFER_SIZE}"),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Counts the audio ports of each client among the full port names `ports`, leaving out the
+/// `system` client and the clients in `own_clients`.
+fn peer_port_counts<'a>(
+    ports: &'a [String],
+    own_clients: &OwnClients,
+) -> BTreeMap<&'a str, ChannelCount> {
+    let mut port_counts = BTreeMap::<&str, ChannelCount>::new();
+    for port in ports {
+        if let Some((peer, _)) = port.split_once(':') {
+            if peer != "system" && !own_clients.contains(peer) {
+                *port_counts.entry(peer).or_default() += 1;
+            }
+        }
+    }
+    port_counts
+}
+
 impl PartialEq for Device {
     fn eq(&self, other: &Self) -> bool {
         // Device::id() can never fail in this implementation
@@ -388,3 +586,67 @@ impl Hash for Device {
         self.id().unwrap().hash(state);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{OwnClients, check_fixed_buffer_size, peer_port_counts, port_names};
+    use crate::{BufferSize, ErrorKind, StreamOptions};
+
+    #[test]
+    fn port_names_from_options() {
+        let mut options = StreamOptions::default();
+        assert!(port_names(&options, 2).unwrap().is_empty());
+
+        options.stream_name = Some("music".to_owned());
+        assert_eq!(port_names(&options, 2).unwrap(), ["music_0", "music_1"]);
+
+        // Explicit names win over the stream name, one per channel.
+        options.jack.port_names = vec!["left".to_owned(), "right".to_owned()];
+        assert_eq!(port_names(&options, 2).unwrap(), ["left", "right"]);
+        let err = port_names(&options, 3).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn peer_ports_leave_out_own_clients() {
+        let ports: Vec<String> = [
+            "system:playback_1",
+            "MyApp-01:out_0",
+            "MyApp-01:out_1",
+            "cpal_client_42_list:unused",
+            "cpal_client_420_out:out_0",
+            "synth:left",
+            "synth:right",
+        ]
+        .map(String::from)
+        .into();
+        let own_clients = OwnClients::default();
+        // JACK renamed the stream's client because "MyApp" was taken.
+        let stream = own_clients.register("MyApp-01");
+        let _list = own_clients.register("cpal_client_42_list");
+
+        let counts = peer_port_counts(&ports, &own_clients);
+        assert_eq!(
+            counts.into_iter().collect::<Vec<_>>(),
+            [("cpal_client_420_out", 1), ("synth", 2)]
+        );
+
+        // Once the stream closes, a client of that name is someone else's.
+        drop(stream);
+        assert_eq!(
+            peer_port_counts(&ports, &own_clients).get("MyApp-01"),
+            Some(&2)
+        );
+    }
+
+    #[test]
+    fn fixed_buffer_size_need_not_match_the_server() {
+        assert!(check_fixed_buffer_size(BufferSize::Default).is_ok());
+        // Whatever the server period is now, the stream follows it.
+        assert!(check_fixed_buffer_size(BufferSize::Fixed(64)).is_ok());
+        assert!(check_fixed_buffer_size(BufferSize::Fixed(8192)).is_ok());
+
+        let err = check_fixed_buffer_size(BufferSize::Fixed(16384)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedConfig);
+    }
+}
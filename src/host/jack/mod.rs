@@ -4,10 +4,14 @@
 
 extern crate jack;
 
+use std::sync::{Arc, Mutex};
+
 use crate::{Error, ErrorKind, SampleFormat, traits::HostTrait};
 
 mod device;
+mod ports;
 mod stream;
+mod transport;
 
 #[expect(
     unused_imports,
@@ -15,7 +19,9 @@ mod stream;
 )]
 pub use self::{
     device::{Device, SupportedInputConfigs, SupportedOutputConfigs},
+    ports::{PortConnection, PortInfo},
     stream::Stream,
+    transport::{Bbt, TimebaseRequest, Transport, TransportPosition, TransportState},
 };
 
 const JACK_SAMPLE_FORMAT: SampleFormat = SampleFormat::F32;
@@ -29,6 +35,9 @@ pub type Devices = std::vec::IntoIter<Device>;
 /// JACK provides configuration options to control connection and server behavior:
 /// - Port auto-connection via [`set_connect_automatically`](Host::set_connect_automatically)
 /// - Server auto-start via [`set_start_server_automatically`](Host::set_start_server_automatically)
+/// - Client naming via [`set_client_name`](Host::set_client_name)
+/// - Listing other JACK clients as devices via
+///   [`set_enumerate_clients`](Host::set_enumerate_clients)
 #[derive(Debug)]
 pub struct Host {
     /// The name that the client will have in JACK.
@@ -39,8 +48,14 @@ pub struct Host {
     connect_ports_automatically: bool,
     /// If the JACK server should be started automatically if it isn't already when creating a Client (default is false).
     start_server_automatically: bool,
+    /// If other JACK clients are listed as devices alongside the system ones (default is false).
+    enumerate_clients: bool,
     /// A list of the devices that have been created from this Host.
     devices_created: Vec<Device>,
+    /// The client that lists the other clients, opened on first use and kept for later calls.
+    list_client: Mutex<Option<(jack::Client, OwnClient)>>,
+    /// The clients this host and its streams have open, which are not listed as devices.
+    own_clients: OwnClients,
 }
 
 impl Host {
@@ -49,7 +64,10 @@ impl Host {
             name: format!("cpal_client_{}", std::process::id()),
             connect_ports_automatically: true,
             start_server_automatically: false,
+            enumerate_clients: false,
             devices_created: vec![],
+            list_client: Mutex::new(None),
+            own_clients: OwnClients::default(),
         };
         // Devices don't exist for JACK, they have to be created
         host.initialize_default_devices();
@@ -77,6 +95,37 @@ impl Host {
         self.start_server_automatically = do_start_server;
     }
 
+    /// Configures whether [`devices`](HostTrait::devices) also lists the other JACK clients, such
+    /// as a synth or a hardware bridge, as devices of their own.
+    ///
+    /// Each client with audio output ports becomes an input device and each client with audio
+    /// input ports an output device, with one channel per port. Streams built on them connect
+    /// their ports to the client's ports in order. The list reflects the JACK graph at the time
+    /// of each call, which fails if the listing client cannot be opened.
+    ///
+    /// Default: `false`
+    pub fn set_enumerate_clients(&mut self, enumerate: bool) {
+        self.enumerate_clients = enumerate;
+    }
+
+    /// Sets the name of the JACK clients this host creates, with `_in` or `_out` appended for
+    /// the input and output device.
+    ///
+    /// The default devices are recreated under the new name; devices obtained earlier keep the
+    /// old one. A stream built with an
+    /// [`application_name`](crate::StreamBuilder::application_name) uses that instead.
+    ///
+    /// Default: `cpal_client_<process ID>`
+    pub fn set_client_name(&mut self, name: impl Into<String>) {
+        self.name = name.into();
+        self.devices_created.clear();
+        *self
+            .list_client
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner()) = None;
+        self.initialize_default_devices();
+    }
+
     pub fn input_device_with_name(&mut self, name: &str) -> Option<Device> {
         self.name = name.to_owned();
         self.default_input_device()
@@ -95,7 +144,8 @@ impl Host {
         );
 
         if let Ok(device) = in_device_res {
-            self.devices_created.push(device);
+            self.devices_created
+                .push(device.with_own_clients(&self.own_clients));
         }
 
         let out_device_res = Device::default_output_device(
@@ -104,7 +154,8 @@ impl Host {
             self.start_server_automatically,
         );
         if let Ok(device) = out_device_res {
-            self.devices_created.push(device);
+            self.devices_created
+                .push(device.with_own_clients(&self.own_clients));
         }
     }
 }
@@ -129,7 +180,29 @@ impl HostTrait for Host {
     }
 
     fn devices(&self) -> Result<Self::Devices, Error> {
-        Ok(self.devices_created.clone().into_iter())
+        let mut devices = self.devices_created.clone();
+        if self.enumerate_clients {
+            let mut list_client = self.list_client.lock().unwrap_or_else(|e| e.into_inner());
+            let (client, _) = match list_client.as_mut() {
+                Some(list_client) => list_client,
+                None => {
+                    let client = get_client(
+                        &format!("{}_list", self.name),
+                        get_client_options(self.start_server_automatically),
+                    )?;
+                    let own_client = self.own_clients.register(client.name());
+                    list_client.insert((client, own_client))
+                }
+            };
+            devices.extend(Device::client_devices(
+                client,
+                &self.name,
+                &self.own_clients,
+                self.connect_ports_automatically,
+                self.start_server_automatically,
+            ));
+        }
+        Ok(devices.into_iter())
     }
 
     fn default_input_device(&self) -> Option<Self::Device> {
@@ -163,7 +236,7 @@ fn get_client_options(start_server_automatically: bool) -> jack::ClientOptions {
 impl From<jack::Error> for Error {
     fn from(err: jack::Error) -> Self {
         let msg = format!("{err}");
-        match err {
+        let error = match err {
             jack::Error::ClientError(_)
             | jack::Error::ClientActivationError
             | jack::Error::ClientDeactivationError
@@ -190,48 +263,96 @@ impl From<jack::Error> for Error {
             }
 
             _ => Error::with_message(ErrorKind::BackendError, msg),
+        };
+        match err {
+            jack::Error::ClientError(status) => error.with_backend_code(status.bits()),
+            _ => error,
+        }
+        .with_source(err)
+    }
+}
+
+/// The names JACK gave the clients a host and its streams have open.
+///
+/// JACK may change a requested name to make it unique, e.g. to `MyApp-01`, so the names are
+/// recorded as the clients report them rather than derived from the requested ones.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OwnClients(Arc<Mutex<Vec<String>>>);
+
+impl OwnClients {
+    /// Records `name` until the returned guard is dropped.
+    fn register(&self, name: &str) -> OwnClient {
+        self.names().push(name.to_owned());
+        OwnClient {
+            clients: self.clone(),
+            name: name.to_owned(),
+        }
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.names().iter().any(|own| own == name)
+    }
+
+    fn names(&self) -> std::sync::MutexGuard<'_, Vec<String>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Keeps a client's name in its [`OwnClients`] while the client is open.
+#[derive(Debug)]
+pub(crate) struct OwnClient {
+    clients: OwnClients,
+    name: String,
+}
+
+impl Drop for OwnClient {
+    fn drop(&mut self) {
+        let mut names = self.clients.names();
+        if let Some(index) = names.iter().position(|own| *own == self.name) {
+            names.swap_remove(index);
         }
     }
 }
 
 fn get_client(name: &str, client_options: jack::ClientOptions) -> Result<jack::Client, Error> {
     let (client, status) = jack::Client::new(name, client_options)?;
-    if status.intersects(jack::ClientStatus::VERSION_ERROR) {
-        Err(Error::with_message(
+    let error = if status.intersects(jack::ClientStatus::VERSION_ERROR) {
+        Error::with_message(
             ErrorKind::UnsupportedOperation,
             "Client protocol version does not match the JACK server",
-        ))
+        )
     } else if status.intersects(jack::ClientStatus::INVALID_OPTION) {
-        Err(Error::with_message(
+        Error::with_message(
             ErrorKind::UnsupportedOperation,
             "JACK client operation contained an invalid or unsupported option",
-        ))
+        )
     } else if status.intersects(jack::ClientStatus::SERVER_ERROR) {
-        Err(Error::with_message(
+        Error::with_message(
             ErrorKind::DeviceNotAvailable,
             "Error communicating with the JACK server",
-        ))
+        )
     } else if status.intersects(jack::ClientStatus::SERVER_FAILED) {
-        Err(Error::with_message(
+        Error::with_message(
             ErrorKind::DeviceNotAvailable,
             "Could not connect to the JACK server",
-        ))
+        )
     } else if status.intersects(jack::ClientStatus::INIT_FAILURE) {
-        Err(Error::with_message(
+        Error::with_message(
             ErrorKind::DeviceNotAvailable,
             "Unable to initialize JACK client",
-        ))
+        )
     } else if status.intersects(jack::ClientStatus::SHM_FAILURE) {
-        Err(Error::with_message(
+        Error::with_message(
             ErrorKind::DeviceNotAvailable,
             "Unable to access JACK shared memory",
-        ))
+        )
     } else if status.intersects(jack::ClientStatus::NO_SUCH_CLIENT) {
-        Err(Error::with_message(
+        Error::with_message(
             ErrorKind::DeviceNotAvailable,
             "Requested JACK client does not exist",
-        ))
+        )
     } else {
-        Ok(client)
-    }
+        return Ok(client);
+    };
+    Err(error.with_backend_code(status.bits()))
 }
@@ -0,0 +1,73 @@
+//! Inspecting and patching the JACK port graph.
+
+use std::sync::{Arc, Mutex};
+
+use crate::{Error, ErrorKind};
+
+/// A port registered with the JACK server, as listed by
+/// [`JackStream::ports`](crate::platform::JackStream::ports).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PortInfo {
+    /// Full name of the port, `client:port`.
+    pub name: String,
+
+    /// JACK type of the port, e.g. `32 bit float mono audio` or `8 bit raw midi`.
+    pub port_type: String,
+
+    /// The port receives data: it is the destination of its connections.
+    pub is_input: bool,
+
+    /// The port produces data: it is the source of its connections.
+    pub is_output: bool,
+
+    /// The port corresponds to a physical connector, such as a sound card channel.
+    pub is_physical: bool,
+
+    /// Data does not flow through the port to other ports: it originates or ends at it.
+    pub is_terminal: bool,
+}
+
+impl PortInfo {
+    pub(super) fn new<PS>(port: &jack::Port<PS>) -> Option<Self> {
+        let flags = port.flags();
+        Some(Self {
+            name: port.name().ok()?,
+            port_type: port.port_type().ok()?,
+            is_input: flags.contains(jack::PortFlags::IS_INPUT),
+            is_output: flags.contains(jack::PortFlags::IS_OUTPUT),
+            is_physical: flags.contains(jack::PortFlags::IS_PHYSICAL),
+            is_terminal: flags.contains(jack::PortFlags::IS_TERMINAL),
+        })
+    }
+}
+
+/// A connection made or broken anywhere in the JACK graph, reported to the listener set with
+/// [`JackStream::set_connection_listener`](crate::platform::JackStream::set_connection_listener).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PortConnection {
+    /// Full name of the output port data flows from.
+    pub source: String,
+
+    /// Full name of the input port data flows to.
+    pub destination: String,
+
+    /// `true` if the ports were connected, `false` if they were disconnected.
+    pub connected: bool,
+}
+
+/// Listener called on JACK's notification thread for each [`PortConnection`] change.
+pub(super) type ConnectionListener = Box<dyn FnMut(PortConnection) + Send + 'static>;
+
+/// The connection listener, shared between a stream and its notification handler.
+pub(super) type ConnectionListenerSlot = Arc<Mutex<Option<ConnectionListener>>>;
+
+/// The JACK API takes port names and patterns as C strings, and panics on interior NUL bytes.
+pub(super) fn check_port_name(name: &str) -> Result<(), Error> {
+    if name.contains('\0') {
+        return Err(Error::with_message(
+            ErrorKind::InvalidInput,
+            "JACK port names must not contain NUL bytes",
+        ));
+    }
+    Ok(())
+}
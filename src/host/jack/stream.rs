@@ -1,27 +1,47 @@
-use std::sync::{
-    Arc, Mutex,
-    atomic::{AtomicBool, AtomicU8, Ordering},
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU8, Ordering},
+    },
+    time::Duration,
 };
 
-use super::JACK_SAMPLE_FORMAT;
+use super::{
+    JACK_SAMPLE_FORMAT, OwnClient, OwnClients,
+    ports::{ConnectionListenerSlot, PortConnection, PortInfo, check_port_name},
+    transport::{self, Bbt, TimebaseCallback, TimebaseRequest, Transport},
+};
 #[cfg(feature = "realtime")]
 use crate::host::try_emit_error;
 use crate::{
-    CallbackInfo, ChannelCount, Data, Error, ErrorKind, FrameCount, ResultExt, Sample, SampleRate,
-    StreamInstant, StreamTimestamp,
-    host::{ErrorCallbackArc, emit_error, frames_to_duration},
+    BufferSize, CallbackInfo, ChannelCount, Data, Error, ErrorKind, FrameCount, ResultExt, Sample,
+    SampleRate, StreamConfig, StreamInstant, StreamPosition, StreamState, StreamTimestamp,
+    XrunPolicy, XrunRecovery,
+    config_change::{ConfigListener, ConfigTracker},
+    host::{
+        ErrorCallbackArc, emit_error,
+        fade::{self, Fader},
+        frames_to_duration,
+    },
+    position::PositionTracker,
+    stream_state::{StateListener, StateTracker},
     traits::StreamTrait,
+    xrun::XrunPolicyCell,
 };
 
+/// The largest period JACK2 accepts. Buffers are sized for it up front so that a period change
+/// never allocates on the process thread.
+pub(super) const MAX_BUFFER_SIZE: usize = 8192;
+
 #[repr(u8)]
 #[derive(Clone, Copy, PartialEq, Eq)]
-enum StreamState {
+enum PlaybackState {
     Starting = 0,
     Paused = 1,
     Playing = 2,
 }
 
-impl StreamState {
+impl PlaybackState {
     fn load(atom: &AtomicU8, order: Ordering) -> Self {
         match atom.load(order) {
             1 => Self::Paused,
@@ -37,16 +57,35 @@ impl StreamState {
 
 pub struct Stream {
     playback_state: Arc<AtomicU8>,
+    state: Arc<StateTracker>,
+    position: Arc<PositionTracker>,
+    xrun_policy: Arc<XrunPolicyCell>,
+    connection_listener: ConnectionListenerSlot,
+    config: Arc<ConfigTracker>,
     async_client: jack::AsyncClient<JackNotificationHandler, LocalProcessHandler>,
+    // Every timebase callback handed to JACK, dropped only after `async_client` closes the client.
+    #[expect(
+        clippy::vec_box,
+        reason = "JACK holds the address of each callback, which must not move"
+    )]
+    timebase_callbacks: Mutex<Vec<Box<TimebaseCallback>>>,
     // Port names are stored in order to connect them to other ports in jack automatically
     input_port_names: Box<[String]>,
     output_port_names: Box<[String]>,
+    // Gain ramps around transport changes; `None` for input streams.
+    fader: Option<Arc<Fader>>,
+    // Frames per channel the process handler's buffers hold, the largest period it can follow.
+    capacity: usize,
+    // Keeps the client out of the host's device list while it is open.
+    _own_client: OwnClient,
 }
 
 impl Stream {
-    pub fn new_input<D, E>(
+    pub(crate) fn new_input<D, E>(
         client: jack::Client,
+        own_clients: &OwnClients,
         channels: ChannelCount,
+        names: &[String],
         data_callback: D,
         error_callback: E,
     ) -> Result<Stream, Error>
@@ -54,11 +93,16 @@ impl Stream {
         D: FnMut(&Data, &CallbackInfo) + Send + 'static,
         E: FnMut(Error) + Send + 'static,
     {
+        let own_client = own_clients.register(client.name());
         let mut ports = vec![];
         let mut port_names: Vec<String> = vec![];
         for i in 0..channels {
+            let name = match names.get(i as usize) {
+                Some(name) => name.clone(),
+                None => format!("in_{i}"),
+            };
             let port = client
-                .register_port(&format!("in_{}", i), jack::AudioIn::default())
+                .register_port(&name, jack::AudioIn::default())
                 .context(format!("Failed to register input port {i}"))?;
             if let Ok(port_name) = port.name() {
                 port_names.push(port_name);
@@ -66,9 +110,15 @@ impl Stream {
             ports.push(port);
         }
 
-        let playback_state = Arc::new(AtomicU8::new(StreamState::Starting as u8));
+        let playback_state = Arc::new(AtomicU8::new(PlaybackState::Starting as u8));
+        let state = Arc::new(StateTracker::new());
+        let position = Arc::new(PositionTracker::new(client.sample_rate() as SampleRate));
         let pending_xrun = Arc::new(AtomicBool::new(false));
+        let xrun_policy = Arc::new(XrunPolicyCell::default());
+        let connection_listener = ConnectionListenerSlot::default();
+        let config = Arc::new(ConfigTracker::default());
         let error_callback_ptr: ErrorCallbackArc = Arc::new(Mutex::new(error_callback));
+        let capacity = (client.buffer_size() as usize).max(MAX_BUFFER_SIZE);
 
         let input_process_handler = LocalProcessHandler::new(
             vec![],
@@ -78,34 +128,53 @@ impl Stream {
             Some(Box::new(data_callback)),
             None,
             playback_state.clone(),
+            position.clone(),
             pending_xrun.clone(),
-            #[cfg(feature = "realtime")]
+            config.clone(),
+            state.clone(),
+            capacity,
             error_callback_ptr.clone(),
         );
 
         let notification_handler = JackNotificationHandler::new(
             error_callback_ptr,
             playback_state.clone(),
+            state.clone(),
+            channels,
             client.sample_rate() as jack::Frames,
             pending_xrun,
+            xrun_policy.clone(),
+            connection_listener.clone(),
+            config.clone(),
         );
 
         let async_client = client
             .activate_async(notification_handler, input_process_handler)
             .context("Failed to activate client")?;
 
-        StreamState::Paused.store(&playback_state, Ordering::Relaxed);
+        PlaybackState::Paused.store(&playback_state, Ordering::Relaxed);
         Ok(Self {
             playback_state,
+            state,
+            position,
+            xrun_policy,
+            connection_listener,
+            config,
             async_client,
+            timebase_callbacks: Mutex::default(),
             input_port_names: port_names.into_boxed_slice(),
             output_port_names: Default::default(),
+            fader: None,
+            capacity,
+            _own_client: own_client,
         })
     }
 
-    pub fn new_output<D, E>(
+    pub(crate) fn new_output<D, E>(
         client: jack::Client,
+        own_clients: &OwnClients,
         channels: ChannelCount,
+        names: &[String],
         data_callback: D,
         error_callback: E,
     ) -> Result<Stream, Error>
@@ -113,11 +182,16 @@ impl Stream {
         D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
         E: FnMut(Error) + Send + 'static,
     {
+        let own_client = own_clients.register(client.name());
         let mut ports = vec![];
         let mut port_names: Vec<String> = vec![];
         for i in 0..channels {
+            let name = match names.get(i as usize) {
+                Some(name) => name.clone(),
+                None => format!("out_{i}"),
+            };
             let port = client
-                .register_port(&format!("out_{}", i), jack::AudioOut::default())
+                .register_port(&name, jack::AudioOut::default())
                 .context(format!("Failed to register output port {i}"))?;
             if let Ok(port_name) = port.name() {
                 port_names.push(port_name);
@@ -125,9 +199,16 @@ impl Stream {
             ports.push(port);
         }
 
-        let playback_state = Arc::new(AtomicU8::new(StreamState::Starting as u8));
+        let playback_state = Arc::new(AtomicU8::new(PlaybackState::Starting as u8));
+        let state = Arc::new(StateTracker::new());
+        let position = Arc::new(PositionTracker::new(client.sample_rate() as SampleRate));
         let pending_xrun = Arc::new(AtomicBool::new(false));
+        let xrun_policy = Arc::new(XrunPolicyCell::default());
+        let connection_listener = ConnectionListenerSlot::default();
+        let config = Arc::new(ConfigTracker::default());
         let error_callback_ptr: ErrorCallbackArc = Arc::new(Mutex::new(error_callback));
+        let capacity = (client.buffer_size() as usize).max(MAX_BUFFER_SIZE);
+        let fader = Arc::new(Fader::new(channels, client.sample_rate() as SampleRate));
 
         let output_process_handler = LocalProcessHandler::new(
             ports,
@@ -135,30 +216,50 @@ impl Stream {
             client.sample_rate(),
             client.buffer_size() as usize,
             None,
-            Some(Box::new(data_callback)),
+            Some(Box::new(fade::output_callback(
+                fader.clone(),
+                data_callback,
+            ))),
             playback_state.clone(),
+            position.clone(),
             pending_xrun.clone(),
-            #[cfg(feature = "realtime")]
+            config.clone(),
+            state.clone(),
+            capacity,
             error_callback_ptr.clone(),
         );
 
         let notification_handler = JackNotificationHandler::new(
             error_callback_ptr,
             playback_state.clone(),
+            state.clone(),
+            channels,
             client.sample_rate() as jack::Frames,
             pending_xrun,
+            xrun_policy.clone(),
+            connection_listener.clone(),
+            config.clone(),
         );
 
         let async_client = client
             .activate_async(notification_handler, output_process_handler)
             .context("Failed to activate client")?;
 
-        StreamState::Paused.store(&playback_state, Ordering::Relaxed);
+        PlaybackState::Paused.store(&playback_state, Ordering::Relaxed);
         Ok(Self {
             playback_state,
+            state,
+            position,
+            xrun_policy,
+            connection_listener,
+            config,
             async_client,
+            timebase_callbacks: Mutex::default(),
             input_port_names: Box::default(),
             output_port_names: port_names.into_boxed_slice(),
+            fader: Some(fader),
+            capacity,
+            _own_client: own_client,
         })
     }
 
@@ -169,28 +270,8 @@ impl Stream {
     ///
     /// Returns `Err` only if an individual port-connection call fails, rolling back any
     /// connections already made so the JACK graph is left unchanged.
-    pub fn connect_to_system_outputs(&mut self) -> Result<(), Error> {
-        let client = self.async_client.as_client();
-        let system_ports = client.ports(Some("system:playback_.*"), None, jack::PortFlags::empty());
-
-        // Connect outputs from this client to the system playback inputs.
-        for (i, (our_port, system_port)) in
-            self.output_port_names.iter().zip(&system_ports).enumerate()
-        {
-            if let Err(e) = client.connect_ports_by_name(our_port, system_port) {
-                for (prev_our, prev_sys) in
-                    self.output_port_names[..i].iter().zip(&system_ports[..i])
-                {
-                    let _ = client.disconnect_ports_by_name(prev_our, prev_sys);
-                }
-
-                return Err(Error::with_message(
-                    ErrorKind::DeviceNotAvailable,
-                    format!("JACK failed to connect port '{our_port}' to '{system_port}': {e}"),
-                ));
-            }
-        }
-        Ok(())
+    pub fn connect_to_system_outputs(&self) -> Result<(), Error> {
+        self.connect_to_pattern("system:playback_.*")
     }
 
     /// Connects the stream's input ports to as many system capture ports as are available; must
@@ -200,47 +281,282 @@ impl Stream {
     ///
     /// Returns `Err` only if an individual port-connection call fails, rolling back any
     /// connections already made so the JACK graph is left unchanged.
-    pub fn connect_to_system_inputs(&mut self) -> Result<(), Error> {
+    pub fn connect_to_system_inputs(&self) -> Result<(), Error> {
+        self.connect_to_pattern("system:capture_.*")
+    }
+
+    /// Connects the stream's ports, in channel order, to the audio ports whose full names match
+    /// the regular expression `pattern`, in the order JACK lists them. Output streams connect to
+    /// matching input ports and input streams to matching output ports. Surplus ports on either
+    /// side are left unconnected.
+    ///
+    /// Returns `Err` if an individual port-connection call fails, rolling back any connections
+    /// already made so the JACK graph is left unchanged.
+    pub fn connect_to_pattern(&self, pattern: &str) -> Result<(), Error> {
+        check_port_name(pattern)?;
         let client = self.async_client.as_client();
-        let system_ports = client.ports(Some("system:capture_.*"), None, jack::PortFlags::empty());
+        let flags = if self.is_output() {
+            jack::PortFlags::IS_INPUT
+        } else {
+            jack::PortFlags::IS_OUTPUT
+        };
+        let audio_type = jack::AudioIn::default();
+        let others = client.ports(
+            Some(pattern),
+            Some(jack::PortSpec::jack_port_type(&audio_type)),
+            flags,
+        );
 
-        // Connect inputs from system capture ports to this client.
-        for (i, (system_port, our_port)) in
-            system_ports.iter().zip(&self.input_port_names).enumerate()
-        {
-            if let Err(e) = client.connect_ports_by_name(system_port, our_port) {
-                for (prev_sys, prev_our) in
-                    system_ports[..i].iter().zip(&self.input_port_names[..i])
-                {
-                    let _ = client.disconnect_ports_by_name(prev_sys, prev_our);
+        let connections: Vec<_> = self
+            .port_names()
+            .iter()
+            .zip(&others)
+            .map(|(ours, other)| self.orient(ours, other))
+            .collect();
+        for (i, &(source, destination)) in connections.iter().enumerate() {
+            if let Err(e) = client.connect_ports_by_name(source, destination) {
+                for &(source, destination) in &connections[..i] {
+                    let _ = client.disconnect_ports_by_name(source, destination);
                 }
 
                 return Err(Error::with_message(
                     ErrorKind::DeviceNotAvailable,
-                    format!("JACK failed to connect port '{system_port}' to '{our_port}': {e}"),
+                    format!("JACK failed to connect port '{source}' to '{destination}': {e}"),
                 ));
             }
         }
         Ok(())
     }
+
+    /// Returns the full names of the stream's ports, `client:port`, in channel order.
+    pub fn port_names(&self) -> &[String] {
+        if self.is_output() {
+            &self.output_port_names
+        } else {
+            &self.input_port_names
+        }
+    }
+
+    /// Lists the ports registered with the JACK server, of every client and type.
+    ///
+    /// `name_pattern` and `type_pattern` are regular expressions the port's full name and type
+    /// must match; `None` matches any.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::InvalidInput`] if a pattern contains a NUL byte.
+    ///
+    /// [`ErrorKind::InvalidInput`]: crate::ErrorKind::InvalidInput
+    pub fn ports(
+        &self,
+        name_pattern: Option<&str>,
+        type_pattern: Option<&str>,
+    ) -> Result<Vec<PortInfo>, Error> {
+        for pattern in [name_pattern, type_pattern].into_iter().flatten() {
+            check_port_name(pattern)?;
+        }
+        let client = self.async_client.as_client();
+        Ok(client
+            .ports(name_pattern, type_pattern, jack::PortFlags::empty())
+            .iter()
+            .filter_map(|name| client.port_by_name(name))
+            .filter_map(|port| PortInfo::new(&port))
+            .collect())
+    }
+
+    /// Returns the full names of the ports connected to the port of `channel`.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::InvalidInput`] if the stream has no such channel.
+    ///
+    /// [`ErrorKind::InvalidInput`]: crate::ErrorKind::InvalidInput
+    pub fn connections(&self, channel: usize) -> Result<Vec<String>, Error> {
+        let ours = self.channel_port(channel)?;
+        Ok(self
+            .async_client
+            .as_client()
+            .port_by_name(ours)
+            .map(|port| port.get_connections())
+            .unwrap_or_default())
+    }
+
+    /// Connects the port of `channel` to the port named `port`, in the direction audio flows:
+    /// from an output stream's port, or to an input stream's port.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::InvalidInput`] if the stream has no such channel or no port is named
+    ///   `port`.
+    /// - [`ErrorKind::BackendError`] if JACK refuses the connection, e.g. because the ports
+    ///   have different types or the same direction.
+    ///
+    /// [`ErrorKind::InvalidInput`]: crate::ErrorKind::InvalidInput
+    /// [`ErrorKind::BackendError`]: crate::ErrorKind::BackendError
+    pub fn connect(&self, channel: usize, port: &str) -> Result<(), Error> {
+        let (source, destination) = self.orient(self.channel_port(channel)?, port);
+        self.check_port_exists(port)?;
+        self.async_client
+            .as_client()
+            .connect_ports_by_name(source, destination)
+            .map_err(Error::from)
+    }
+
+    /// Disconnects the port of `channel` from the port named `port`.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::InvalidInput`] if the stream has no such channel or no port is named
+    ///   `port`.
+    /// - [`ErrorKind::BackendError`] if JACK fails to disconnect them, e.g. because they are
+    ///   not connected.
+    ///
+    /// [`ErrorKind::InvalidInput`]: crate::ErrorKind::InvalidInput
+    /// [`ErrorKind::BackendError`]: crate::ErrorKind::BackendError
+    pub fn disconnect(&self, channel: usize, port: &str) -> Result<(), Error> {
+        let (source, destination) = self.orient(self.channel_port(channel)?, port);
+        self.check_port_exists(port)?;
+        self.async_client
+            .as_client()
+            .disconnect_ports_by_name(source, destination)
+            .map_err(Error::from)
+    }
+
+    /// Sets a listener called each time two ports are connected or disconnected anywhere in the
+    /// JACK graph, replacing any previous listener.
+    ///
+    /// The listener runs on JACK's notification thread. It may call back into the stream, e.g.
+    /// to [`connections`](Self::connections), but must not call `set_connection_listener`.
+    pub fn set_connection_listener<F>(&self, listener: F)
+    where
+        F: FnMut(PortConnection) + Send + 'static,
+    {
+        *self
+            .connection_listener
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(Box::new(listener));
+    }
+
+    /// Returns a handle for reading and moving the JACK transport, which can be moved into the
+    /// data callback to follow the transport cycle by cycle.
+    pub fn transport(&self) -> Transport {
+        Transport::new(self.async_client.as_client())
+    }
+
+    /// Makes the stream's client the JACK timebase master, replacing its previous callback if it
+    /// already is. `callback` computes the musical position for each cycle while the transport
+    /// rolls, and for each relocation, on the process thread, so it must be real-time safe.
+    ///
+    /// With `conditional` set, this fails instead of taking over from another master.
+    ///
+    /// A replaced callback is kept until the stream is dropped, as JACK may still be running it.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::DeviceBusy`] if `conditional` is set and another client is the master.
+    /// - [`ErrorKind::BackendError`] if JACK refuses the callback for another reason.
+    ///
+    /// [`ErrorKind::DeviceBusy`]: crate::ErrorKind::DeviceBusy
+    /// [`ErrorKind::BackendError`]: crate::ErrorKind::BackendError
+    pub fn set_timebase_master<F>(&self, conditional: bool, callback: F) -> Result<(), Error>
+    where
+        F: FnMut(&TimebaseRequest) -> Bbt + Send + 'static,
+    {
+        let mut callback: Box<TimebaseCallback> = Box::new(Box::new(callback));
+        let mut callbacks = self
+            .timebase_callbacks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        transport::set_timebase_callback(
+            self.async_client.as_client(),
+            conditional,
+            &mut callback,
+        )?;
+        callbacks.push(callback);
+        Ok(())
+    }
+
+    /// Stops being the JACK timebase master. The transport keeps rolling without a musical
+    /// position until another client takes over.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::InvalidInput`] if the stream's client is not the timebase master.
+    ///
+    /// [`ErrorKind::InvalidInput`]: crate::ErrorKind::InvalidInput
+    pub fn release_timebase(&self) -> Result<(), Error> {
+        transport::release_timebase(self.async_client.as_client())
+    }
+
+    fn is_output(&self) -> bool {
+        !self.output_port_names.is_empty()
+    }
+
+    /// Orders one of the stream's ports and another port as (source, destination).
+    fn orient<'a>(&self, ours: &'a str, other: &'a str) -> (&'a str, &'a str) {
+        if self.is_output() {
+            (ours, other)
+        } else {
+            (other, ours)
+        }
+    }
+
+    fn channel_port(&self, channel: usize) -> Result<&str, Error> {
+        self.port_names()
+            .get(channel)
+            .map(String::as_str)
+            .ok_or_else(|| {
+                Error::with_message(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "channel {channel} is out of range for a stream with {} ports",
+                        self.port_names().len()
+                    ),
+                )
+            })
+    }
+
+    fn check_port_exists(&self, port: &str) -> Result<(), Error> {
+        check_port_name(port)?;
+        match self.async_client.as_client().port_by_name(port) {
+            Some(_) => Ok(()),
+            None => Err(Error::with_message(
+                ErrorKind::InvalidInput,
+                format!("no JACK port named '{port}'"),
+            )),
+        }
+    }
 }
 
 impl StreamTrait for Stream {
     fn start(&self) -> Result<(), Error> {
-        StreamState::Playing.store(&self.playback_state, Ordering::Relaxed);
+        PlaybackState::Playing.store(&self.playback_state, Ordering::Relaxed);
+        if let Some(fader) = &self.fader {
+            fader.fade_in();
+        }
+        self.state.set(StreamState::Running);
         Ok(())
     }
 
     fn pause(&self) -> Result<(), Error> {
-        StreamState::Paused.store(&self.playback_state, Ordering::Relaxed);
+        if let Some(fader) = &self.fader {
+            fader.fade_out(true, None);
+        }
+        PlaybackState::Paused.store(&self.playback_state, Ordering::Relaxed);
+        self.state.set(StreamState::Paused);
         Ok(())
     }
 
     fn stop(&self, timeout: Option<std::time::Duration>) -> Result<(), Error> {
-        StreamState::Paused.store(&self.playback_state, Ordering::Relaxed);
+        // The ramp drains with the rest of the buffered audio, so only wait for it to be written.
+        let spent = self
+            .fader
+            .as_ref()
+            .map_or(Duration::ZERO, |fader| fader.fade_out(false, timeout));
+        let timeout = timeout.map(|timeout| timeout.saturating_sub(spent));
+        PlaybackState::Paused.store(&self.playback_state, Ordering::Relaxed);
 
-        let is_output = !self.output_port_names.is_empty();
-        if is_output && timeout != Some(std::time::Duration::ZERO) {
+        if self.is_output() && timeout != Some(std::time::Duration::ZERO) {
             let client = self.async_client.as_client();
             let ports: Vec<_> = self
                 .output_port_names
@@ -252,9 +568,11 @@ impl StreamTrait for Stream {
             let buffered = frames_to_duration(latency_frames, client.sample_rate() as SampleRate);
             let wait = timeout.map_or(buffered, |t| buffered.min(t));
             if !wait.is_zero() {
+                self.state.set(StreamState::Draining);
                 std::thread::sleep(wait);
             }
         }
+        self.state.set(StreamState::Paused);
         Ok(())
     }
 
@@ -265,6 +583,78 @@ impl StreamTrait for Stream {
     fn buffer_size(&self) -> Result<FrameCount, Error> {
         Ok(self.async_client.as_client().buffer_size() as FrameCount)
     }
+
+    fn set_buffer_size(&self, frames: FrameCount) -> Result<(), Error> {
+        check_buffer_size(frames, self.capacity)?;
+        self.async_client
+            .as_client()
+            .set_buffer_size(frames)
+            .map_err(Error::from)
+    }
+
+    fn position(&self) -> Result<StreamPosition, Error> {
+        let advancing = matches!(
+            self.state.get(),
+            StreamState::Running | StreamState::Draining
+        );
+        Ok(self.position.get(self.now(), advancing))
+    }
+
+    fn state(&self) -> Result<StreamState, Error> {
+        Ok(self.state.get())
+    }
+
+    fn set_state_listener(&self, listener: StateListener) -> Result<(), Error> {
+        self.state.set_listener(listener);
+        Ok(())
+    }
+
+    fn set_xrun_policy(&self, policy: XrunPolicy) -> Result<(), Error> {
+        self.xrun_policy.set(policy);
+        Ok(())
+    }
+
+    fn set_config_listener(&self, listener: ConfigListener) -> Result<(), Error> {
+        self.config.set_listener(listener);
+        Ok(())
+    }
+
+    fn set_fade(&self, fade: Duration) -> Result<(), Error> {
+        let fader = self.fader.as_ref().ok_or_else(|| {
+            Error::with_message(ErrorKind::UnsupportedOperation, "input streams do not fade")
+        })?;
+        fader.set_fade(fade);
+        Ok(())
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        if let Some(fader) = &self.fader {
+            fader.fade_out(true, None);
+        }
+    }
+}
+
+/// Checks that a stream whose buffers hold `capacity` frames per channel can follow a server
+/// period of `frames`.
+fn check_buffer_size(frames: FrameCount, capacity: usize) -> Result<(), Error> {
+    // JACK periods are powers of two; the server rejects anything else.
+    if !frames.is_power_of_two() {
+        return Err(Error::with_message(
+            ErrorKind::InvalidInput,
+            format!("JACK buffer size must be a power of two, got {frames}"),
+        ));
+    }
+    // A larger period would invalidate the stream, as its buffers cannot grow on the process
+    // thread.
+    if frames as usize > capacity {
+        return Err(Error::with_message(
+            ErrorKind::UnsupportedConfig,
+            format!("JACK buffer size {frames} exceeds the supported maximum of {capacity}"),
+        ));
+    }
+    Ok(())
 }
 
 type InputDataCallback = Box<dyn FnMut(&Data, &CallbackInfo) + Send + 'static>;
@@ -284,8 +674,14 @@ struct LocalProcessHandler {
     temp_input_buffer: Vec<f32>,
     temp_output_buffer: Vec<f32>,
     playback_state: Arc<AtomicU8>,
+    position: Arc<PositionTracker>,
+    // Frames passed through the ports while playing.
+    frames_processed: u64,
     pending_xrun: Arc<AtomicBool>,
-    #[cfg(feature = "realtime")]
+    config: Arc<ConfigTracker>,
+    state: Arc<StateTracker>,
+    // Frames per channel the temporary buffers hold.
+    capacity: usize,
     error_callback: ErrorCallbackArc,
     #[cfg(feature = "realtime")]
     rt_checked: bool,
@@ -301,11 +697,15 @@ impl LocalProcessHandler {
         input_data_callback: Option<InputDataCallback>,
         output_data_callback: Option<OutputDataCallback>,
         playback_state: Arc<AtomicU8>,
+        position: Arc<PositionTracker>,
         pending_xrun: Arc<AtomicBool>,
-        #[cfg(feature = "realtime")] error_callback: ErrorCallbackArc,
+        config: Arc<ConfigTracker>,
+        state: Arc<StateTracker>,
+        capacity: usize,
+        error_callback: ErrorCallbackArc,
     ) -> Self {
-        let temp_input_buffer = vec![f32::EQUILIBRIUM; in_ports.len() * buffer_size];
-        let temp_output_buffer = vec![f32::EQUILIBRIUM; out_ports.len() * buffer_size];
+        let temp_input_buffer = vec![f32::EQUILIBRIUM; in_ports.len() * capacity];
+        let temp_output_buffer = vec![f32::EQUILIBRIUM; out_ports.len() * capacity];
 
         Self {
             out_ports,
@@ -317,8 +717,12 @@ impl LocalProcessHandler {
             temp_input_buffer,
             temp_output_buffer,
             playback_state,
+            position,
+            frames_processed: 0,
             pending_xrun,
-            #[cfg(feature = "realtime")]
+            config,
+            state,
+            capacity,
             error_callback,
             #[cfg(feature = "realtime")]
             rt_checked: false,
@@ -339,7 +743,7 @@ impl jack::ProcessHandler for LocalProcessHandler {
         client: &jack::Client,
         process_scope: &jack::ProcessScope,
     ) -> jack::Control {
-        if StreamState::load(&self.playback_state, Ordering::Relaxed) != StreamState::Playing {
+        if PlaybackState::load(&self.playback_state, Ordering::Relaxed) != PlaybackState::Playing {
             // JACK does not zero-fill output port buffers before calling the process handler
             for port in &mut self.out_ports {
                 port.as_mut_slice(process_scope).fill(f32::EQUILIBRIUM);
@@ -471,11 +875,31 @@ impl jack::ProcessHandler for LocalProcessHandler {
             };
             let info = CallbackInfo { timestamp, xrun };
             input_callback(&data, &info);
+
+            // By the cycle start this buffer and everything still in the capture path had been
+            // sampled.
+            let captured = self.frames_processed
+                + current_frame_count as u64
+                + u64::from(
+                    hardware_latency_frames(&self.in_ports, jack::LatencyType::Capture)
+                        .unwrap_or(0),
+                );
+            self.position
+                .report(captured, start_cycle_instant, u64::MAX);
         }
 
         if let Some(output_callback) = &mut self.output_data_callback {
             let num_out_channels = self.out_ports.len();
 
+            // Frames from earlier cycles still in the playback path at the cycle start.
+            let in_flight = hardware_latency_frames(&self.out_ports, jack::LatencyType::Playback)
+                .unwrap_or(current_frame_count as FrameCount);
+            self.position.report(
+                self.frames_processed.saturating_sub(u64::from(in_flight)),
+                start_cycle_instant,
+                self.frames_processed + current_frame_count as u64,
+            );
+
             let total = current_frame_count * num_out_channels;
             self.temp_output_buffer[..total].fill(f32::EQUILIBRIUM);
 
@@ -520,25 +944,53 @@ impl jack::ProcessHandler for LocalProcessHandler {
             }
         }
 
+        self.frames_processed += current_frame_count as u64;
+
         // Continue as normal
         jack::Control::Continue
     }
 
     fn buffer_size(&mut self, _: &jack::Client, size: jack::Frames) -> jack::Control {
-        // The `buffer_size` callback is actually called on the process thread, but
-        // it does not need to be suitable for real-time use. Thus we can simply allocate
-        // new buffers here. Details: https://github.com/RustAudio/rust-jack/issues/137
         let new_size = size as usize;
-        if new_size != self.buffer_size {
-            self.buffer_size = new_size;
-            self.temp_input_buffer = vec![f32::EQUILIBRIUM; self.in_ports.len() * new_size];
-            self.temp_output_buffer = vec![f32::EQUILIBRIUM; self.out_ports.len() * new_size];
+        if new_size == self.buffer_size {
+            return jack::Control::Continue;
+        }
+        // The buffers hold the server's largest period already, so a period change never
+        // allocates on the process thread. A server beyond that cannot be followed.
+        if new_size > self.capacity {
+            PlaybackState::Paused.store(&self.playback_state, Ordering::Relaxed);
+            if self.state.invalidate() {
+                emit_error(
+                    &self.error_callback,
+                    Error::with_message(
+                        ErrorKind::StreamInvalidated,
+                        format!(
+                            "JACK buffer size {new_size} exceeds the supported maximum of {}",
+                            self.capacity
+                        ),
+                    ),
+                );
+            }
+            return jack::Control::Quit;
         }
 
+        let previous = self.config(self.buffer_size);
+        self.buffer_size = new_size;
+        self.config.notify(previous, self.config(new_size));
         jack::Control::Continue
     }
 }
 
+impl LocalProcessHandler {
+    fn config(&self, buffer_size: usize) -> StreamConfig {
+        StreamConfig {
+            channels: (self.in_ports.len() + self.out_ports.len()) as ChannelCount,
+            sample_rate: self.sample_rate,
+            buffer_size: BufferSize::Fixed(buffer_size as FrameCount),
+        }
+    }
+}
+
 #[inline]
 fn micros_to_stream_instant(micros: u64) -> StreamInstant {
     StreamInstant::from_micros(micros)
@@ -566,29 +1018,46 @@ fn hardware_latency_frames<PS>(
 struct JackNotificationHandler {
     error_callback_ptr: ErrorCallbackArc,
     playback_state: Arc<AtomicU8>,
+    state: Arc<StateTracker>,
+    channels: ChannelCount,
     configured_sample_rate: jack::Frames,
     pending_xrun: Arc<AtomicBool>,
+    xrun_policy: Arc<XrunPolicyCell>,
+    connection_listener: ConnectionListenerSlot,
+    config: Arc<ConfigTracker>,
 }
 
 impl JackNotificationHandler {
+    #[expect(clippy::too_many_arguments)]
     pub fn new(
         error_callback_ptr: ErrorCallbackArc,
         playback_state: Arc<AtomicU8>,
+        state: Arc<StateTracker>,
+        channels: ChannelCount,
         configured_sample_rate: jack::Frames,
         pending_xrun: Arc<AtomicBool>,
+        xrun_policy: Arc<XrunPolicyCell>,
+        connection_listener: ConnectionListenerSlot,
+        config: Arc<ConfigTracker>,
     ) -> Self {
         JackNotificationHandler {
             error_callback_ptr,
             playback_state,
+            state,
+            channels,
             configured_sample_rate,
             pending_xrun,
+            xrun_policy,
+            connection_listener,
+            config,
         }
     }
 }
 
 impl jack::NotificationHandler for JackNotificationHandler {
     unsafe fn shutdown(&mut self, _status: jack::ClientStatus, reason: &str) {
-        if StreamState::load(&self.playback_state, Ordering::Relaxed) == StreamState::Starting {
+        self.state.set(StreamState::Invalidated);
+        if PlaybackState::load(&self.playback_state, Ordering::Relaxed) == PlaybackState::Starting {
             return;
         }
         emit_error(
@@ -600,12 +1069,26 @@ impl jack::NotificationHandler for JackNotificationHandler {
         );
     }
 
-    fn sample_rate(&mut self, _: &jack::Client, srate: jack::Frames) -> jack::Control {
+    fn sample_rate(&mut self, client: &jack::Client, srate: jack::Frames) -> jack::Control {
         if srate == self.configured_sample_rate {
             // One of these notifications is sent every time a client is started.
             return jack::Control::Continue;
         }
-        if StreamState::load(&self.playback_state, Ordering::Relaxed) != StreamState::Starting {
+        // The data callback expects the configured rate, so the stream cannot follow.
+        let previous = StreamConfig {
+            channels: self.channels,
+            sample_rate: self.configured_sample_rate,
+            buffer_size: BufferSize::Fixed(client.buffer_size()),
+        };
+        self.config.notify(
+            previous,
+            StreamConfig {
+                sample_rate: srate,
+                ..previous
+            },
+        );
+        self.state.set(StreamState::Invalidated);
+        if PlaybackState::load(&self.playback_state, Ordering::Relaxed) != PlaybackState::Starting {
             emit_error(
                 &self.error_callback_ptr,
                 Error::with_message(
@@ -618,9 +1101,88 @@ impl jack::NotificationHandler for JackNotificationHandler {
     }
 
     fn xrun(&mut self, _: &jack::Client) -> jack::Control {
-        if StreamState::load(&self.playback_state, Ordering::Relaxed) != StreamState::Starting {
-            self.pending_xrun.store(true, Ordering::Relaxed);
+        if PlaybackState::load(&self.playback_state, Ordering::Relaxed) == PlaybackState::Starting {
+            return jack::Control::Continue;
+        }
+        // The server recovers from xruns by itself; the policy only decides what happens next.
+        match self.xrun_policy.get().recovery {
+            XrunRecovery::Silent => {
+                self.pending_xrun.store(true, Ordering::Relaxed);
+            }
+            XrunRecovery::Notify => {
+                self.pending_xrun.store(true, Ordering::Relaxed);
+                emit_error(
+                    &self.error_callback_ptr,
+                    Error::with_message(ErrorKind::Xrun, "JACK server reported an xrun"),
+                );
+            }
+            XrunRecovery::Stop => {
+                PlaybackState::Paused.store(&self.playback_state, Ordering::Relaxed);
+                if self.state.invalidate() {
+                    emit_error(
+                        &self.error_callback_ptr,
+                        Error::with_message(
+                            ErrorKind::StreamInvalidated,
+                            "JACK server reported an xrun",
+                        ),
+                    );
+                }
+                return jack::Control::Quit;
+            }
         }
         jack::Control::Continue
     }
+
+    fn ports_connected(
+        &mut self,
+        client: &jack::Client,
+        port_id_a: jack::PortId,
+        port_id_b: jack::PortId,
+        are_connected: bool,
+    ) {
+        let mut listener = self
+            .connection_listener
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let Some(listener) = listener.as_mut() else {
+            return;
+        };
+        let (Some(a), Some(b)) = (client.port_by_id(port_id_a), client.port_by_id(port_id_b))
+        else {
+            return;
+        };
+        let (Ok(name_a), Ok(name_b)) = (a.name(), b.name()) else {
+            return;
+        };
+        // JACK does not promise which of the two ports is the source.
+        let (source, destination) = if a.flags().contains(jack::PortFlags::IS_OUTPUT) {
+            (name_a, name_b)
+        } else {
+            (name_b, name_a)
+        };
+        listener(PortConnection {
+            source,
+            destination,
+            connected: are_connected,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MAX_BUFFER_SIZE, check_buffer_size};
+    use crate::ErrorKind;
+
+    #[test]
+    fn buffer_size_within_capacity() {
+        assert!(check_buffer_size(256, MAX_BUFFER_SIZE).is_ok());
+        assert!(check_buffer_size(MAX_BUFFER_SIZE as u32, MAX_BUFFER_SIZE).is_ok());
+
+        let err = check_buffer_size(300, MAX_BUFFER_SIZE).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        // The stream would have to be invalidated rather than follow this period.
+        let err = check_buffer_size(16384, MAX_BUFFER_SIZE).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedConfig);
+    }
 }
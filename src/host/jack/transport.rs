@@ -0,0 +1,243 @@
+//! Following and driving the JACK transport.
+
+use std::{
+    ffi::{c_int, c_void},
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::Arc,
+};
+
+use jack::jack_sys as j;
+
+use crate::{Error, ErrorKind, FrameCount, SampleRate};
+
+/// Whether the JACK transport is moving.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TransportState {
+    /// The transport is stopped.
+    Stopped,
+    /// The transport is rolling.
+    Rolling,
+    /// The transport is about to roll, waiting for slow-sync clients to get ready.
+    Starting,
+}
+
+impl From<jack::TransportState> for TransportState {
+    fn from(state: jack::TransportState) -> Self {
+        match state {
+            jack::TransportState::Stopped => Self::Stopped,
+            jack::TransportState::Rolling => Self::Rolling,
+            jack::TransportState::Starting => Self::Starting,
+        }
+    }
+}
+
+/// A musical position in bars, beats and ticks, with the meter and tempo at that point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bbt {
+    /// Bar, counting from 1.
+    pub bar: u32,
+    /// Beat within the bar, counting from 1.
+    pub beat: u32,
+    /// Tick within the beat, counting from 0.
+    pub tick: u32,
+    /// Ticks from the start of the timeline to the first beat of the bar.
+    pub bar_start_tick: f64,
+    /// Time signature numerator.
+    pub beats_per_bar: f32,
+    /// Time signature denominator.
+    pub beat_type: f32,
+    /// Ticks per beat, usually a number with many divisors such as 1920.
+    pub ticks_per_beat: f64,
+    /// Tempo, averaged over the cycle if it changes within it.
+    pub beats_per_minute: f64,
+}
+
+impl From<jack::TransportBBT> for Bbt {
+    fn from(bbt: jack::TransportBBT) -> Self {
+        Self {
+            bar: bbt.bar as u32,
+            beat: bbt.beat as u32,
+            tick: bbt.tick as u32,
+            bar_start_tick: bbt.bar_start_tick,
+            beats_per_bar: bbt.sig_num,
+            beat_type: bbt.sig_denom,
+            ticks_per_beat: bbt.ticks_per_beat,
+            beats_per_minute: bbt.bpm,
+        }
+    }
+}
+
+impl From<Bbt> for jack::TransportBBT {
+    fn from(bbt: Bbt) -> Self {
+        Self {
+            bar: bbt.bar as usize,
+            beat: bbt.beat as usize,
+            tick: bbt.tick as usize,
+            sig_num: bbt.beats_per_bar,
+            sig_denom: bbt.beat_type,
+            ticks_per_beat: bbt.ticks_per_beat,
+            bpm: bbt.beats_per_minute,
+            bar_start_tick: bbt.bar_start_tick,
+        }
+    }
+}
+
+/// The transport state and position, as returned by [`Transport::query`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TransportPosition {
+    /// Whether the transport is moving.
+    pub state: TransportState,
+    /// Frame on the transport timeline.
+    pub frame: FrameCount,
+    /// Sample rate of the timeline.
+    pub frame_rate: SampleRate,
+    /// Musical position, if the timebase master provides one.
+    pub bbt: Option<Bbt>,
+}
+
+impl TransportPosition {
+    fn new(state: jack::TransportState, pos: &jack::TransportPosition) -> Self {
+        Self {
+            state: state.into(),
+            frame: pos.frame(),
+            frame_rate: pos.frame_rate().unwrap_or(0),
+            bbt: pos.bbt().map(Bbt::from),
+        }
+    }
+}
+
+/// A handle for reading and moving the JACK transport, obtained from
+/// [`JackStream::transport`](crate::platform::JackStream::transport).
+///
+/// The handle can be cloned and moved into a data callback. All its methods are real-time safe,
+/// and fail with [`ErrorKind::StreamInvalidated`] once the stream is dropped.
+///
+/// [`ErrorKind::StreamInvalidated`]: crate::ErrorKind::StreamInvalidated
+#[derive(Clone)]
+pub struct Transport(Arc<jack::Transport>);
+
+impl std::fmt::Debug for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Transport").finish_non_exhaustive()
+    }
+}
+
+impl Transport {
+    pub(super) fn new(client: &jack::Client) -> Self {
+        Self(Arc::new(client.transport()))
+    }
+
+    /// Returns the transport state and position. Called from a data callback, they hold for the
+    /// first frame of the buffer and the rest of the cycle.
+    pub fn query(&self) -> Result<TransportPosition, Error> {
+        let jack::TransportStatePosition { pos, state } = self.0.query()?;
+        Ok(TransportPosition::new(state, &pos))
+    }
+
+    /// Starts the transport rolling, no sooner than the next cycle.
+    pub fn start(&self) -> Result<(), Error> {
+        self.0.start().map_err(Error::from)
+    }
+
+    /// Stops the transport on the next cycle.
+    pub fn stop(&self) -> Result<(), Error> {
+        self.0.stop().map_err(Error::from)
+    }
+
+    /// Moves the transport to `frame` on its timeline, taking effect within two cycles.
+    pub fn locate(&self, frame: FrameCount) -> Result<(), Error> {
+        self.0.locate(frame).map_err(Error::from)
+    }
+}
+
+/// What a timebase master is asked for each cycle: the musical position of the transport.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimebaseRequest {
+    /// The transport at the start of the cycle. Its `bbt` is what the master returned for the
+    /// previous cycle, unless `new_position` is set.
+    pub position: TransportPosition,
+    /// Frames in the cycle.
+    pub frames: FrameCount,
+    /// The transport was relocated, or the master was just installed, so the position must be
+    /// computed from `frame` rather than advanced from the previous one.
+    pub new_position: bool,
+}
+
+/// Computes the musical position as timebase master, on the JACK process thread.
+pub(super) type TimebaseCallback = Box<dyn FnMut(&TimebaseRequest) -> Bbt + Send + 'static>;
+
+/// Registers `callback` as the client's timebase callback. It must stay alive, and at the same
+/// address, for as long as the client may call it.
+pub(super) fn set_timebase_callback(
+    client: &jack::Client,
+    conditional: bool,
+    callback: &mut TimebaseCallback,
+) -> Result<(), Error> {
+    let arg: *mut TimebaseCallback = callback;
+    let code = unsafe {
+        j::jack_set_timebase_callback(
+            client.raw(),
+            c_int::from(conditional),
+            Some(timebase_callback),
+            arg.cast(),
+        )
+    };
+    if code == 0 {
+        return Ok(());
+    }
+    // A conditional request fails with EBUSY when another client is already master.
+    let error =
+        if std::io::Error::from_raw_os_error(code).kind() == std::io::ErrorKind::ResourceBusy {
+            Error::with_message(
+                ErrorKind::DeviceBusy,
+                "another JACK client is already the timebase master",
+            )
+        } else {
+            Error::with_message(
+                ErrorKind::BackendError,
+                "JACK refused to install the timebase callback",
+            )
+        };
+    Err(error.with_backend_code(code))
+}
+
+/// Gives up being timebase master, if the client is.
+pub(super) fn release_timebase(client: &jack::Client) -> Result<(), Error> {
+    match unsafe { j::jack_release_timebase(client.raw()) } {
+        0 => Ok(()),
+        code => Err(Error::with_message(
+            ErrorKind::InvalidInput,
+            "the JACK client is not the timebase master",
+        )
+        .with_backend_code(code)),
+    }
+}
+
+unsafe extern "C" fn timebase_callback(
+    state: j::jack_transport_state_t,
+    nframes: j::jack_nframes_t,
+    pos: *mut j::jack_position_t,
+    new_pos: c_int,
+    arg: *mut c_void,
+) {
+    // SAFETY: `arg` is the callback registered in `set_timebase_callback`, which outlives the
+    // client, and JACK only calls it from the process thread. `jack::TransportPosition` is a
+    // transparent wrapper of `jack_position_t`.
+    let callback = unsafe { &mut *arg.cast::<TimebaseCallback>() };
+    let pos = unsafe { &mut *pos.cast::<jack::TransportPosition>() };
+    let state = match state {
+        j::JackTransportStopped => jack::TransportState::Stopped,
+        j::JackTransportStarting => jack::TransportState::Starting,
+        _ => jack::TransportState::Rolling,
+    };
+    let request = TimebaseRequest {
+        position: TransportPosition::new(state, pos),
+        frames: nframes,
+        new_position: new_pos != 0,
+    };
+    // Unwinding into JACK is undefined behavior.
+    let bbt = catch_unwind(AssertUnwindSafe(|| callback(&request))).ok();
+    if pos.set_bbt(bbt.map(jack::TransportBBT::from)).is_err() {
+        let _ = pos.set_bbt(None);
+    }
+}
@@ -55,6 +55,19 @@ impl Latch {
         self.threads.push(thread);
     }
 
+    /// Returns `true` if [`release`](Self::release) has been called.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+    ))]
+    pub(crate) fn is_released(&self) -> bool {
+        self.flag
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Acquire))
+    }
+
     /// Releases the latch and unparks all registered threads.
     pub(crate) fn release(&self) {
         if let Some(flag) = &self.flag {
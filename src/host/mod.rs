@@ -1,3 +1,18 @@
+// DoP streams use the DSD silence byte on every target; the rest serves the native hosts.
+#[cfg_attr(
+    not(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "windows",
+        target_vendor = "apple",
+        target_os = "android",
+    )),
+    allow(dead_code)
+)]
+pub(crate) mod equilibrium;
+
 #[cfg(any(
     target_os = "linux",
     target_os = "dragonfly",
@@ -5,9 +20,8 @@
     target_os = "netbsd",
     target_os = "windows",
     target_vendor = "apple",
-    target_os = "android",
 ))]
-pub(crate) mod equilibrium;
+pub(crate) mod fade;
 
 #[cfg(windows)]
 pub(crate) mod com;
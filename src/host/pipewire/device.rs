@@ -1,5 +1,5 @@
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     fmt,
     hash::{Hash, Hasher},
     rc::Rc,
@@ -29,19 +29,25 @@ use super::stream::Stream;
 use crate::{
     BufferSize, CallbackInfo, ChannelCount, Data, DeviceDescription, DeviceDescriptionBuilder,
     DeviceDirection, DeviceId, DeviceType, Error, ErrorKind, FrameCount, HostId, InterfaceType,
-    SampleFormat, SampleRate, StreamConfig, SupportedBufferSize, SupportedStreamConfig,
-    SupportedStreamConfigRange,
+    PipeWireTarget, SampleFormat, SampleRate, StreamConfig, StreamOptions, SupportedBufferSize,
+    SupportedStreamConfig, SupportedStreamConfigRange,
+    config_change::ConfigTracker,
     host::{
         Notify, emit_error,
+        fade::{self, Fader},
         latch::Latch,
         pipewire::{
+            links::PortGraph,
             stream::{
                 DefaultDeviceMonitor, PwInitGuard, SUPPORTED_FORMATS, StreamCommand, StreamData,
+                set_node_latency,
             },
             utils::{DEVICE_ICON_NAME, METADATA_NAME, audio, clock, default, node},
         },
     },
     iter::{SupportedInputConfigs, SupportedOutputConfigs},
+    position::PositionTracker,
+    stream_state::StateTracker,
     traits::DeviceTrait,
 };
 
@@ -57,6 +63,8 @@ pub(crate) enum Class {
     DefaultSink,
     DefaultInput,
     DefaultOutput,
+    // Published by cpal itself through `Host::virtual_sink` or `Host::virtual_source`
+    Virtual,
 }
 
 #[derive(Clone, Debug, Default, Copy)]
@@ -69,6 +77,13 @@ enum Role {
     StreamInput,
 }
 
+/// Which kind of node a virtual device publishes.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum VirtualRole {
+    Sink,
+    Source,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Device {
     node_name: String,
@@ -88,6 +103,8 @@ pub struct Device {
     address: Option<String>,
     driver: Option<String>,
     connect_automatically: Arc<AtomicBool>,
+    // Set by a stream whose connection broke, so the host knows its devices are stale.
+    daemon_lost: Arc<AtomicBool>,
 }
 
 impl Device {
@@ -129,7 +146,63 @@ impl Device {
         }
     }
 
+    /// A device whose streams publish an `Audio/Sink` or `Audio/Source` node named `node_name`,
+    /// running at the clock of `template` and sharing its host settings.
+    pub(crate) fn virtual_node(
+        role: VirtualRole,
+        node_name: &str,
+        description: &str,
+        channels: ChannelCount,
+        template: &Device,
+    ) -> Result<Self, Error> {
+        if node_name.is_empty() {
+            return Err(Error::with_message(
+                ErrorKind::InvalidInput,
+                "PipeWire node names must not be empty",
+            ));
+        }
+        if node_name.contains('\0') || description.contains('\0') {
+            return Err(Error::with_message(
+                ErrorKind::InvalidInput,
+                "PipeWire node properties must not contain NUL bytes",
+            ));
+        }
+        if channels == 0 {
+            return Err(Error::with_message(
+                ErrorKind::InvalidInput,
+                "A virtual node needs at least one channel",
+            ));
+        }
+        // Other applications play into a sink, so cpal reads from it, and the other way around
+        // for a source.
+        let (role, direction) = match role {
+            VirtualRole::Sink => (Role::Sink, DeviceDirection::Input),
+            VirtualRole::Source => (Role::Source, DeviceDirection::Output),
+        };
+        Ok(Self {
+            node_name: node_name.to_owned(),
+            description: description.to_owned(),
+            direction,
+            channels,
+            class: Class::Virtual,
+            role,
+            icon_name: "default".to_owned(),
+            interface_type: InterfaceType::Virtual,
+            address: None,
+            driver: None,
+            object_serial: 0,
+            ..template.clone()
+        })
+    }
+
+    fn is_virtual(&self) -> bool {
+        matches!(self.class, Class::Virtual)
+    }
+
     fn device_type(&self) -> DeviceType {
+        if self.is_virtual() {
+            return DeviceType::Virtual;
+        }
         match self.icon_name.as_str() {
             "audio-headphones" => DeviceType::Headphones,
             "audio-headset" => DeviceType::Headset,
@@ -145,7 +218,7 @@ impl Device {
         match self.class {
             Class::DefaultOutput | Class::DefaultSink => Some(default::SINK),
             Class::DefaultInput => Some(default::SOURCE),
-            Class::Node => None,
+            Class::Node | Class::Virtual => None,
         }
     }
 
@@ -153,6 +226,7 @@ impl Device {
         &self,
         direction: DeviceDirection,
         config: &StreamConfig,
+        extra: &[(String, String)],
     ) -> PropertiesBox {
         let mut properties = match direction {
             DeviceDirection::Output => pw::properties::properties! {
@@ -165,7 +239,13 @@ impl Device {
             },
             _ => unreachable!(),
         };
-        if matches!(self.role, Role::Sink) && matches!(direction, DeviceDirection::Input) {
+        if self.is_virtual() {
+            let media_class = match self.role {
+                Role::Source => audio::SOURCE,
+                _ => audio::SINK,
+            };
+            properties.insert(*pw::keys::MEDIA_CLASS, media_class);
+        } else if matches!(self.role, Role::Sink) && matches!(direction, DeviceDirection::Input) {
             properties.insert(*pw::keys::STREAM_CAPTURE_SINK, "true");
         }
         if matches!(self.class, Class::Node) {
@@ -182,8 +262,41 @@ impl Device {
                 format!("{buffer_size}/{rate}", rate = config.sample_rate),
             );
         }
+        for (key, value) in extra {
+            properties.insert(key.as_str(), value.as_str());
+        }
+        // Other clients find a virtual node by its name, so a stream name must not replace it.
+        if self.is_virtual() {
+            properties.insert(*pw::keys::NODE_NAME, self.node_name.as_str());
+            properties.insert(*pw::keys::NODE_DESCRIPTION, self.description.as_str());
+        }
         properties
     }
+
+    /// One range per supported format, restricted to the graph's allowed rates when the
+    /// clock settings list any, otherwise fixed at the device rate.
+    fn supported_configs(&self) -> std::vec::IntoIter<SupportedStreamConfigRange> {
+        let (min_rate, max_rate, sample_rates) =
+            match (self.allow_rates.first(), self.allow_rates.last()) {
+                (Some(&min), Some(&max)) => (min, max, Some(Arc::clone(&self.allow_rates))),
+                _ => (self.rate, self.rate, None),
+            };
+        SUPPORTED_FORMATS
+            .iter()
+            .map(|&sample_format| SupportedStreamConfigRange {
+                channels: self.channels,
+                min_sample_rate: min_rate,
+                max_sample_rate: max_rate,
+                sample_rates: sample_rates.clone(),
+                buffer_size: SupportedBufferSize::Range {
+                    min: self.min_quantum,
+                    max: self.max_quantum,
+                },
+                sample_format,
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
 }
 
 impl PartialEq for Device {
@@ -207,6 +320,80 @@ impl Hash for Device {
     }
 }
 
+/// Collects the node properties requested through the stream options: naming and media role
+/// first, then the PipeWire-specific ones, which may override them.
+///
+/// The stream name is a human-readable label, so it goes into `media.name` and
+/// `node.description` and leaves `node.name`, the identifier other clients look nodes up by, to
+/// PipeWire.
+///
+/// PipeWire properties are C strings, so user-supplied ones must not contain NUL bytes.
+fn stream_properties(
+    options: &StreamOptions,
+    config: &StreamConfig,
+) -> Result<Vec<(String, String)>, Error> {
+    let pipewire = &options.pipewire;
+    if [pipewire.rate, pipewire.latency, pipewire.force_quantum].contains(&Some(0)) {
+        return Err(Error::with_message(
+            ErrorKind::InvalidInput,
+            "PipeWire node rates and quanta must not be zero",
+        ));
+    }
+    let mut properties = Vec::new();
+    if let Some(name) = &options.application_name {
+        properties.push(((*pw::keys::APP_NAME).to_owned(), name.clone()));
+    }
+    if let Some(name) = &options.stream_name {
+        properties.push(((*pw::keys::MEDIA_NAME).to_owned(), name.clone()));
+        properties.push(((*pw::keys::NODE_DESCRIPTION).to_owned(), name.clone()));
+    }
+    if let Some(icon) = &options.icon_name {
+        properties.push(((*pw::keys::APP_ICON_NAME).to_owned(), icon.clone()));
+    }
+    if let Some(role) = options.media_role {
+        properties.push((
+            (*pw::keys::MEDIA_ROLE).to_owned(),
+            role.pipewire_role().to_owned(),
+        ));
+    }
+    if let Some(rate) = pipewire.rate {
+        properties.push((node::RATE.to_owned(), format!("1/{rate}")));
+    }
+    if let Some(latency) = pipewire.latency {
+        properties.push((
+            node::LATENCY.to_owned(),
+            format!("{latency}/{rate}", rate = config.sample_rate),
+        ));
+    }
+    if let Some(quantum) = pipewire.force_quantum {
+        properties.push((node::FORCE_QUANTUM.to_owned(), quantum.to_string()));
+    }
+    if pipewire.lock_quantum {
+        properties.push((node::LOCK_QUANTUM.to_owned(), "true".to_owned()));
+    }
+    match &pipewire.target {
+        Some(PipeWireTarget::Serial(serial)) => {
+            properties.push(((*pw::keys::TARGET_OBJECT).to_owned(), serial.to_string()));
+        }
+        Some(PipeWireTarget::Name(name)) => {
+            properties.push(((*pw::keys::TARGET_OBJECT).to_owned(), name.clone()));
+        }
+        None => {}
+    }
+    properties.extend(pipewire.properties.iter().cloned());
+
+    if properties
+        .iter()
+        .any(|(key, value)| key.contains('\0') || value.contains('\0'))
+    {
+        return Err(Error::with_message(
+            ErrorKind::InvalidInput,
+            "PipeWire node properties must not contain NUL bytes",
+        ));
+    }
+    Ok(properties)
+}
+
 impl DeviceTrait for Device {
     type Stream = Stream;
     type SupportedInputConfigs = SupportedInputConfigs;
@@ -218,6 +405,7 @@ impl DeviceTrait for Device {
 
     fn description(&self) -> Result<DeviceDescription, Error> {
         let mut builder = DeviceDescriptionBuilder::new(&self.description)
+            .stable_name(&self.node_name)
             .direction(self.direction)
             .device_type(self.device_type())
             .interface_type(self.interface_type);
@@ -248,57 +436,13 @@ impl DeviceTrait for Device {
         if !self.supports_input() {
             return Ok(vec![].into_iter());
         }
-        let rates: &[SampleRate] = if self.allow_rates.is_empty() {
-            &[self.rate]
-        } else {
-            &self.allow_rates
-        };
-        Ok(rates
-            .iter()
-            .flat_map(|&rate| {
-                SUPPORTED_FORMATS
-                    .iter()
-                    .map(move |sample_format| SupportedStreamConfigRange {
-                        channels: self.channels,
-                        min_sample_rate: rate,
-                        max_sample_rate: rate,
-                        buffer_size: SupportedBufferSize::Range {
-                            min: self.min_quantum,
-                            max: self.max_quantum,
-                        },
-                        sample_format: *sample_format,
-                    })
-            })
-            .collect::<Vec<_>>()
-            .into_iter())
+        Ok(self.supported_configs())
     }
     fn supported_output_configs(&self) -> Result<Self::SupportedOutputConfigs, Error> {
         if !self.supports_output() {
             return Ok(vec![].into_iter());
         }
-        let rates: &[SampleRate] = if self.allow_rates.is_empty() {
-            &[self.rate]
-        } else {
-            &self.allow_rates
-        };
-        Ok(rates
-            .iter()
-            .flat_map(|&rate| {
-                SUPPORTED_FORMATS
-                    .iter()
-                    .map(move |sample_format| SupportedStreamConfigRange {
-                        channels: self.channels,
-                        min_sample_rate: rate,
-                        max_sample_rate: rate,
-                        buffer_size: SupportedBufferSize::Range {
-                            min: self.min_quantum,
-                            max: self.max_quantum,
-                        },
-                        sample_format: *sample_format,
-                    })
-            })
-            .collect::<Vec<_>>()
-            .into_iter())
+        Ok(self.supported_configs())
     }
     fn default_input_config(&self) -> Result<SupportedStreamConfig, Error> {
         if !self.supports_input() {
@@ -344,11 +488,37 @@ impl DeviceTrait for Device {
         error_callback: E,
         timeout: Option<std::time::Duration>,
     ) -> Result<Self::Stream, Error>
+    where
+        D: FnMut(&Data, &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        let options = StreamOptions {
+            timeout,
+            ..Default::default()
+        };
+        self.build_input_stream_with_options(
+            config,
+            sample_format,
+            &options,
+            data_callback,
+            error_callback,
+        )
+    }
+
+    fn build_input_stream_with_options<D, E>(
+        &self,
+        config: StreamConfig,
+        sample_format: SampleFormat,
+        options: &StreamOptions,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, Error>
     where
         D: FnMut(&Data, &CallbackInfo) + Send + 'static,
         E: FnMut(Error) + Send + 'static,
     {
         crate::validate_stream_config(&config)?;
+        let extra_properties = stream_properties(options, &config)?;
         if let BufferSize::Fixed(n) = config.buffer_size {
             // When max_quantum is 0 the server clock metadata has not been received yet.
             if self.max_quantum > 0 && !(self.min_quantum..=self.max_quantum).contains(&n) {
@@ -368,7 +538,15 @@ impl DeviceTrait for Device {
         let mut latch = Latch::new();
         let waiter = latch.waiter();
         let device = self.clone();
-        let wait_timeout = timeout.unwrap_or(Duration::from_secs(2));
+        let wait_timeout = options.timeout.unwrap_or(Duration::from_secs(2));
+        // A virtual node is what others link to, so it is never linked on its own.
+        let connect_automatically = !self.is_virtual()
+            && options
+                .pipewire
+                .connect_automatically
+                .unwrap_or_else(|| self.connect_automatically.load(Ordering::Relaxed));
+        // A stream with a target stays on it instead of following the default device.
+        let follows_default = options.pipewire.target.is_none();
         let initial_quantum = match config.buffer_size {
             BufferSize::Fixed(n) => n,
             BufferSize::Default => self.quantum,
@@ -377,6 +555,12 @@ impl DeviceTrait for Device {
         let last_quantum_clone = last_quantum.clone();
         let draining = Arc::new(AtomicBool::new(false));
         let draining_clone = draining.clone();
+        let state = Arc::new(StateTracker::new());
+        let state_clone = state.clone();
+        let position = Arc::new(PositionTracker::new(config.sample_rate));
+        let position_clone = position.clone();
+        let config_tracker = Arc::new(ConfigTracker::default());
+        let config_tracker_clone = config_tracker.clone();
         // Keep `capture` monotonic: pw_time delay() grows when another client joins
         // needing a larger buffer, which can pull `capture` backward.
         let data_callback = crate::host::monotonic_input_callback(data_callback);
@@ -385,7 +569,8 @@ impl DeviceTrait for Device {
             .name("pw_in".to_owned())
             .spawn(move || {
                 let _pw = PwInitGuard::new();
-                let properties = device.pw_properties(DeviceDirection::Input, &config);
+                let properties =
+                    device.pw_properties(DeviceDirection::Input, &config, &extra_properties);
 
                 let stream_data = match super::stream::connect_input(
                     super::stream::ConnectParams {
@@ -394,13 +579,19 @@ impl DeviceTrait for Device {
                         sample_format,
                         last_quantum: last_quantum_clone,
                         start,
-                        connect_automatically: device.connect_automatically.load(Ordering::Relaxed),
+                        connect_automatically,
                         draining: draining_clone,
                         drained: None,
-                        is_default_device: matches!(
-                            device.class(),
-                            Class::DefaultSink | Class::DefaultInput | Class::DefaultOutput
-                        ),
+                        is_default_device: follows_default
+                            && matches!(
+                                device.class(),
+                                Class::DefaultSink | Class::DefaultInput | Class::DefaultOutput
+                            ),
+                        state: state_clone,
+                        position: position_clone,
+                        config_tracker: config_tracker_clone,
+                        daemon_lost: device.daemon_lost.clone(),
+                        fader: None,
                     },
                     data_callback,
                     error_callback,
@@ -424,32 +615,44 @@ impl DeviceTrait for Device {
                     core_monitor,
                     error_callback,
                     pending_device_changed,
-                    invalidated,
+                    state,
                 } = stream_data;
 
-                let default_monitor = if let Some(key) = device.default_metadata_key() {
-                    match core.get_registry_rc() {
-                        Ok(registry) => Some(DefaultDeviceMonitor::new(
-                            registry,
-                            key,
-                            error_callback.clone(),
-                            invalidated,
-                            pending_device_changed,
-                        )),
-                        Err(e) => {
-                            let _ = init_tx.send(Err(Error::with_message(
-                                ErrorKind::BackendError,
-                                format!("Could not acquire registry: {e}"),
-                            )));
-                            return;
+                let default_monitor =
+                    if let Some(key) = device.default_metadata_key().filter(|_| follows_default) {
+                        match core.get_registry_rc() {
+                            Ok(registry) => Some(DefaultDeviceMonitor::new(
+                                registry,
+                                key,
+                                error_callback.clone(),
+                                state,
+                                pending_device_changed,
+                            )),
+                            Err(e) => {
+                                let _ = init_tx.send(Err(Error::with_message(
+                                    ErrorKind::BackendError,
+                                    format!("Could not acquire registry: {e}"),
+                                )));
+                                return;
+                            }
                         }
+                    } else {
+                        None
+                    };
+                let port_graph = match PortGraph::new(core.clone()) {
+                    Ok(port_graph) => port_graph,
+                    Err(e) => {
+                        let _ = init_tx.send(Err(Error::with_message(
+                            ErrorKind::BackendError,
+                            format!("Could not acquire registry: {e}"),
+                        )));
+                        return;
                     }
-                } else {
-                    None
                 };
                 let stream_clone = stream.clone();
                 let mainloop_rc1 = mainloop.clone();
                 let error_callback_cmd = error_callback.clone();
+                let rate = config.sample_rate;
                 let _receiver = pw_play_rx.attach(mainloop.loop_(), move |play| match play {
                     StreamCommand::Toggle(state) => {
                         if let Err(e) = stream_clone.set_active(state) {
@@ -463,6 +666,37 @@ impl DeviceTrait for Device {
                         }
                     }
                     StreamCommand::Drain => {}
+                    StreamCommand::Flush => {
+                        if let Err(e) = stream_clone.flush(false) {
+                            emit_error(
+                                &error_callback_cmd,
+                                Error::with_message(
+                                    ErrorKind::BackendError,
+                                    format!("Stream flush failed: {e}"),
+                                ),
+                            );
+                        }
+                    }
+                    StreamCommand::SetBufferSize { frames, reply } => {
+                        let _ = reply.send(set_node_latency(&stream_clone, frames, rate));
+                    }
+                    StreamCommand::NodeId(reply) => {
+                        let _ = reply.send(stream_clone.node_id());
+                    }
+                    StreamCommand::Ports(reply) => {
+                        let _ = reply.send(port_graph.ports());
+                    }
+                    StreamCommand::Link {
+                        channel,
+                        port,
+                        reply,
+                    } => {
+                        let node_id = stream_clone.node_id();
+                        let _ = reply.send(port_graph.link(node_id, false, channel, port));
+                    }
+                    StreamCommand::Unlink { link, reply } => {
+                        let _ = reply.send(port_graph.unlink(link));
+                    }
                     StreamCommand::Stop => {
                         if let Err(e) = stream_clone.disconnect() {
                             emit_error(
@@ -523,6 +757,11 @@ impl DeviceTrait for Device {
             false,
             draining,
             None,
+            state,
+            position,
+            config_tracker,
+            (self.min_quantum, self.max_quantum),
+            None,
         );
         stream.signal_ready();
         Ok(stream)
@@ -536,11 +775,37 @@ impl DeviceTrait for Device {
         error_callback: E,
         timeout: Option<std::time::Duration>,
     ) -> Result<Self::Stream, Error>
+    where
+        D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        let options = StreamOptions {
+            timeout,
+            ..Default::default()
+        };
+        self.build_output_stream_with_options(
+            config,
+            sample_format,
+            &options,
+            data_callback,
+            error_callback,
+        )
+    }
+
+    fn build_output_stream_with_options<D, E>(
+        &self,
+        config: StreamConfig,
+        sample_format: SampleFormat,
+        options: &StreamOptions,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, Error>
     where
         D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
         E: FnMut(Error) + Send + 'static,
     {
         crate::validate_stream_config(&config)?;
+        let extra_properties = stream_properties(options, &config)?;
         if let BufferSize::Fixed(n) = config.buffer_size {
             // When max_quantum is 0 the server clock metadata has not been received yet.
             if self.max_quantum > 0 && !(self.min_quantum..=self.max_quantum).contains(&n) {
@@ -560,7 +825,15 @@ impl DeviceTrait for Device {
         let mut latch = Latch::new();
         let waiter = latch.waiter();
         let device = self.clone();
-        let wait_timeout = timeout.unwrap_or(Duration::from_secs(2));
+        let wait_timeout = options.timeout.unwrap_or(Duration::from_secs(2));
+        // A virtual node is what others link to, so it is never linked on its own.
+        let connect_automatically = !self.is_virtual()
+            && options
+                .pipewire
+                .connect_automatically
+                .unwrap_or_else(|| self.connect_automatically.load(Ordering::Relaxed));
+        // A stream with a target stays on it instead of following the default device.
+        let follows_default = options.pipewire.target.is_none();
         let initial_quantum = match config.buffer_size {
             BufferSize::Fixed(n) => n,
             BufferSize::Default => self.quantum,
@@ -569,18 +842,30 @@ impl DeviceTrait for Device {
         let last_quantum_clone = last_quantum.clone();
         let draining = Arc::new(AtomicBool::new(false));
         let draining_clone = draining.clone();
+        let state = Arc::new(StateTracker::new());
+        let state_clone = state.clone();
+        let position = Arc::new(PositionTracker::new(config.sample_rate));
+        let position_clone = position.clone();
+        let config_tracker = Arc::new(ConfigTracker::default());
+        let config_tracker_clone = config_tracker.clone();
         let drained: Arc<Notify> = Arc::new(Notify::default());
         let drained_clone = drained.clone();
         let drained_cmd = drained.clone();
+        let fader = Arc::new(Fader::new(config.channels, config.sample_rate));
+        let fader_clone = fader.clone();
         // Keep `playback` monotonic: pw_time delay() shrinks when other clients that needed
         // a larger buffer leave the graph, which can pull `playback` backward.
-        let data_callback = crate::host::monotonic_output_callback(data_callback);
+        let data_callback = crate::host::monotonic_output_callback(fade::output_callback(
+            fader.clone(),
+            data_callback,
+        ));
         let start = std::time::Instant::now();
         let handle = thread::Builder::new()
             .name("pw_out".to_owned())
             .spawn(move || {
                 let _pw = PwInitGuard::new();
-                let properties = device.pw_properties(DeviceDirection::Output, &config);
+                let properties =
+                    device.pw_properties(DeviceDirection::Output, &config, &extra_properties);
 
                 let stream_data = match super::stream::connect_output(
                     super::stream::ConnectParams {
@@ -589,13 +874,19 @@ impl DeviceTrait for Device {
                         sample_format,
                         last_quantum: last_quantum_clone,
                         start,
-                        connect_automatically: device.connect_automatically.load(Ordering::Relaxed),
+                        connect_automatically,
                         draining: draining_clone,
                         drained: Some(drained_clone),
-                        is_default_device: matches!(
-                            device.class(),
-                            Class::DefaultSink | Class::DefaultInput | Class::DefaultOutput
-                        ),
+                        is_default_device: follows_default
+                            && matches!(
+                                device.class(),
+                                Class::DefaultSink | Class::DefaultInput | Class::DefaultOutput
+                            ),
+                        state: state_clone,
+                        position: position_clone,
+                        config_tracker: config_tracker_clone,
+                        daemon_lost: device.daemon_lost.clone(),
+                        fader: Some(fader_clone),
                     },
                     data_callback,
                     error_callback,
@@ -619,32 +910,44 @@ impl DeviceTrait for Device {
                     core_monitor,
                     error_callback,
                     pending_device_changed,
-                    invalidated,
+                    state,
                 } = stream_data;
 
-                let default_monitor = if let Some(key) = device.default_metadata_key() {
-                    match core.get_registry_rc() {
-                        Ok(registry) => Some(DefaultDeviceMonitor::new(
-                            registry,
-                            key,
-                            error_callback.clone(),
-                            invalidated,
-                            pending_device_changed,
-                        )),
-                        Err(e) => {
-                            let _ = init_tx.send(Err(Error::with_message(
-                                ErrorKind::BackendError,
-                                format!("Could not acquire registry: {e}"),
-                            )));
-                            return;
+                let default_monitor =
+                    if let Some(key) = device.default_metadata_key().filter(|_| follows_default) {
+                        match core.get_registry_rc() {
+                            Ok(registry) => Some(DefaultDeviceMonitor::new(
+                                registry,
+                                key,
+                                error_callback.clone(),
+                                state,
+                                pending_device_changed,
+                            )),
+                            Err(e) => {
+                                let _ = init_tx.send(Err(Error::with_message(
+                                    ErrorKind::BackendError,
+                                    format!("Could not acquire registry: {e}"),
+                                )));
+                                return;
+                            }
                         }
+                    } else {
+                        None
+                    };
+                let port_graph = match PortGraph::new(core.clone()) {
+                    Ok(port_graph) => port_graph,
+                    Err(e) => {
+                        let _ = init_tx.send(Err(Error::with_message(
+                            ErrorKind::BackendError,
+                            format!("Could not acquire registry: {e}"),
+                        )));
+                        return;
                     }
-                } else {
-                    None
                 };
                 let stream_clone = stream.clone();
                 let mainloop_rc1 = mainloop.clone();
                 let error_callback_cmd = error_callback.clone();
+                let rate = config.sample_rate;
                 let _receiver = pw_play_rx.attach(mainloop.loop_(), move |play| match play {
                     StreamCommand::Toggle(state) => {
                         if let Err(e) = stream_clone.set_active(state) {
@@ -671,6 +974,37 @@ impl DeviceTrait for Device {
                             cvar.notify_one();
                         }
                     }
+                    StreamCommand::Flush => {
+                        if let Err(e) = stream_clone.flush(false) {
+                            emit_error(
+                                &error_callback_cmd,
+                                Error::with_message(
+                                    ErrorKind::BackendError,
+                                    format!("Stream flush failed: {e}"),
+                                ),
+                            );
+                        }
+                    }
+                    StreamCommand::SetBufferSize { frames, reply } => {
+                        let _ = reply.send(set_node_latency(&stream_clone, frames, rate));
+                    }
+                    StreamCommand::NodeId(reply) => {
+                        let _ = reply.send(stream_clone.node_id());
+                    }
+                    StreamCommand::Ports(reply) => {
+                        let _ = reply.send(port_graph.ports());
+                    }
+                    StreamCommand::Link {
+                        channel,
+                        port,
+                        reply,
+                    } => {
+                        let node_id = stream_clone.node_id();
+                        let _ = reply.send(port_graph.link(node_id, true, channel, port));
+                    }
+                    StreamCommand::Unlink { link, reply } => {
+                        let _ = reply.send(port_graph.unlink(link));
+                    }
                     StreamCommand::Stop => {
                         if let Err(e) = stream_clone.disconnect() {
                             emit_error(
@@ -730,19 +1064,130 @@ impl DeviceTrait for Device {
             true,
             draining,
             Some(drained),
+            state,
+            position,
+            config_tracker,
+            (self.min_quantum, self.max_quantum),
+            Some(fader),
         );
         stream.signal_ready();
         Ok(stream)
     }
 }
 
-#[derive(Clone, Default)]
-struct Settings {
-    rate: SampleRate,
-    allow_rates: Box<[SampleRate]>,
-    quantum: FrameCount,
-    min_quantum: FrameCount,
-    max_quantum: FrameCount,
+/// The graph clock configuration from PipeWire's `settings` metadata, as returned by
+/// [`PipeWireHost::clock_settings`](crate::platform::PipeWireHost::clock_settings).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClockSettings {
+    /// Rate the graph runs at by default (`clock.rate`).
+    pub rate: SampleRate,
+
+    /// Rates the graph may switch to for the nodes it runs (`clock.allowed-rates`), sorted.
+    pub allowed_rates: Vec<SampleRate>,
+
+    /// Quantum the graph runs with by default (`clock.quantum`).
+    pub quantum: FrameCount,
+
+    /// Smallest quantum nodes may ask for (`clock.min-quantum`).
+    pub min_quantum: FrameCount,
+
+    /// Largest quantum nodes may ask for (`clock.max-quantum`).
+    pub max_quantum: FrameCount,
+
+    /// Rate the graph is forced to run at (`clock.force-rate`), if any.
+    pub force_rate: Option<SampleRate>,
+
+    /// Quantum the graph is forced to run with (`clock.force-quantum`), if any.
+    pub force_quantum: Option<FrameCount>,
+}
+
+impl ClockSettings {
+    /// Applies one property of the `settings` metadata. Other keys and unparsable values are
+    /// ignored.
+    fn apply(&mut self, key: &str, value: &str) {
+        match key {
+            clock::RATE => {
+                if let Ok(rate) = value.parse() {
+                    self.rate = rate;
+                }
+            }
+            clock::ALLOWED_RATES => {
+                if let Some(allowed_rates) = parse_allow_rates(value) {
+                    self.allowed_rates = allowed_rates;
+                }
+            }
+            clock::QUANTUM => {
+                if let Ok(quantum) = value.parse() {
+                    self.quantum = quantum;
+                }
+            }
+            clock::MIN_QUANTUM => {
+                if let Ok(min_quantum) = value.parse() {
+                    self.min_quantum = min_quantum;
+                }
+            }
+            clock::MAX_QUANTUM => {
+                if let Ok(max_quantum) = value.parse() {
+                    self.max_quantum = max_quantum;
+                }
+            }
+            // Zero lifts the restriction.
+            clock::FORCE_RATE => {
+                if let Ok(rate) = value.parse() {
+                    self.force_rate = Some(rate).filter(|&rate| rate != 0);
+                }
+            }
+            clock::FORCE_QUANTUM => {
+                if let Ok(quantum) = value.parse() {
+                    self.force_quantum = Some(quantum).filter(|&quantum| quantum != 0);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Keeps `settings` up to date with the properties of the `settings` metadata.
+fn settings_listener(
+    metadata: &Metadata,
+    settings: Rc<RefCell<ClockSettings>>,
+) -> MetadataListener {
+    metadata
+        .add_listener_local()
+        .property(move |_, key, _, value| {
+            if let (Some(key), Some(value)) = (key, value) {
+                settings.borrow_mut().apply(key, value);
+            }
+            0
+        })
+        .register()
+}
+
+fn is_settings_metadata(props: Option<&pw::spa::utils::dict::DictRef>) -> bool {
+    props.is_some_and(|props| {
+        props
+            .get(METADATA_NAME)
+            .is_some_and(|name| name == "settings")
+    })
+}
+
+/// Runs `mainloop` until it quits, for at most `INIT_TIMEOUT` to guard against PipeWire daemons
+/// that accept a connection but never send `done` events.
+fn run_with_timeout(mainloop: &MainLoopRc) {
+    let (cancel_tx, cancel_rx) = mpsc::channel::<()>();
+    let (timeout_tx, timeout_rx) = pw::channel::channel::<()>();
+    let loop_quit = mainloop.clone();
+    let _timeout_watcher = timeout_rx.attach(mainloop.loop_(), move |_| {
+        loop_quit.quit();
+    });
+    thread::spawn(move || {
+        if cancel_rx.recv_timeout(INIT_TIMEOUT).is_err() {
+            let _ = timeout_tx.send(());
+        }
+    });
+
+    mainloop.run();
+    let _ = cancel_tx.send(());
 }
 
 // NOTE: it is just used to keep the lifetime
@@ -778,14 +1223,17 @@ fn parse_fraction(s: &str) -> Option<(u32, u32)> {
     Some((num, den))
 }
 
-fn remote_props() -> Option<PropertiesBox> {
+pub(super) fn remote_props() -> Option<PropertiesBox> {
     let socket = super::utils::find_socket_path()?;
     let mut props = PropertiesBox::new();
     props.insert(*pw::keys::REMOTE_NAME, socket.to_string_lossy().as_ref());
     Some(props)
 }
 
-pub fn init_devices(connect_automatically: Arc<AtomicBool>) -> Option<Vec<Device>> {
+pub fn init_devices(
+    connect_automatically: Arc<AtomicBool>,
+    daemon_lost: Arc<AtomicBool>,
+) -> Option<Vec<Device>> {
     let _pw = PwInitGuard::new();
     let mainloop = MainLoopRc::new(None).ok()?;
     let context = ContextRc::new(&mainloop, None).ok()?;
@@ -795,7 +1243,7 @@ pub fn init_devices(connect_automatically: Arc<AtomicBool>) -> Option<Vec<Device
     // Discovered hardware nodes collected during enumeration.
     let discovered: Rc<RefCell<Vec<(Device, NodeOverrides)>>> = Rc::new(RefCell::new(vec![]));
     let requests = Rc::new(RefCell::new(vec![]));
-    let settings = Rc::new(RefCell::new(Settings::default()));
+    let settings = Rc::new(RefCell::new(ClockSettings::default()));
     let loop_clone = mainloop.clone();
 
     // Trigger the sync event. The server's answer won't be processed until we start the main loop,
@@ -834,11 +1282,7 @@ pub fn init_devices(connect_automatically: Arc<AtomicBool>) -> Option<Vec<Device
             let settings = settings.clone();
             move |global| match global.type_ {
                 ObjectType::Metadata => {
-                    if !global.props.is_some_and(|props| {
-                        props
-                            .get(METADATA_NAME)
-                            .is_some_and(|name| name == "settings")
-                    }) {
+                    if !is_settings_metadata(global.props) {
                         return;
                     }
                     let meta_settings: Metadata = match registry.bind(global) {
@@ -850,48 +1294,7 @@ pub fn init_devices(connect_automatically: Arc<AtomicBool>) -> Option<Vec<Device
                             return;
                         }
                     };
-                    let settings = settings.clone();
-                    let listener = meta_settings
-                        .add_listener_local()
-                        .property(move |_, key, _, value| {
-                            match (key, value) {
-                                (Some(clock::RATE), Some(rate)) => {
-                                    let Ok(rate) = rate.parse() else {
-                                        return 0;
-                                    };
-                                    settings.borrow_mut().rate = rate;
-                                }
-                                (Some(clock::ALLOWED_RATES), Some(list)) => {
-                                    let Some(allow_rates) = parse_allow_rates(list) else {
-                                        return 0;
-                                    };
-
-                                    settings.borrow_mut().allow_rates =
-                                        allow_rates.into_boxed_slice();
-                                }
-                                (Some(clock::QUANTUM), Some(quantum)) => {
-                                    let Ok(quantum) = quantum.parse() else {
-                                        return 0;
-                                    };
-                                    settings.borrow_mut().quantum = quantum;
-                                }
-                                (Some(clock::MIN_QUANTUM), Some(min_quantum)) => {
-                                    let Ok(min_quantum) = min_quantum.parse() else {
-                                        return 0;
-                                    };
-                                    settings.borrow_mut().min_quantum = min_quantum;
-                                }
-                                (Some(clock::MAX_QUANTUM), Some(max_quantum)) => {
-                                    let Ok(max_quantum) = max_quantum.parse() else {
-                                        return 0;
-                                    };
-                                    settings.borrow_mut().max_quantum = max_quantum;
-                                }
-                                _ => {}
-                            }
-                            0
-                        })
-                        .register();
+                    let listener = settings_listener(&meta_settings, settings.clone());
                     let Ok(pending) = core.sync(0) else {
                         // TODO: maybe we should add a log?
                         return;
@@ -1064,21 +1467,7 @@ pub fn init_devices(connect_automatically: Arc<AtomicBool>) -> Option<Vec<Device
         })
         .register();
 
-    // Guard against PipeWire daemons that accept a connection but never send `done` events.
-    let (cancel_tx, cancel_rx) = mpsc::channel::<()>();
-    let (timeout_tx, timeout_rx) = pw::channel::channel::<()>();
-    let loop_quit = mainloop.clone();
-    let _timeout_watcher = timeout_rx.attach(mainloop.loop_(), move |_| {
-        loop_quit.quit();
-    });
-    thread::spawn(move || {
-        if cancel_rx.recv_timeout(INIT_TIMEOUT).is_err() {
-            let _ = timeout_tx.send(());
-        }
-    });
-
-    mainloop.run();
-    let _ = cancel_tx.send(());
+    run_with_timeout(&mainloop);
 
     // If PipeWire connected but discovered no real audio nodes, it cannot route any streams. Treat
     // this as unavailable so the caller can fall back to PulseAudio or ALSA.
@@ -1094,7 +1483,7 @@ pub fn init_devices(connect_automatically: Arc<AtomicBool>) -> Option<Vec<Device
         Device::input_default(),
         Device::output_default(),
     ];
-    let shared_rates: Arc<[SampleRate]> = Arc::from(settings.allow_rates.as_ref());
+    let shared_rates: Arc<[SampleRate]> = Arc::from(settings.allowed_rates.as_slice());
     for device in devices.iter_mut() {
         device.rate = settings.rate;
         device.allow_rates = Arc::clone(&shared_rates);
@@ -1102,6 +1491,7 @@ pub fn init_devices(connect_automatically: Arc<AtomicBool>) -> Option<Vec<Device
         device.min_quantum = settings.min_quantum;
         device.max_quantum = settings.max_quantum;
         device.connect_automatically = connect_automatically.clone();
+        device.daemon_lost = daemon_lost.clone();
     }
 
     // Resolve each discovered hardware node: global settings apply unless the node
@@ -1117,6 +1507,7 @@ pub fn init_devices(connect_automatically: Arc<AtomicBool>) -> Option<Vec<Device
                 device.min_quantum = settings.min_quantum;
                 device.max_quantum = settings.max_quantum;
                 device.connect_automatically = connect_automatically.clone();
+                device.daemon_lost = daemon_lost.clone();
                 device
             }),
     );
@@ -1124,20 +1515,101 @@ pub fn init_devices(connect_automatically: Arc<AtomicBool>) -> Option<Vec<Device
     Some(devices)
 }
 
+/// Reads the graph clock settings from PipeWire's `settings` metadata.
+pub fn clock_settings() -> Result<ClockSettings, Error> {
+    let _pw = PwInitGuard::new();
+    let unavailable = |e: pw::Error| {
+        Error::with_message(
+            ErrorKind::HostUnavailable,
+            format!("Failed to connect to PipeWire: {e}"),
+        )
+    };
+    let mainloop = MainLoopRc::new(None).map_err(unavailable)?;
+    let context = ContextRc::new(&mainloop, None).map_err(unavailable)?;
+    let core = context.connect_rc(remote_props()).map_err(unavailable)?;
+    let registry = core.get_registry_rc().map_err(unavailable)?;
+
+    let settings = Rc::new(RefCell::new(ClockSettings::default()));
+    let bound: Rc<RefCell<Option<(Metadata, MetadataListener)>>> = Rc::new(RefCell::new(None));
+    // The server answers syncs in order, so once the last one is done, the globals and the
+    // properties of the metadata bound in between have all arrived.
+    let pending = Rc::new(Cell::new(core.sync(0).map_err(unavailable)?));
+
+    let _listener_core = core
+        .add_listener_local()
+        .done({
+            let pending = pending.clone();
+            let mainloop = mainloop.clone();
+            move |id, seq| {
+                if id == PW_ID_CORE && seq == pending.get() {
+                    mainloop.quit();
+                }
+            }
+        })
+        .register();
+    let _listener_reg = registry
+        .add_listener_local()
+        .global({
+            let registry = registry.clone();
+            let core = core.clone();
+            let settings = settings.clone();
+            let bound = bound.clone();
+            move |global| {
+                if !matches!(global.type_, ObjectType::Metadata)
+                    || !is_settings_metadata(global.props)
+                {
+                    return;
+                }
+                let metadata: Metadata = match registry.bind(global) {
+                    Ok(metadata) => metadata,
+                    Err(_) => return,
+                };
+                let listener = settings_listener(&metadata, settings.clone());
+                *bound.borrow_mut() = Some((metadata, listener));
+                if let Ok(seq) = core.sync(0) {
+                    pending.set(seq);
+                }
+            }
+        })
+        .register();
+
+    run_with_timeout(&mainloop);
+
+    if bound.borrow().is_none() {
+        return Err(Error::with_message(
+            ErrorKind::BackendError,
+            "PipeWire has no settings metadata",
+        ));
+    }
+    Ok(settings.take())
+}
+
 fn parse_allow_rates(list: &str) -> Option<Vec<SampleRate>> {
-    list.trim()
+    let mut rates: Vec<SampleRate> = list
+        .trim()
         .strip_prefix("[")?
         .strip_suffix("]")?
         .split([' ', ','])
         .filter(|s| !s.is_empty())
         .map(|s| s.parse().ok())
-        .collect()
+        .collect::<Option<_>>()?;
+    // Kept sorted and unique so they can back `SupportedStreamConfigRange::sample_rates`.
+    rates.sort_unstable();
+    rates.dedup();
+    Some(rates)
 }
 
 #[cfg(test)]
 mod test {
-    use super::{Class, Device, parse_allow_rates, parse_fraction};
+    use super::{
+        Class, ClockSettings, Device, VirtualRole, parse_allow_rates, parse_fraction,
+        stream_properties,
+    };
     use crate::host::pipewire::utils::default;
+    use crate::{
+        BufferSize, DeviceDirection, DeviceType, ErrorKind, MediaRole, PipeWireTarget,
+        StreamConfig, StreamOptions, traits::DeviceTrait,
+    };
 
     #[test]
     fn rate_parse() {
@@ -1150,6 +1622,10 @@ mod test {
         let rates = parse_allow_rates(rate_str).unwrap();
         assert_eq!(rates, vec![44100, 48000, 88200, 96000, 176400, 192000]);
         assert_eq!(rates, vec![44100, 48000, 88200, 96000, 176400, 192000]);
+        // Unordered and repeated entries are normalised
+        let rate_str = r#"[ 96000 44100 48000 44100 ]"#;
+        let rates = parse_allow_rates(rate_str).unwrap();
+        assert_eq!(rates, vec![44100, 48000, 96000]);
         // We only use [] to define the list
         let rate_str = r#"  { 44100, 48000, 88200, 96000 ,176400 ,192000 } "#;
         let rates = parse_allow_rates(rate_str);
@@ -1176,6 +1652,32 @@ mod test {
         assert_eq!(parse_fraction("256/"), None);
     }
 
+    #[test]
+    fn clock_settings_parse() {
+        let mut settings = ClockSettings::default();
+        settings.apply("clock.rate", "48000");
+        settings.apply("clock.allowed-rates", "[ 48000 44100 ]");
+        settings.apply("clock.min-quantum", "32");
+        settings.apply("clock.force-quantum", "64");
+        settings.apply("clock.force-rate", "0");
+        settings.apply("clock.quantum", "not a number");
+        settings.apply("log.level", "2");
+        assert_eq!(
+            settings,
+            ClockSettings {
+                rate: 48000,
+                allowed_rates: vec![44100, 48000],
+                min_quantum: 32,
+                force_quantum: Some(64),
+                ..Default::default()
+            }
+        );
+
+        // Zero lifts a previous restriction
+        settings.apply("clock.force-quantum", "0");
+        assert_eq!(settings.force_quantum, None);
+    }
+
     #[test]
     fn default_metadata_key_mapping() {
         assert_eq!(
@@ -1197,4 +1699,87 @@ mod test {
         };
         assert_eq!(node.default_metadata_key(), None);
     }
+
+    #[test]
+    fn virtual_node_direction() {
+        let template = Device {
+            rate: 48000,
+            quantum: 1024,
+            ..Device::output_default()
+        };
+        let sink = Device::virtual_node(
+            VirtualRole::Sink,
+            "myapp_recorder",
+            "MyApp Recorder",
+            2,
+            &template,
+        )
+        .unwrap();
+        assert!(sink.supports_input() && !sink.supports_output());
+        assert_eq!(sink.default_metadata_key(), None);
+        assert_eq!(sink.rate, 48000);
+        assert_eq!(sink.quantum, 1024);
+        let description = sink.description().unwrap();
+        assert_eq!(description.name(), "MyApp Recorder");
+        assert_eq!(description.device_type(), DeviceType::Virtual);
+
+        let source =
+            Device::virtual_node(VirtualRole::Source, "myapp_mic", "", 1, &template).unwrap();
+        assert_eq!(source.direction, DeviceDirection::Output);
+
+        for (name, description, channels) in [("", "x", 2), ("a\0b", "x", 2), ("a", "x", 0)] {
+            let err =
+                Device::virtual_node(VirtualRole::Sink, name, description, channels, &template)
+                    .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn stream_properties_from_options() {
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: 48000,
+            buffer_size: BufferSize::Default,
+        };
+        let mut options = StreamOptions {
+            application_name: Some("MyApp".to_owned()),
+            stream_name: Some("Music playback".to_owned()),
+            media_role: Some(MediaRole::Music),
+            ..Default::default()
+        };
+        options.pipewire.latency = Some(256);
+        options.pipewire.target = Some(PipeWireTarget::Serial(42));
+        options
+            .pipewire
+            .properties
+            .push(("media.role".to_owned(), "Game".to_owned()));
+
+        let properties = stream_properties(&options, &config).unwrap();
+        let get = |key: &str| {
+            properties
+                .iter()
+                .rev()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(get("application.name"), Some("MyApp"));
+        assert_eq!(get("media.name"), Some("Music playback"));
+        assert_eq!(get("node.description"), Some("Music playback"));
+        // The label never becomes the node's identifier.
+        assert_eq!(get("node.name"), None);
+        assert_eq!(get("node.latency"), Some("256/48000"));
+        assert_eq!(get("target.object"), Some("42"));
+        // Raw properties come last, so they override the typed options.
+        assert_eq!(get("media.role"), Some("Game"));
+
+        options.pipewire.force_quantum = Some(0);
+        let err = stream_properties(&options, &config).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        options.pipewire.force_quantum = None;
+        options.icon_name = Some("a\0b".to_owned());
+        let err = stream_properties(&options, &config).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
 }
@@ -0,0 +1,239 @@
+//! Inspecting the PipeWire port graph and linking a stream's ports.
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use pipewire::{
+    self as pw,
+    core::CoreRc,
+    link::Link,
+    properties::PropertiesBox,
+    registry::{Listener as RegistryListener, RegistryRc},
+    spa::utils::dict::DictRef,
+    types::ObjectType,
+};
+
+use crate::{Error, ErrorKind};
+
+/// An audio port in the PipeWire graph, as listed by
+/// [`PipeWireStream::ports`](crate::platform::PipeWireStream::ports).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PortInfo {
+    /// Global id of the port, which [`PipeWireStream::link`](crate::platform::PipeWireStream::link)
+    /// takes.
+    pub id: u32,
+
+    /// Global id of the node the port belongs to.
+    pub node_id: u32,
+
+    /// `node.name` of the node the port belongs to, if known.
+    pub node_name: Option<String>,
+
+    /// `port.name`, e.g. `playback_FL`.
+    pub name: String,
+
+    /// `port.alias`, e.g. `Built-in Audio:playback_FL`, if set.
+    pub alias: Option<String>,
+
+    /// `audio.channel`, e.g. `FL`, if set.
+    pub channel: Option<String>,
+
+    /// The port receives data: it is the destination of its links.
+    pub is_input: bool,
+
+    /// The port corresponds to a physical connector, such as a sound card channel.
+    pub is_physical: bool,
+
+    /// The port carries a copy of what a sink plays.
+    pub is_monitor: bool,
+}
+
+/// A link made by [`PipeWireStream::link`](crate::platform::PipeWireStream::link), removed with
+/// [`PipeWireStream::unlink`](crate::platform::PipeWireStream::unlink) or when the stream is
+/// dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LinkHandle(u32);
+
+// Handles are numbered across all streams, so a stream never mistakes another stream's handle
+// for one of its own.
+static NEXT_LINK: AtomicU32 = AtomicU32::new(0);
+
+struct Port {
+    info: PortInfo,
+    // `port.id`: the index of the port among the node's ports in the same direction.
+    index: Option<u32>,
+}
+
+impl Port {
+    fn new(id: u32, props: &DictRef) -> Option<Self> {
+        let is_input = match props.get(*pw::keys::PORT_DIRECTION)? {
+            "in" => true,
+            "out" => false,
+            _ => return None,
+        };
+        if !props
+            .get(*pw::keys::FORMAT_DSP)
+            .is_some_and(|format| format.contains("audio"))
+        {
+            return None;
+        }
+        let flag = |key: &str| props.get(key).is_some_and(|value| value == "true");
+        Some(Self {
+            info: PortInfo {
+                id,
+                node_id: props.get(*pw::keys::NODE_ID)?.parse().ok()?,
+                node_name: None,
+                name: props.get(*pw::keys::PORT_NAME)?.to_owned(),
+                alias: props.get(*pw::keys::PORT_ALIAS).map(str::to_owned),
+                channel: props.get(*pw::keys::AUDIO_CHANNEL).map(str::to_owned),
+                is_input,
+                is_physical: flag(*pw::keys::PORT_PHYSICAL),
+                is_monitor: flag(*pw::keys::PORT_MONITOR),
+            },
+            index: props
+                .get(*pw::keys::PORT_ID)
+                .and_then(|index| index.parse().ok()),
+        })
+    }
+}
+
+/// The graph's audio ports as seen from a stream's thread, and the links the stream made.
+pub(super) struct PortGraph {
+    core: CoreRc,
+    _registry: RegistryRc,
+    _registry_listener: RegistryListener,
+    ports: Rc<RefCell<BTreeMap<u32, Port>>>,
+    node_names: Rc<RefCell<HashMap<u32, String>>>,
+    links: RefCell<HashMap<u32, Link>>,
+}
+
+impl PortGraph {
+    pub(super) fn new(core: CoreRc) -> Result<Self, pw::Error> {
+        let registry = core.get_registry_rc()?;
+        let ports = Rc::new(RefCell::new(BTreeMap::new()));
+        let node_names = Rc::new(RefCell::new(HashMap::new()));
+        let registry_listener = registry
+            .add_listener_local()
+            .global({
+                let ports = ports.clone();
+                let node_names = node_names.clone();
+                move |global| {
+                    let Some(props) = global.props else {
+                        return;
+                    };
+                    match global.type_ {
+                        ObjectType::Node => {
+                            if let Some(name) = props.get(*pw::keys::NODE_NAME) {
+                                node_names.borrow_mut().insert(global.id, name.to_owned());
+                            }
+                        }
+                        ObjectType::Port => {
+                            if let Some(port) = Port::new(global.id, props) {
+                                ports.borrow_mut().insert(global.id, port);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            })
+            .global_remove({
+                let ports = ports.clone();
+                let node_names = node_names.clone();
+                move |id| {
+                    ports.borrow_mut().remove(&id);
+                    node_names.borrow_mut().remove(&id);
+                }
+            })
+            .register();
+        Ok(Self {
+            core,
+            _registry: registry,
+            _registry_listener: registry_listener,
+            ports,
+            node_names,
+            links: RefCell::new(HashMap::new()),
+        })
+    }
+
+    pub(super) fn ports(&self) -> Vec<PortInfo> {
+        let node_names = self.node_names.borrow();
+        self.ports
+            .borrow()
+            .values()
+            .map(|port| PortInfo {
+                node_name: node_names.get(&port.info.node_id).cloned(),
+                ..port.info.clone()
+            })
+            .collect()
+    }
+
+    /// Links the port of `node_id` carrying `channel` to `peer`. `is_output` is the direction of
+    /// the stream, so data flows from the stream's port to `peer` if it is set.
+    pub(super) fn link(
+        &self,
+        node_id: u32,
+        is_output: bool,
+        channel: usize,
+        peer: u32,
+    ) -> Result<LinkHandle, Error> {
+        let ports = self.ports.borrow();
+        let own = ports
+            .values()
+            .filter(|port| port.info.node_id == node_id && port.info.is_input != is_output)
+            .find(|port| !port.info.is_monitor && port.index == u32::try_from(channel).ok())
+            .ok_or_else(|| {
+                Error::with_message(
+                    ErrorKind::InvalidInput,
+                    format!("The stream has no port for channel {channel}"),
+                )
+            })?;
+        let peer = ports
+            .get(&peer)
+            .filter(|port| port.info.is_input == is_output)
+            .ok_or_else(|| {
+                Error::with_message(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "No audio {direction} port with id {peer}",
+                        direction = if is_output { "input" } else { "output" }
+                    ),
+                )
+            })?;
+        let (output, input) = if is_output { (own, peer) } else { (peer, own) };
+
+        let mut properties = PropertiesBox::new();
+        properties.insert(*pw::keys::LINK_OUTPUT_NODE, output.info.node_id.to_string());
+        properties.insert(*pw::keys::LINK_OUTPUT_PORT, output.info.id.to_string());
+        properties.insert(*pw::keys::LINK_INPUT_NODE, input.info.node_id.to_string());
+        properties.insert(*pw::keys::LINK_INPUT_PORT, input.info.id.to_string());
+        // The link is destroyed with its proxy, on unlink or when the stream goes away.
+        properties.insert(*pw::keys::OBJECT_LINGER, "false");
+        let link: Link = self
+            .core
+            .create_object("link-factory", &properties)
+            .map_err(|e| {
+                Error::with_message(
+                    ErrorKind::BackendError,
+                    format!("Failed to create link: {e}"),
+                )
+            })?;
+
+        let handle = LinkHandle(NEXT_LINK.fetch_add(1, Ordering::Relaxed));
+        self.links.borrow_mut().insert(handle.0, link);
+        Ok(handle)
+    }
+
+    pub(super) fn unlink(&self, link: LinkHandle) -> Result<(), Error> {
+        match self.links.borrow_mut().remove(&link.0) {
+            Some(_) => Ok(()),
+            None => Err(Error::with_message(
+                ErrorKind::InvalidInput,
+                "The link was not made by this stream, or was already removed",
+            )),
+        }
+    }
+}
@@ -1,47 +1,112 @@
 use std::sync::{
-    Arc,
+    Arc, Mutex,
     atomic::{AtomicBool, Ordering},
 };
 
-use device::{Class, Device, Devices, init_devices};
+use device::{Class, Device, Devices, VirtualRole, init_devices};
 use stream::PwInitGuard;
+use watch::DaemonWatch;
 
-use crate::{Error, ErrorKind, traits::HostTrait};
+pub use self::{
+    device::ClockSettings,
+    links::{LinkHandle, PortInfo},
+    stream::Stream,
+};
+
+use crate::{ChannelCount, Error, ErrorKind, traits::HostTrait};
 
 mod device;
+mod links;
 #[cfg(all(target_os = "linux", feature = "realtime"))]
 mod rt_promote;
 mod stream;
 mod utils;
+mod watch;
 
 /// The PipeWire host, providing access to PipeWire audio devices.
 ///
 /// # PipeWire-Specific Configuration
 ///
 /// PipeWire provides a configuration option to control graph connection behavior:
-/// - Port auto-connection via [`set_connect_automatically`](Host::set_connect_automatically)
+/// - Port auto-connection via [`set_connect_automatically`](Host::set_connect_automatically),
+///   which a stream can override with
+///   [`PipeWireStreamOptions::connect_automatically`](crate::PipeWireStreamOptions::connect_automatically)
+///
+/// The graph clock settings can be read with [`clock_settings`](Host::clock_settings), and a
+/// stream can ask for a rate and quantum through
+/// [`PipeWireStreamOptions`](crate::PipeWireStreamOptions).
+///
+/// [`virtual_sink`](Host::virtual_sink) and [`virtual_source`](Host::virtual_source) publish
+/// nodes that other applications can play to or record from.
+///
+/// # Daemon restarts
+///
+/// When the PipeWire daemon restarts, every stream reports an
+/// [`ErrorKind::StreamInvalidated`] error whose message says the daemon disconnected. The host
+/// keeps its own connection to notice the restart even with no stream running, and enumerates
+/// its devices again the next time they are asked for.
+/// [`refresh_devices`](Host::refresh_devices) does the same on request.
+///
+/// Streams are not re-created automatically. Once the daemon is back, rebuild them on the device
+/// with the same [`DeviceId`](crate::DeviceId), which stays the same across restarts.
 pub struct Host {
+    _watch: Option<DaemonWatch>,
     // Keeps PipeWire initialized for the lifetime of the host, preventing
     // pw_deinit() from running between device enumeration and stream creation.
     _pw: PwInitGuard,
-    devices: Vec<Device>,
+    devices: Mutex<Vec<Device>>,
     connect_automatically: Arc<AtomicBool>,
+    daemon_lost: Arc<AtomicBool>,
 }
 
 impl Host {
     pub fn new() -> Result<Self, Error> {
         let _pw = PwInitGuard::new();
         let connect_automatically = Arc::new(AtomicBool::new(true));
-        let devices = init_devices(connect_automatically.clone()).ok_or_else(|| {
-            Error::with_message(ErrorKind::HostUnavailable, "PipeWire is not available")
-        })?;
+        let daemon_lost = Arc::new(AtomicBool::new(false));
+        let devices = enumerate(&connect_automatically, &daemon_lost)?;
         Ok(Self {
+            _watch: DaemonWatch::spawn(daemon_lost.clone()),
             _pw,
-            devices,
+            devices: Mutex::new(devices),
             connect_automatically,
+            daemon_lost,
         })
     }
 
+    /// Enumerates the devices again, e.g. after the daemon restarted or nodes were added.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::HostUnavailable`] if PipeWire cannot be reached. The previous device list
+    ///   is kept.
+    pub fn refresh_devices(&self) -> Result<(), Error> {
+        // Clear the flag first so a disconnect seen during enumeration is not lost.
+        self.daemon_lost.store(false, Ordering::Release);
+        match enumerate(&self.connect_automatically, &self.daemon_lost) {
+            Ok(devices) => {
+                *self.devices.lock().unwrap_or_else(|e| e.into_inner()) = devices;
+                Ok(())
+            }
+            Err(e) => {
+                self.daemon_lost.store(true, Ordering::Release);
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns the devices, enumerating them again first if a stream saw the daemon go away.
+    fn current_devices(&self) -> Result<Vec<Device>, Error> {
+        if self.daemon_lost.load(Ordering::Acquire) {
+            self.refresh_devices()?;
+        }
+        Ok(self
+            .devices
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone())
+    }
+
     /// Configures whether created streams should automatically connect to system playback/capture
     /// nodes via the session manager.
     ///
@@ -54,6 +119,79 @@ impl Host {
     pub fn set_connect_automatically(&mut self, connect: bool) {
         self.connect_automatically.store(connect, Ordering::Relaxed);
     }
+
+    /// Returns the graph's clock settings as they are now, including any rate or quantum forced
+    /// with `pw-metadata -n settings 0 clock.force-quantum <n>` or similar.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::HostUnavailable`] if PipeWire cannot be reached.
+    /// - [`ErrorKind::BackendError`] if PipeWire publishes no clock settings.
+    pub fn clock_settings(&self) -> Result<ClockSettings, Error> {
+        device::clock_settings()
+    }
+
+    /// Returns a device that publishes a virtual `Audio/Sink` node, which desktop sound settings
+    /// offer as an output device. What other applications play to it is delivered to the input
+    /// streams built on the device.
+    ///
+    /// `name` becomes the node's `node.name` and `description` the name shown to users, e.g.
+    /// `"MyApp Recorder"`. The node exists while a stream built on the device is running; each
+    /// stream publishes its own. Such streams are never linked automatically, and
+    /// [`PipeWireStreamOptions::target`](crate::PipeWireStreamOptions::target) has no effect.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::InvalidInput`] if `name` is empty, `name` or `description` contains a NUL
+    ///   byte, or `channels` is zero.
+    pub fn virtual_sink(
+        &self,
+        name: &str,
+        description: &str,
+        channels: ChannelCount,
+    ) -> Result<Device, Error> {
+        self.virtual_device(VirtualRole::Sink, name, description, channels)
+    }
+
+    /// Returns a device that publishes a virtual `Audio/Source` node, which desktop sound
+    /// settings offer as an input device, e.g. `"MyApp Virtual Mic"`. Output streams built on
+    /// the device provide what other applications record from it.
+    ///
+    /// See [`virtual_sink`](Host::virtual_sink) for the arguments and errors.
+    pub fn virtual_source(
+        &self,
+        name: &str,
+        description: &str,
+        channels: ChannelCount,
+    ) -> Result<Device, Error> {
+        self.virtual_device(VirtualRole::Source, name, description, channels)
+    }
+
+    fn virtual_device(
+        &self,
+        role: VirtualRole,
+        name: &str,
+        description: &str,
+        channels: ChannelCount,
+    ) -> Result<Device, Error> {
+        // The node runs at the graph clock, which the default devices carry.
+        let template = self
+            .current_devices()?
+            .into_iter()
+            .find(|device| matches!(device.class(), Class::DefaultOutput))
+            .ok_or_else(|| {
+                Error::with_message(ErrorKind::HostUnavailable, "PipeWire is not available")
+            })?;
+        Device::virtual_node(role, name, description, channels, &template)
+    }
+}
+
+fn enumerate(
+    connect_automatically: &Arc<AtomicBool>,
+    daemon_lost: &Arc<AtomicBool>,
+) -> Result<Vec<Device>, Error> {
+    init_devices(connect_automatically.clone(), daemon_lost.clone())
+        .ok_or_else(|| Error::with_message(ErrorKind::HostUnavailable, "PipeWire is not available"))
 }
 
 impl HostTrait for Host {
@@ -65,20 +203,20 @@ impl HostTrait for Host {
     }
 
     fn devices(&self) -> Result<Self::Devices, Error> {
-        Ok(self.devices.clone().into_iter())
+        Ok(self.current_devices()?.into_iter())
     }
 
     fn default_input_device(&self) -> Option<Self::Device> {
-        self.devices
-            .iter()
+        self.current_devices()
+            .ok()?
+            .into_iter()
             .find(|device| matches!(device.class(), Class::DefaultInput))
-            .cloned()
     }
 
     fn default_output_device(&self) -> Option<Self::Device> {
-        self.devices
-            .iter()
+        self.current_devices()
+            .ok()?
+            .into_iter()
             .find(|device| matches!(device.class(), Class::DefaultOutput))
-            .cloned()
     }
 }
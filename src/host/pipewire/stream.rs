@@ -4,9 +4,10 @@ use std::{
     sync::{
         Arc, Mutex,
         atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc,
     },
     thread::JoinHandle,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use pipewire::{
@@ -28,19 +29,23 @@ use pipewire::{
         sys::{SPA_IO_Clock, spa_io_clock},
         utils::{Direction, SpaTypes},
     },
-    stream::{StreamFlags, StreamListener, StreamRc, StreamState, Time},
+    stream::{StreamFlags, StreamListener, StreamRc, StreamState as PwStreamState, Time},
     types::ObjectType,
 };
 
+use super::links::{LinkHandle, PortInfo};
 #[cfg(all(target_os = "linux", feature = "realtime"))]
 use super::rt_promote::RtPromoter;
 use crate::{
-    CallbackInfo, Data, Error, ErrorKind, FrameCount, SampleFormat, StreamConfig, StreamInstant,
-    StreamTimestamp,
+    BufferSize, CallbackInfo, ChannelCount, Data, Error, ErrorKind, FrameCount, SampleFormat,
+    SampleRate, StreamConfig, StreamInstant, StreamPosition, StreamState, StreamTimestamp,
+    config_change::{ConfigListener, ConfigTracker},
     host::{
-        ErrorCallbackArc, Notify, emit_error, equilibrium::fill_equilibrium, frames_to_duration,
-        latch::Latch, try_emit_error,
+        ErrorCallbackArc, Notify, emit_error, equilibrium::fill_equilibrium, fade::Fader,
+        frames_to_duration, latch::Latch, try_emit_error,
     },
+    position::PositionTracker,
+    stream_state::{StateListener, StateTracker},
     traits::StreamTrait,
 };
 
@@ -79,9 +84,28 @@ impl Drop for PwInitGuard {
 pub(super) enum StreamCommand {
     Toggle(bool),
     Drain,
+    Flush,
+    SetBufferSize {
+        frames: FrameCount,
+        reply: mpsc::Sender<Result<(), Error>>,
+    },
+    NodeId(mpsc::Sender<u32>),
+    Ports(mpsc::Sender<Vec<PortInfo>>),
+    Link {
+        channel: usize,
+        port: u32,
+        reply: mpsc::Sender<Result<LinkHandle, Error>>,
+    },
+    Unlink {
+        link: LinkHandle,
+        reply: mpsc::Sender<Result<(), Error>>,
+    },
     Stop,
 }
 
+/// How long to wait for the stream's thread to answer a request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
 pub struct Stream {
     handle: Option<JoinHandle<()>>,
     controller: pw::channel::Sender<StreamCommand>,
@@ -91,6 +115,13 @@ pub struct Stream {
     is_output: bool,
     draining: Arc<AtomicBool>,
     drained: Option<Arc<Notify>>,
+    state: Arc<StateTracker>,
+    position: Arc<PositionTracker>,
+    config_tracker: Arc<ConfigTracker>,
+    // The graph's `clock.min-quantum` and `clock.max-quantum`, or zeros if they are unknown.
+    quantum_range: (FrameCount, FrameCount),
+    // Gain ramps around transport changes; `None` for input streams.
+    fader: Option<Arc<Fader>>,
 }
 
 impl Stream {
@@ -104,6 +135,11 @@ impl Stream {
         is_output: bool,
         draining: Arc<AtomicBool>,
         drained: Option<Arc<Notify>>,
+        state: Arc<StateTracker>,
+        position: Arc<PositionTracker>,
+        config_tracker: Arc<ConfigTracker>,
+        quantum_range: (FrameCount, FrameCount),
+        fader: Option<Arc<Fader>>,
     ) -> Self {
         Self {
             handle: Some(handle),
@@ -114,17 +150,93 @@ impl Stream {
             is_output,
             draining,
             drained,
+            state,
+            position,
+            config_tracker,
+            quantum_range,
+            fader,
         }
     }
 
     /// Releases the latch so the worker thread can begin processing audio callbacks.
-    pub fn signal_ready(&self) {
+    pub(super) fn signal_ready(&self) {
         self.latch.release();
     }
+
+    /// Returns the global id of the stream's node, which owns the stream's ports.
+    ///
+    /// This and the other graph methods are answered by the stream's PipeWire thread, so they
+    /// must not be called from the data callback.
+    pub fn node_id(&self) -> Result<u32, Error> {
+        self.request(StreamCommand::NodeId)
+    }
+
+    /// Lists the audio ports in the PipeWire graph, including the stream's own.
+    pub fn ports(&self) -> Result<Vec<PortInfo>, Error> {
+        self.request(StreamCommand::Ports)
+    }
+
+    /// Links the stream's port for `channel` to the port with the global id `port`, e.g. from
+    /// [`ports`](Self::ports). An output stream links to input ports, an input stream to output
+    /// ports.
+    ///
+    /// Build the stream with [`PipeWireStreamOptions::connect_automatically`] set to `false` to
+    /// do all the linking yourself.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::InvalidInput`] if the stream has no port for `channel`, e.g. before it
+    ///   has been negotiated, or `port` is not an audio port of the opposite direction.
+    /// - [`ErrorKind::BackendError`] if PipeWire refuses to create the link.
+    ///
+    /// [`PipeWireStreamOptions::connect_automatically`]: crate::PipeWireStreamOptions::connect_automatically
+    pub fn link(&self, channel: usize, port: u32) -> Result<LinkHandle, Error> {
+        self.request(|reply| StreamCommand::Link {
+            channel,
+            port,
+            reply,
+        })?
+    }
+
+    /// Removes a link made by [`link`](Self::link).
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::InvalidInput`] if the link was not made by this stream or is already
+    ///   removed.
+    pub fn unlink(&self, link: LinkHandle) -> Result<(), Error> {
+        self.request(|reply| StreamCommand::Unlink { link, reply })?
+    }
+
+    /// Sends the command made by `command` to the stream's thread and waits for its answer.
+    fn request<T>(
+        &self,
+        command: impl FnOnce(mpsc::Sender<T>) -> StreamCommand,
+    ) -> Result<T, Error> {
+        let (reply, answer) = mpsc::channel();
+        self.controller.send(command(reply)).map_err(|_| {
+            Error::with_message(
+                ErrorKind::StreamInvalidated,
+                "stream command channel closed",
+            )
+        })?;
+        answer.recv_timeout(REQUEST_TIMEOUT).map_err(|e| match e {
+            mpsc::RecvTimeoutError::Timeout => {
+                Error::with_message(ErrorKind::BackendError, "PipeWire did not answer in time")
+            }
+            mpsc::RecvTimeoutError::Disconnected => Error::with_message(
+                ErrorKind::StreamInvalidated,
+                "stream command channel closed",
+            ),
+        })
+    }
 }
 
 impl Drop for Stream {
     fn drop(&mut self) {
+        if let Some(fader) = &self.fader {
+            fader.fade_out(true, None);
+        }
         // Unblock the worker in case the stream is dropped before signal_ready() was called.
         self.signal_ready();
         let _ = self.controller.send(StreamCommand::Stop);
@@ -149,9 +261,15 @@ impl StreamTrait for Stream {
                     "stream command channel closed",
                 )
             })?;
+        if let Some(fader) = &self.fader {
+            fader.fade_in();
+        }
         Ok(())
     }
     fn pause(&self) -> Result<(), Error> {
+        if let Some(fader) = &self.fader {
+            fader.fade_out(true, None);
+        }
         self.controller
             .send(StreamCommand::Toggle(false))
             .map_err(|_| {
@@ -164,6 +282,12 @@ impl StreamTrait for Stream {
     }
 
     fn stop(&self, timeout: Option<std::time::Duration>) -> Result<(), Error> {
+        // The ramp drains with the rest of the buffered audio, so only wait for it to be written.
+        let spent = self
+            .fader
+            .as_ref()
+            .map_or(Duration::ZERO, |fader| fader.fade_out(false, timeout));
+        let timeout = timeout.map(|timeout| timeout.saturating_sub(spent));
         self.draining.store(true, Ordering::Relaxed);
         let do_drain = self.is_output && timeout != Some(std::time::Duration::ZERO);
         if do_drain {
@@ -171,6 +295,8 @@ impl StreamTrait for Stream {
                 let (mutex, _) = drained.as_ref();
                 *mutex.lock().unwrap_or_else(|e| e.into_inner()) = false;
             }
+            // The Paused state change that follows the drain is reported by PipeWire.
+            self.state.set(StreamState::Draining);
             self.controller.send(StreamCommand::Drain).map_err(|_| {
                 Error::with_message(
                     ErrorKind::StreamInvalidated,
@@ -213,6 +339,24 @@ impl StreamTrait for Stream {
         Ok(())
     }
 
+    fn flush(&self) -> Result<(), Error> {
+        // Cutting into queued audio clicks as well, so ramp out before and back in after.
+        let fader = self.fader.as_ref().filter(|fader| fader.is_running());
+        if let Some(fader) = fader {
+            fader.fade_out(true, None);
+        }
+        let result = self.controller.send(StreamCommand::Flush).map_err(|_| {
+            Error::with_message(
+                ErrorKind::StreamInvalidated,
+                "stream command channel closed",
+            )
+        });
+        if let Some(fader) = fader {
+            fader.fade_in();
+        }
+        result
+    }
+
     fn now(&self) -> StreamInstant {
         monotonic_stream_instant().unwrap_or_else(|| stream_instant_from_start(self.start))
     }
@@ -220,6 +364,56 @@ impl StreamTrait for Stream {
     fn buffer_size(&self) -> Result<FrameCount, Error> {
         Ok(self.last_quantum.load(Ordering::Relaxed) as _)
     }
+
+    fn set_buffer_size(&self, frames: FrameCount) -> Result<(), Error> {
+        if frames == 0 {
+            return Err(Error::with_message(
+                ErrorKind::InvalidInput,
+                "buffer size must not be zero",
+            ));
+        }
+        let (min, max) = self.quantum_range;
+        // When max is 0 the server clock metadata was not known when the stream was built.
+        if max > 0 && !(min..=max).contains(&frames) {
+            return Err(Error::with_message(
+                ErrorKind::UnsupportedConfig,
+                format!("Buffer size {frames} is not in the supported quantum range {min}..={max}"),
+            ));
+        }
+        // `buffer_size` keeps reporting the current quantum until the process callback sees the
+        // one the graph settles on, which may differ: the smallest `node.latency` wins.
+        self.request(|reply| StreamCommand::SetBufferSize { frames, reply })?
+    }
+
+    fn position(&self) -> Result<StreamPosition, Error> {
+        let advancing = matches!(
+            self.state.get(),
+            StreamState::Running | StreamState::Draining
+        );
+        Ok(self.position.get(self.now(), advancing))
+    }
+
+    fn state(&self) -> Result<StreamState, Error> {
+        Ok(self.state.get())
+    }
+
+    fn set_state_listener(&self, listener: StateListener) -> Result<(), Error> {
+        self.state.set_listener(listener);
+        Ok(())
+    }
+
+    fn set_config_listener(&self, listener: ConfigListener) -> Result<(), Error> {
+        self.config_tracker.set_listener(listener);
+        Ok(())
+    }
+
+    fn set_fade(&self, fade: Duration) -> Result<(), Error> {
+        let fader = self.fader.as_ref().ok_or_else(|| {
+            Error::with_message(ErrorKind::UnsupportedOperation, "input streams do not fade")
+        })?;
+        fader.set_fade(fade);
+        Ok(())
+    }
 }
 
 pub(crate) const SUPPORTED_FORMATS: &[SampleFormat] = &[
@@ -294,7 +488,14 @@ pub struct UserData<D> {
     draining: Arc<AtomicBool>,
     is_default_device: bool,
     has_connected: bool,
-    invalidated: Arc<AtomicBool>,
+    state: Arc<StateTracker>,
+    position: Arc<PositionTracker>,
+    // The configuration last reported to the config listener.
+    config: StreamConfig,
+    config_tracker: Arc<ConfigTracker>,
+    fader: Option<Arc<Fader>>,
+    // Frames passed through `process` since the stream was created.
+    frames_processed: u64,
     pending_device_changed: Arc<AtomicBool>,
     spa_io_clock: *const spa_io_clock,
     xrun_recovering: bool,
@@ -324,30 +525,74 @@ impl<D> UserData<D> {
         }
     }
 
-    fn state_changed(&mut self, new: StreamState) {
+    fn state_changed(&mut self, new: PwStreamState) {
         match new {
-            StreamState::Streaming => self.has_connected = true,
-            StreamState::Unconnected => {
+            PwStreamState::Streaming => {
+                self.has_connected = true;
+                self.state.set(StreamState::Running);
+            }
+            PwStreamState::Paused => self.state.set(StreamState::Paused),
+            PwStreamState::Unconnected => {
                 // Let the metadata monitor fire for default-device streams
-                if self.has_connected
-                    && !self.is_default_device
-                    && !self.invalidated.swap(true, Ordering::Relaxed)
-                {
+                if self.has_connected && !self.is_default_device && self.state.invalidate() {
                     emit_error(
                         &self.error_callback,
                         Error::with_message(ErrorKind::DeviceNotAvailable, "Device disconnected"),
                     );
                 }
             }
-            StreamState::Error(e) => {
-                if !self.invalidated.swap(true, Ordering::Relaxed) {
+            PwStreamState::Error(e) => {
+                if self.state.invalidate() {
                     emit_error(
                         &self.error_callback,
                         Error::with_message(ErrorKind::StreamInvalidated, e),
                     );
                 }
             }
-            StreamState::Paused | StreamState::Connecting => {}
+            PwStreamState::Connecting => {}
+        }
+    }
+
+    /// Reports a renegotiated channel count or rate to the config listener and adopts it.
+    /// Returns whether the stream keeps running, which it only does if there is a listener.
+    fn follow_format(&mut self) -> bool {
+        if !self.config_tracker.has_listener() {
+            return false;
+        }
+        let config = StreamConfig {
+            channels: self.format.channels() as ChannelCount,
+            sample_rate: self.format.rate(),
+            ..self.config
+        };
+        // Follow the change even if the listener is being replaced and misses it.
+        self.config_tracker.notify(self.config, config);
+        self.position.set_sample_rate(config.sample_rate);
+        if let Some(fader) = &self.fader {
+            fader.set_format(config.channels, config.sample_rate);
+        }
+        self.config = config;
+        true
+    }
+
+    /// Reports a change of the graph quantum, in stream frames, to the config listener.
+    fn track_quantum(&mut self, frames: usize) {
+        let mut quantum = frames as FrameCount;
+        if !self.spa_io_clock.is_null() {
+            // While the adapter resamples, the frames per cycle jitter around the graph quantum,
+            // so report the quantum itself, converted from graph ticks to stream frames.
+            // io_changed and process run on the same thread.
+            let (duration, rate) =
+                unsafe { ((*self.spa_io_clock).duration, (*self.spa_io_clock).rate) };
+            if rate.denom != 0 {
+                quantum = (duration * u64::from(rate.num) * u64::from(self.config.sample_rate)
+                    / u64::from(rate.denom)) as FrameCount;
+            }
+        }
+        let buffer_size = BufferSize::Fixed(quantum);
+        if self.config.buffer_size != buffer_size {
+            let previous = self.config;
+            self.config.buffer_size = buffer_size;
+            self.config_tracker.notify(previous, self.config);
         }
     }
 
@@ -377,6 +622,16 @@ fn pw_stream_time(stream: &pw::stream::Stream) -> Option<Time> {
     Some(t)
 }
 
+/// Frames between the stream and the device: the graph delay, converted from graph ticks to the
+/// stream rate, plus what the stream's resampler holds.
+fn in_flight_frames(t: &Time, sample_rate: SampleRate) -> u64 {
+    let delay = u128::try_from(t.delay()).unwrap_or(0) * u128::from(sample_rate)
+        / u128::from(t.rate().denom);
+    u64::try_from(delay)
+        .unwrap_or(u64::MAX)
+        .saturating_add(t.buffered())
+}
+
 impl<D> UserData<D>
 where
     D: FnMut(&Data, &CallbackInfo) + Send + 'static,
@@ -389,14 +644,28 @@ where
         xrun: bool,
     ) {
         self.last_quantum.store(frames as u32, Ordering::Relaxed);
+        self.track_quantum(frames);
 
         #[cfg(feature = "realtime")]
         if frames as u32 > self.rt_promoted_frames {
             self.promote_realtime(frames as FrameCount);
         }
 
+        let time = pw_stream_time(stream);
+        if let Some(t) = &time {
+            // The device has captured this quantum and everything still on its way to us.
+            let captured =
+                self.frames_processed + frames as u64 + in_flight_frames(t, self.format.rate());
+            self.position.report(
+                captured,
+                StreamInstant::from_nanos(t.now() as u64),
+                u64::MAX,
+            );
+        }
+        self.frames_processed += frames as u64;
+
         if !self.draining.load(Ordering::Relaxed) {
-            let (callback, capture) = match pw_stream_time(stream) {
+            let (callback, capture) = match &time {
                 Some(t) => {
                     // `pw_stream_time` guarantees `now > 0` and `denom != 0`.
                     let now = t.now() as u64;
@@ -437,14 +706,28 @@ where
         xrun: bool,
     ) {
         self.last_quantum.store(frames as u32, Ordering::Relaxed);
+        self.track_quantum(frames);
 
         #[cfg(feature = "realtime")]
         if frames as u32 > self.rt_promoted_frames {
             self.promote_realtime(frames as FrameCount);
         }
 
+        let time = pw_stream_time(stream);
+        if let Some(t) = &time {
+            // Earlier quanta still on their way to the device have not been played.
+            let written = self.frames_processed + frames as u64;
+            self.position.report(
+                self.frames_processed
+                    .saturating_sub(in_flight_frames(t, self.format.rate())),
+                StreamInstant::from_nanos(t.now() as u64),
+                written,
+            );
+        }
+        self.frames_processed += frames as u64;
+
         if !self.draining.load(Ordering::Relaxed) {
-            let (callback, playback) = match pw_stream_time(stream) {
+            let (callback, playback) = match &time {
                 Some(t) => {
                     // `pw_stream_time` guarantees `now > 0` and `denom != 0`.
                     let now = t.now() as u64;
@@ -480,7 +763,7 @@ pub struct StreamData<D> {
     pub core_monitor: CoreListener,
     pub error_callback: ErrorCallbackArc,
     pub pending_device_changed: Arc<AtomicBool>,
-    pub invalidated: Arc<AtomicBool>,
+    pub state: Arc<StateTracker>,
 }
 
 /// Fallback timestamp using elapsed time since stream creation.
@@ -507,6 +790,28 @@ fn monotonic_stream_instant() -> Option<StreamInstant> {
     }
 }
 
+/// Asks the graph for `frames` per cycle by updating the stream's `node.latency`.
+pub(super) fn set_node_latency(
+    stream: &pw::stream::Stream,
+    frames: FrameCount,
+    rate: SampleRate,
+) -> Result<(), Error> {
+    let mut properties = PropertiesBox::new();
+    properties.insert(*pw::keys::NODE_LATENCY, format!("{frames}/{rate}"));
+    // SAFETY: the stream and the dictionary are valid for the call, which copies the entries.
+    let res = unsafe {
+        pw::sys::pw_stream_update_properties(stream.as_raw_ptr(), properties.dict().as_raw_ptr())
+    };
+    if res < 0 {
+        return Err(Error::with_message(
+            ErrorKind::BackendError,
+            format!("Failed to update node.latency to {frames}/{rate}"),
+        )
+        .with_backend_code(res));
+    }
+    Ok(())
+}
+
 fn remote_props() -> Option<PropertiesBox> {
     let socket = super::utils::find_socket_path()?;
     let mut props = PropertiesBox::new();
@@ -534,7 +839,7 @@ impl DefaultDeviceMonitor {
         registry: RegistryRc,
         key: &'static str,
         error_callback: ErrorCallbackArc,
-        invalidated: Arc<AtomicBool>,
+        state: Arc<StateTracker>,
         pending_device_changed: Arc<AtomicBool>,
     ) -> Self {
         let meta_objects: Rc<RefCell<Option<MetadataObjects>>> = Rc::new(RefCell::new(None));
@@ -568,7 +873,7 @@ impl DefaultDeviceMonitor {
                     }
                 };
                 let error_callback_cb = error_callback.clone();
-                let invalidated_cb = invalidated.clone();
+                let state_cb = state.clone();
                 let pending_device_changed_cb = pending_device_changed.clone();
 
                 let last_value: RefCell<Option<Option<String>>> = RefCell::new(None);
@@ -592,7 +897,7 @@ impl DefaultDeviceMonitor {
                                             pending_device_changed_cb
                                                 .store(true, Ordering::Relaxed);
                                         }
-                                    } else if !invalidated_cb.swap(true, Ordering::Relaxed) {
+                                    } else if state_cb.invalidate() {
                                         emit_error(
                                             &error_callback_cb,
                                             Error::with_message(
@@ -632,6 +937,44 @@ pub struct ConnectParams {
     pub draining: Arc<AtomicBool>,
     pub drained: Option<Arc<Notify>>,
     pub is_default_device: bool,
+    pub state: Arc<StateTracker>,
+    pub position: Arc<PositionTracker>,
+    pub config_tracker: Arc<ConfigTracker>,
+    pub daemon_lost: Arc<AtomicBool>,
+    pub fader: Option<Arc<Fader>>,
+}
+
+/// Invalidates the stream when the core reports an error. A broken connection means the daemon
+/// went away, which is also recorded in `daemon_lost` so the host re-enumerates its devices.
+fn monitor_core(
+    core: &CoreRc,
+    state: Arc<StateTracker>,
+    error_callback: ErrorCallbackArc,
+    daemon_lost: Arc<AtomicBool>,
+) -> CoreListener {
+    core.add_listener_local()
+        .error(move |id, _seq, res, message| {
+            if id != PW_ID_CORE {
+                return;
+            }
+            let disconnected = res == -libc::EPIPE;
+            if disconnected {
+                daemon_lost.store(true, Ordering::Release);
+            }
+            if state.invalidate() {
+                let message = if disconnected {
+                    "PipeWire daemon disconnected".to_owned()
+                } else {
+                    format!("PipeWire server error: {message}")
+                };
+                emit_error(
+                    &error_callback,
+                    Error::with_message(ErrorKind::StreamInvalidated, message)
+                        .with_backend_code(res),
+                );
+            }
+        })
+        .register()
 }
 
 pub fn connect_output<D, E>(
@@ -653,6 +996,11 @@ where
         draining,
         drained,
         is_default_device,
+        state,
+        position,
+        config_tracker,
+        daemon_lost,
+        fader,
     } = params;
 
     let mainloop = MainLoopRc::new(None)?;
@@ -660,31 +1008,17 @@ where
     let core = context.connect_rc(remote_props())?;
 
     let error_callback: ErrorCallbackArc = Arc::new(Mutex::new(error_callback));
-    let invalidated = Arc::new(AtomicBool::new(false));
-
     let pending_device_changed = Arc::new(AtomicBool::new(false));
 
-    let core_monitor = {
-        let invalidated_core = invalidated.clone();
-        let error_callback_core = error_callback.clone();
-        core.add_listener_local()
-            .error(move |id, _seq, _res, message| {
-                if id == PW_ID_CORE && !invalidated_core.swap(true, Ordering::Relaxed) {
-                    emit_error(
-                        &error_callback_core,
-                        Error::with_message(
-                            ErrorKind::StreamInvalidated,
-                            format!("PipeWire server error: {message}"),
-                        ),
-                    );
-                }
-            })
-            .register()
-    };
+    let core_monitor = monitor_core(&core, state.clone(), error_callback.clone(), daemon_lost);
 
     let error_callback_out = error_callback.clone();
     #[cfg(all(target_os = "linux", feature = "realtime"))]
     let rt_promoter = RtPromoter::spawn(error_callback.clone(), config.sample_rate);
+    let initial_config = StreamConfig {
+        buffer_size: BufferSize::Fixed(last_quantum.load(Ordering::Relaxed)),
+        ..config
+    };
     let data = UserData {
         data_callback,
         error_callback,
@@ -693,7 +1027,12 @@ where
         last_quantum,
         start,
         draining,
-        invalidated: invalidated.clone(),
+        state: state.clone(),
+        position,
+        config: initial_config,
+        config_tracker,
+        fader,
+        frames_processed: 0,
         is_default_device,
         has_connected: false,
         pending_device_changed: pending_device_changed.clone(),
@@ -736,6 +1075,8 @@ where
             // set
             match user_data.format.parse(param) {
                 Ok(_) => {
+                    let channels = u32::from(user_data.config.channels);
+                    let rate = user_data.config.sample_rate;
                     let current_channels = user_data.format.channels();
                     let current_rate = user_data.format.rate();
                     let expected_fmt =
@@ -744,7 +1085,12 @@ where
                     let mismatch = current_channels != channels
                         || current_rate != rate
                         || current_fmt != expected_fmt;
-                    if mismatch && !user_data.invalidated.swap(true, Ordering::Relaxed) {
+                    // A config listener lets the stream follow a new channel count or rate, but
+                    // the data callback is bound to the sample format.
+                    if mismatch
+                        && !(current_fmt == expected_fmt && user_data.follow_format())
+                        && user_data.state.invalidate()
+                    {
                         let fmt_note = if current_fmt != expected_fmt {
                             "; sample format differs"
                         } else {
@@ -769,7 +1115,7 @@ where
                     }
                 }
                 Err(e) => {
-                    if !user_data.invalidated.swap(true, Ordering::Relaxed) {
+                    if user_data.state.invalidate() {
                         emit_error(
                             &user_data.error_callback,
                             Error::with_message(
@@ -900,7 +1246,7 @@ where
         core_monitor,
         error_callback: error_callback_out,
         pending_device_changed,
-        invalidated,
+        state,
     })
 }
 
@@ -923,6 +1269,11 @@ where
         draining,
         drained: _,
         is_default_device,
+        state,
+        position,
+        config_tracker,
+        daemon_lost,
+        fader,
     } = params;
 
     let mainloop = MainLoopRc::new(None)?;
@@ -930,31 +1281,17 @@ where
     let core = context.connect_rc(remote_props())?;
 
     let error_callback: ErrorCallbackArc = Arc::new(Mutex::new(error_callback));
-    let invalidated = Arc::new(AtomicBool::new(false));
-
     let pending_device_changed = Arc::new(AtomicBool::new(false));
 
-    let core_monitor = {
-        let invalidated_core = invalidated.clone();
-        let error_callback_core = error_callback.clone();
-        core.add_listener_local()
-            .error(move |id, _seq, _res, message| {
-                if id == PW_ID_CORE && !invalidated_core.swap(true, Ordering::Relaxed) {
-                    emit_error(
-                        &error_callback_core,
-                        Error::with_message(
-                            ErrorKind::StreamInvalidated,
-                            format!("PipeWire server error: {message}"),
-                        ),
-                    );
-                }
-            })
-            .register()
-    };
+    let core_monitor = monitor_core(&core, state.clone(), error_callback.clone(), daemon_lost);
 
     let error_callback_out = error_callback.clone();
     #[cfg(all(target_os = "linux", feature = "realtime"))]
     let rt_promoter = RtPromoter::spawn(error_callback.clone(), config.sample_rate);
+    let initial_config = StreamConfig {
+        buffer_size: BufferSize::Fixed(last_quantum.load(Ordering::Relaxed)),
+        ..config
+    };
     let data = UserData {
         data_callback,
         error_callback,
@@ -963,7 +1300,12 @@ where
         last_quantum,
         start,
         draining,
-        invalidated: invalidated.clone(),
+        state: state.clone(),
+        position,
+        config: initial_config,
+        config_tracker,
+        fader,
+        frames_processed: 0,
         is_default_device,
         has_connected: false,
         pending_device_changed: pending_device_changed.clone(),
@@ -1007,6 +1349,8 @@ where
             // set
             match user_data.format.parse(param) {
                 Ok(_) => {
+                    let channels = u32::from(user_data.config.channels);
+                    let rate = user_data.config.sample_rate;
                     let current_channels = user_data.format.channels();
                     let current_rate = user_data.format.rate();
                     let expected_fmt =
@@ -1015,7 +1359,12 @@ where
                     let mismatch = current_channels != channels
                         || current_rate != rate
                         || current_fmt != expected_fmt;
-                    if mismatch && !user_data.invalidated.swap(true, Ordering::Relaxed) {
+                    // A config listener lets the stream follow a new channel count or rate, but
+                    // the data callback is bound to the sample format.
+                    if mismatch
+                        && !(current_fmt == expected_fmt && user_data.follow_format())
+                        && user_data.state.invalidate()
+                    {
                         let fmt_note = if current_fmt != expected_fmt {
                             "; sample format differs"
                         } else {
@@ -1040,7 +1389,7 @@ where
                     }
                 }
                 Err(e) => {
-                    if !user_data.invalidated.swap(true, Ordering::Relaxed) {
+                    if user_data.state.invalidate() {
                         emit_error(
                             &user_data.error_callback,
                             Error::with_message(
@@ -1146,6 +1495,6 @@ where
         core_monitor,
         error_callback: error_callback_out,
         pending_device_changed,
-        invalidated,
+        state,
     })
 }
@@ -18,11 +18,15 @@ pub mod clock {
     pub const QUANTUM: &str = "clock.quantum";
     pub const MIN_QUANTUM: &str = "clock.min-quantum";
     pub const MAX_QUANTUM: &str = "clock.max-quantum";
+    pub const FORCE_RATE: &str = "clock.force-rate";
+    pub const FORCE_QUANTUM: &str = "clock.force-quantum";
 }
 
 pub mod node {
     pub const RATE: &str = "node.rate";
     pub const LATENCY: &str = "node.latency";
+    pub const FORCE_QUANTUM: &str = "node.force-quantum";
+    pub const LOCK_QUANTUM: &str = "node.lock-quantum";
 }
 
 pub mod default {
@@ -0,0 +1,108 @@
+//! Watching the connection to the PipeWire daemon on behalf of the host.
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use pipewire::{
+    self as pw,
+    context::ContextRc,
+    core::{CoreRc, Listener as CoreListener, PW_ID_CORE},
+    main_loop::MainLoopRc,
+};
+
+use super::{device::remote_props, stream::PwInitGuard};
+
+/// How often the watcher tries to reach the daemon while it is away.
+const RECONNECT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Keeps a connection to the daemon open and sets `daemon_lost` when it breaks, so the host
+/// knows its devices are stale even while no stream is running.
+pub(super) struct DaemonWatch {
+    quit: pw::channel::Sender<()>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DaemonWatch {
+    pub(super) fn spawn(daemon_lost: Arc<AtomicBool>) -> Option<Self> {
+        let (quit, quit_rx) = pw::channel::channel::<()>();
+        let handle = thread::Builder::new()
+            .name("pw_watch".to_owned())
+            .spawn(move || {
+                let _pw = PwInitGuard::new();
+                let Ok(mainloop) = MainLoopRc::new(None) else {
+                    return;
+                };
+                let Ok(context) = ContextRc::new(&mainloop, None) else {
+                    return;
+                };
+                let connection: Rc<RefCell<Option<(CoreRc, CoreListener)>>> =
+                    Rc::new(RefCell::new(None));
+                let broken = Rc::new(Cell::new(true));
+
+                let _receiver = quit_rx.attach(mainloop.loop_(), {
+                    let mainloop = mainloop.clone();
+                    move |()| mainloop.quit()
+                });
+
+                // A core cannot be dropped from its own callbacks, so a broken connection is
+                // replaced from the timer.
+                let timer = mainloop.loop_().add_timer({
+                    let connection = connection.clone();
+                    let broken = broken.clone();
+                    move |_| {
+                        if !broken.get() {
+                            return;
+                        }
+                        connection.borrow_mut().take();
+                        let Ok(core) = context.connect_rc(remote_props()) else {
+                            return;
+                        };
+                        let listener = core
+                            .add_listener_local()
+                            .error({
+                                let broken = broken.clone();
+                                let daemon_lost = daemon_lost.clone();
+                                move |id, _seq, res, _message| {
+                                    if id == PW_ID_CORE && res == -libc::EPIPE {
+                                        daemon_lost.store(true, Ordering::Release);
+                                        broken.set(true);
+                                    }
+                                }
+                            })
+                            .register();
+                        *connection.borrow_mut() = Some((core, listener));
+                        broken.set(false);
+                    }
+                });
+                let _ = timer.update_timer(Some(Duration::from_nanos(1)), Some(RECONNECT_INTERVAL));
+
+                mainloop.run();
+
+                // The core goes before the context, which the timer owns.
+                connection.borrow_mut().take();
+                drop(timer);
+            })
+            .ok()?;
+        Some(Self {
+            quit,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for DaemonWatch {
+    fn drop(&mut self) {
+        let _ = self.quit.send(());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
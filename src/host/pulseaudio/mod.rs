@@ -16,8 +16,9 @@ pub use stream::Stream;
 
 use crate::{
     BufferSize, CallbackInfo, Data, DeviceDescription, DeviceDescriptionBuilder, DeviceDirection,
-    DeviceId, Error, ErrorKind, FrameCount, HostId, SampleFormat, SampleRate, StreamConfig,
-    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
+    DeviceId, Error, ErrorKind, FrameCount, HostId, PulseAudioStreamOptions, SampleFormat,
+    SampleRate, StreamConfig, StreamOptions, SupportedBufferSize, SupportedStreamConfig,
+    SupportedStreamConfigRange,
     error::ResultExt,
     traits::{DeviceTrait, HostTrait},
 };
@@ -32,6 +33,8 @@ const PULSE_FORMATS: &[SampleFormat] = &[
     SampleFormat::I24,
     SampleFormat::I32,
     SampleFormat::F32,
+    SampleFormat::ALaw,
+    SampleFormat::MuLaw,
 ];
 
 impl TryFrom<protocol::SampleFormat> for SampleFormat {
@@ -49,6 +52,8 @@ impl TryFrom<protocol::SampleFormat> for SampleFormat {
             protocol::SampleFormat::Float32Le | protocol::SampleFormat::Float32Be => {
                 Ok(SampleFormat::F32)
             }
+            protocol::SampleFormat::Alaw => Ok(SampleFormat::ALaw),
+            protocol::SampleFormat::Ulaw => Ok(SampleFormat::MuLaw),
             _ => Err(()),
         }
     }
@@ -71,6 +76,8 @@ impl TryFrom<SampleFormat> for protocol::SampleFormat {
             (SampleFormat::I32, false) => Ok(protocol::SampleFormat::S32Be),
             (SampleFormat::F32, true) => Ok(protocol::SampleFormat::Float32Le),
             (SampleFormat::F32, false) => Ok(protocol::SampleFormat::Float32Be),
+            (SampleFormat::ALaw, _) => Ok(protocol::SampleFormat::Alaw),
+            (SampleFormat::MuLaw, _) => Ok(protocol::SampleFormat::Ulaw),
             _ => Err(()),
         }
     }
@@ -98,15 +105,23 @@ impl From<pulseaudio::ClientError> for Error {
             }
         }
 
-        match err {
+        let error = match &err {
             ServerUnavailable => {
                 Error::with_message(ErrorKind::HostUnavailable, "PulseAudio is not available")
             }
             UnexpectedSequenceNumber | Disconnected => {
                 Error::with_message(ErrorKind::StreamInvalidated, "PulseAudio disconnected")
             }
-            Io(e) => Error::with_message(ErrorKind::StreamInvalidated, format!("I/O error: {e}")),
-            ServerError(e) => Error::with_message(pulse_error_kind(e), format!("{e}")),
+            Io(e) => {
+                let error =
+                    Error::with_message(ErrorKind::StreamInvalidated, format!("I/O error: {e}"));
+                match e.raw_os_error() {
+                    Some(code) => error.with_backend_code(code),
+                    None => error,
+                }
+            }
+            ServerError(e) => Error::with_message(pulse_error_kind(*e), format!("{e}"))
+                .with_backend_code(*e as u32),
             Protocol(e) => {
                 use protocol::ProtocolError::*;
                 let kind = match &e {
@@ -116,9 +131,14 @@ impl From<pulseaudio::ClientError> for Error {
                     UnexpectedCommand(_) | Io(_) => ErrorKind::StreamInvalidated,
                     ServerError(e) => pulse_error_kind(*e),
                 };
-                Error::with_message(kind, format!("{e}"))
+                let error = Error::with_message(kind, format!("{e}"));
+                match e {
+                    ServerError(e) => error.with_backend_code(*e as u32),
+                    _ => error,
+                }
             }
-        }
+        };
+        error.with_source(err)
     }
 }
 
@@ -235,6 +255,7 @@ fn supported_config_ranges(is_playback: bool) -> Vec<SupportedStreamConfigRange>
                 channels: channel_count as _,
                 min_sample_rate: MIN_SAMPLE_RATE,
                 max_sample_rate: protocol::sample_spec::MAX_RATE,
+                sample_rates: None,
                 buffer_size: SupportedBufferSize::Range {
                     min: 1,
                     max: max_frames,
@@ -318,6 +339,31 @@ impl DeviceTrait for Device {
         error_callback: E,
         timeout: Option<Duration>,
     ) -> Result<Self::Stream, Error>
+    where
+        D: FnMut(&Data, &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        let options = StreamOptions {
+            timeout,
+            ..Default::default()
+        };
+        self.build_input_stream_with_options(
+            config,
+            sample_format,
+            &options,
+            data_callback,
+            error_callback,
+        )
+    }
+
+    fn build_input_stream_with_options<D, E>(
+        &self,
+        config: StreamConfig,
+        sample_format: SampleFormat,
+        options: &StreamOptions,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, Error>
     where
         D: FnMut(&Data, &CallbackInfo) + Send + 'static,
         E: FnMut(Error) + Send + 'static,
@@ -353,7 +399,8 @@ impl DeviceTrait for Device {
 
         let sample_spec = make_sample_spec(config, format);
         let channel_map = make_channel_map(config);
-        let buffer_attr = make_record_buffer_attr(config, format);
+        let buffer_attr =
+            apply_buffer_attr_options(make_record_buffer_attr(config, format), &options.pulseaudio);
         let adjust_latency = matches!(config.buffer_size, BufferSize::Fixed(_));
 
         let params = protocol::RecordStreamParams {
@@ -361,6 +408,7 @@ impl DeviceTrait for Device {
             channel_map,
             source_index: Some(info.index),
             buffer_attr,
+            props: stream_props(options)?,
             flags: protocol::stream::StreamFlags {
                 // Start the stream suspended.
                 start_corked: true,
@@ -376,7 +424,7 @@ impl DeviceTrait for Device {
         // to a different source, pulling `capture` backward.
         let data_callback = crate::host::monotonic_input_callback(data_callback);
         let client = client.clone();
-        let stream = if let Some(dur) = timeout {
+        let stream = if let Some(dur) = options.timeout {
             // Run stream creation on a thread so we can bound the wait. If the PulseAudio server
             // is hung, `create_record_stream` would block forever.
             let (tx, rx) = std::sync::mpsc::channel();
@@ -411,6 +459,31 @@ impl DeviceTrait for Device {
         error_callback: E,
         timeout: Option<Duration>,
     ) -> Result<Self::Stream, Error>
+    where
+        D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        let options = StreamOptions {
+            timeout,
+            ..Default::default()
+        };
+        self.build_output_stream_with_options(
+            config,
+            sample_format,
+            &options,
+            data_callback,
+            error_callback,
+        )
+    }
+
+    fn build_output_stream_with_options<D, E>(
+        &self,
+        config: StreamConfig,
+        sample_format: SampleFormat,
+        options: &StreamOptions,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, Error>
     where
         D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
         E: FnMut(Error) + Send + 'static,
@@ -448,7 +521,10 @@ impl DeviceTrait for Device {
 
         let sample_spec = make_sample_spec(config, format);
         let channel_map = make_channel_map(config);
-        let buffer_attr = make_playback_buffer_attr(config, format);
+        let buffer_attr = apply_buffer_attr_options(
+            make_playback_buffer_attr(config, format),
+            &options.pulseaudio,
+        );
         let adjust_latency = matches!(config.buffer_size, BufferSize::Fixed(_));
 
         let params = protocol::PlaybackStreamParams {
@@ -456,6 +532,7 @@ impl DeviceTrait for Device {
             sample_spec,
             channel_map,
             buffer_attr,
+            props: stream_props(options)?,
             flags: protocol::stream::StreamFlags {
                 // Start the stream suspended.
                 start_corked: true,
@@ -471,7 +548,7 @@ impl DeviceTrait for Device {
         // to a different sink, pulling `playback` backward.
         let data_callback = crate::host::monotonic_output_callback(data_callback);
         let client = client.clone();
-        let stream = if let Some(dur) = timeout {
+        let stream = if let Some(dur) = options.timeout {
             // Run stream creation on a thread so we can bound the wait. If the PulseAudio server
             // is hung, `create_playback_stream` would block forever.
             let (tx, rx) = std::sync::mpsc::channel();
@@ -499,16 +576,46 @@ impl DeviceTrait for Device {
     }
 
     fn description(&self) -> Result<DeviceDescription, Error> {
-        let (name, description, direction) = match self {
-            Device::Sink { info, .. } => (&info.name, &info.description, DeviceDirection::Output),
-            Device::Source { info, .. } => (&info.name, &info.description, DeviceDirection::Input),
+        let (name, description, props, direction) = match self {
+            Device::Sink { info, .. } => (
+                &info.name,
+                &info.description,
+                &info.props,
+                DeviceDirection::Output,
+            ),
+            Device::Source { info, .. } => (
+                &info.name,
+                &info.description,
+                &info.props,
+                DeviceDirection::Input,
+            ),
         };
 
         let display_name = String::from_utf8_lossy(description.as_ref().unwrap_or(name).as_bytes());
 
-        Ok(DeviceDescriptionBuilder::new(display_name)
+        let mut builder = DeviceDescriptionBuilder::new(display_name)
             .direction(direction)
-            .build())
+            .stable_name(String::from_utf8_lossy(name.as_bytes()));
+        let prop = |prop| {
+            props
+                .get(prop)
+                .and_then(|value| std::str::from_utf8(value).ok())
+                .map(|value| value.trim_end_matches('\0'))
+                .filter(|value| !value.is_empty())
+        };
+        // udev reports IDs as bare hex, e.g. "1235".
+        let hex_id = |id: Option<&str>| id.and_then(|id| u16::from_str_radix(id, 16).ok());
+        if let Some(vendor) = hex_id(prop(protocol::Prop::DeviceVendorId)) {
+            builder = builder.vendor_id(vendor);
+        }
+        if let Some(product) = hex_id(prop(protocol::Prop::DeviceProductId)) {
+            builder = builder.product_id(product);
+        }
+        if let Some(serial) = prop(protocol::Prop::DeviceSerial) {
+            builder = builder.serial_number(serial);
+        }
+
+        Ok(builder.build())
     }
 
     fn id(&self) -> Result<DeviceId, Error> {
@@ -654,6 +761,68 @@ fn make_record_buffer_attr(
     }
 }
 
+/// Overrides the buffer attributes derived from the stream config with those set explicitly.
+fn apply_buffer_attr_options(
+    mut attr: protocol::stream::BufferAttr,
+    options: &PulseAudioStreamOptions,
+) -> protocol::stream::BufferAttr {
+    if let Some(max_length) = options.max_length {
+        attr.max_length = max_length;
+    }
+    if let Some(target_length) = options.target_length {
+        attr.target_length = target_length;
+    }
+    if let Some(pre_buffering) = options.pre_buffering {
+        attr.pre_buffering = pre_buffering;
+    }
+    if let Some(minimum_request_length) = options.minimum_request_length {
+        attr.minimum_request_length = minimum_request_length;
+    }
+    if let Some(fragment_size) = options.fragment_size {
+        attr.fragment_size = fragment_size;
+    }
+    attr
+}
+
+/// Builds the proplist carrying the stream's naming and media role.
+fn stream_props(options: &StreamOptions) -> Result<protocol::Props, Error> {
+    let mut props = protocol::Props::new();
+    let entries = [
+        (
+            protocol::Prop::ApplicationName,
+            "application name",
+            options.application_name.as_deref(),
+        ),
+        (
+            protocol::Prop::MediaName,
+            "stream name",
+            options.stream_name.as_deref(),
+        ),
+        (
+            protocol::Prop::ApplicationIconName,
+            "icon name",
+            options.icon_name.as_deref(),
+        ),
+        (
+            protocol::Prop::MediaRole,
+            "media role",
+            options.media_role.map(|role| role.pulseaudio_role()),
+        ),
+    ];
+    for (prop, what, value) in entries {
+        if let Some(value) = value {
+            let value = CString::new(value).map_err(|_| {
+                Error::with_message(
+                    ErrorKind::InvalidInput,
+                    format!("the {what} must not contain NUL bytes"),
+                )
+            })?;
+            props.set(prop, value);
+        }
+    }
+    Ok(props)
+}
+
 impl PartialEq for Device {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
@@ -16,8 +16,15 @@ use futures_util::FutureExt as _;
 use pulseaudio::{AsPlaybackSource, protocol};
 
 use crate::{
-    CallbackInfo, Data, Error, ErrorKind, FrameCount, SampleFormat, StreamInstant, StreamTimestamp,
-    host::{ErrorCallbackArc, emit_error, latch::Latch},
+    ALaw, CallbackInfo, Data, Error, ErrorKind, FrameCount, MuLaw, SampleFormat, StreamInstant,
+    StreamPosition, StreamState, StreamTimestamp,
+    host::{
+        ErrorCallbackArc, emit_error,
+        fade::{self, Fader},
+        latch::Latch,
+    },
+    position::PositionTracker,
+    stream_state::{StateListener, StateTracker},
     traits::StreamTrait,
 };
 
@@ -60,6 +67,7 @@ enum StreamInner {
         handle: LatencyHandle,
         draining: Arc<AtomicBool>,
         fill_usec: Arc<AtomicU64>,
+        fader: Arc<Fader>,
     },
     Record {
         stream: pulseaudio::RecordStream,
@@ -70,6 +78,8 @@ enum StreamInner {
 
 pub struct Stream {
     inner: StreamInner,
+    state: Arc<StateTracker>,
+    position: Arc<PositionTracker>,
     workers: Vec<std::thread::JoinHandle<()>>,
     latch: Latch,
 }
@@ -77,7 +87,13 @@ pub struct Stream {
 impl Drop for Stream {
     fn drop(&mut self) {
         match &mut self.inner {
-            StreamInner::Playback { stream, handle, .. } => {
+            StreamInner::Playback {
+                stream,
+                handle,
+                fader,
+                ..
+            } => {
+                fader.fade_out(true, None);
                 handle.cancel();
                 // Help the play_all driver thread terminate by
                 // queueing a delete, which causes the reactor to drop
@@ -111,11 +127,13 @@ impl StreamTrait for Stream {
                 stream,
                 handle,
                 draining,
+                fader,
                 ..
             } => {
                 // Clear any pending drain so the write callback resumes pulling real audio.
                 draining.store(false, Ordering::Relaxed);
                 block_on(stream.uncork()).map_err(Error::from)?;
+                fader.fade_in();
                 handle.notify();
             }
             StreamInner::Record { stream, handle, .. } => {
@@ -124,12 +142,16 @@ impl StreamTrait for Stream {
                 handle.notify();
             }
         }
+        self.state.set(StreamState::Running);
         Ok(())
     }
 
     fn pause(&self) -> Result<(), Error> {
         let res = match &self.inner {
-            StreamInner::Playback { stream, .. } => block_on(stream.cork()),
+            StreamInner::Playback { stream, fader, .. } => {
+                fader.fade_out(true, None);
+                block_on(stream.cork())
+            }
             StreamInner::Record { stream, .. } => block_on(stream.cork()),
         };
         res.map_err(Error::from)?;
@@ -138,6 +160,7 @@ impl StreamTrait for Stream {
                 handle.notify();
             }
         }
+        self.state.set(StreamState::Paused);
         Ok(())
     }
 
@@ -148,14 +171,20 @@ impl StreamTrait for Stream {
                 handle,
                 draining,
                 fill_usec,
+                fader,
                 ..
             } => {
+                // The ramp drains with the rest of the buffered audio, so only wait for it to be
+                // written.
+                let spent = fader.fade_out(false, timeout);
+                let timeout = timeout.map(|timeout| timeout.saturating_sub(spent));
                 // TODO: use PulseAudio's drain() when https://github.com/colinmarc/pulseaudio-rs/pull/9 is merged.
                 draining.store(true, Ordering::Relaxed);
                 if timeout != Some(Duration::ZERO) {
                     let buffered = Duration::from_micros(fill_usec.load(Ordering::Relaxed));
                     let wait = timeout.map_or(buffered, |t| buffered.min(t));
                     if !wait.is_zero() {
+                        self.state.set(StreamState::Draining);
                         std::thread::sleep(wait);
                     }
                 }
@@ -169,6 +198,33 @@ impl StreamTrait for Stream {
                 handle.notify();
             }
         }
+        self.state.set(StreamState::Paused);
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        match &self.inner {
+            StreamInner::Playback {
+                stream,
+                handle,
+                fader,
+                ..
+            } => {
+                // Cutting into queued audio clicks as well, so ramp out before and back in after.
+                let running = fader.is_running();
+                fader.fade_out(true, None);
+                let result = block_on(stream.flush()).map_err(Error::from);
+                if running {
+                    fader.fade_in();
+                }
+                result?;
+                handle.notify();
+            }
+            StreamInner::Record { stream, handle, .. } => {
+                block_on(stream.flush()).map_err(Error::from)?;
+                handle.notify();
+            }
+        }
         Ok(())
     }
 
@@ -194,13 +250,52 @@ impl StreamTrait for Stream {
         let frame_size = spec.channels as usize * spec.format.bytes_per_sample();
         Ok((bytes / frame_size) as _)
     }
+
+    fn set_buffer_size(&self, _frames: FrameCount) -> Result<(), Error> {
+        // The protocol has SET_*_STREAM_BUFFER_ATTR, but the client library does not expose it.
+        Err(Error::with_message(
+            ErrorKind::UnsupportedOperation,
+            "PulseAudio streams cannot change their buffer attributes while running; \
+             rebuild the stream with new `PulseAudioStreamOptions` instead",
+        ))
+    }
+
+    fn position(&self) -> Result<StreamPosition, Error> {
+        let advancing = matches!(
+            self.state.get(),
+            StreamState::Running | StreamState::Draining
+        );
+        Ok(self.position.get(self.now(), advancing))
+    }
+
+    fn state(&self) -> Result<StreamState, Error> {
+        Ok(self.state.get())
+    }
+
+    fn set_state_listener(&self, listener: StateListener) -> Result<(), Error> {
+        self.state.set_listener(listener);
+        Ok(())
+    }
+
+    fn set_fade(&self, fade: Duration) -> Result<(), Error> {
+        match &self.inner {
+            StreamInner::Playback { fader, .. } => {
+                fader.set_fade(fade);
+                Ok(())
+            }
+            StreamInner::Record { .. } => Err(Error::with_message(
+                ErrorKind::UnsupportedOperation,
+                "input streams do not fade",
+            )),
+        }
+    }
 }
 
 impl Stream {
     pub fn new_playback<D, E>(
         client: pulseaudio::Client,
         params: protocol::PlaybackStreamParams,
-        mut data_callback: D,
+        data_callback: D,
         error_callback: E,
     ) -> Result<Self, Error>
     where
@@ -228,11 +323,13 @@ impl Stream {
         // Silence for unsigned formats is the midpoint, not zero. Among
         // PulseAudio's supported formats, only U8 is unsigned and has a
         // single-byte repeatable silence representation (0x80). Multi-byte
-        // unsigned formats (U16, U32, ...) are not currently supported.
-        let silence_byte = if format == SampleFormat::U8 {
-            0x80u8
-        } else {
-            0u8
+        // unsigned formats (U16, U32, ...) are not currently supported. The
+        // G.711 formats encode linear zero as a single repeatable code too.
+        let silence_byte = match format {
+            SampleFormat::U8 => 0x80u8,
+            SampleFormat::ALaw => ALaw::EQUILIBRIUM.0,
+            SampleFormat::MuLaw => MuLaw::EQUILIBRIUM.0,
+            _ => 0u8,
         };
 
         let handle = LatencyHandle::new();
@@ -244,6 +341,15 @@ impl Stream {
         let fill_usec = Arc::new(AtomicU64::new(0));
         let fill_usec_clone = fill_usec.clone();
 
+        let state = Arc::new(StateTracker::new());
+        let position = Arc::new(PositionTracker::new(sample_spec.sample_rate));
+
+        let fader = Arc::new(Fader::new(
+            sample_spec.channels.into(),
+            sample_spec.sample_rate,
+        ));
+        let mut data_callback = fade::output_callback(fader.clone(), data_callback);
+
         // Wrap the write callback to match the pulseaudio signature.
         let callback = move |buf: &mut [u8]| {
             // Preemptively fill the buffer with silence in case the user
@@ -322,6 +428,7 @@ impl Stream {
         let stream_clone = stream.clone();
         let error_callback_clone = error_callback.clone();
         let cancel_driver = handle.cancel.clone();
+        let state_driver = state.clone();
 
         // The latch is released just before the `Stream` is returned so the driver and latency
         // threads cannot fire any callbacks before the caller has the handle.
@@ -335,6 +442,7 @@ impl Stream {
                 // closes their stream. No need to report it back to
                 // the client.
                 if !cancel_driver.load(Ordering::Relaxed) {
+                    state_driver.set(StreamState::Invalidated);
                     emit_error(&error_callback_clone, Error::from(e));
                 }
             }
@@ -345,6 +453,8 @@ impl Stream {
         let stream_clone = stream.clone();
         let latency_clone = current_latency_micros.clone();
         let poll_clone = last_poll_micros.clone();
+        let state_thread = state.clone();
+        let position_thread = position.clone();
 
         let waiter_latency = latch.waiter();
         let latency_handle = std::thread::spawn(move || {
@@ -357,10 +467,17 @@ impl Stream {
                 let timing_info = match block_on(stream_clone.timing_info()) {
                     Ok(timing_info) => timing_info,
                     Err(e) => {
+                        state_thread.set(StreamState::Invalidated);
                         emit_error(&error_callback, Error::from(e));
                         break;
                     }
                 };
+                // Only a stream that stopped playing can be on a suspended sink; one that is
+                // merely starved or prebuffering keeps its sink running.
+                let suspended = !timing_info.playing
+                    && block_on(client.sink_info(stream_clone.sink()))
+                        .is_ok_and(|sink| sink.state == protocol::SinkState::Suspended);
+                track_server_state(&state_thread, suspended);
 
                 let poll_since_epoch =
                     Instant::now().saturating_duration_since(start).as_micros() as u64;
@@ -375,6 +492,17 @@ impl Stream {
                     timing_info.read_offset,
                 );
 
+                // The sink has read up to `read_offset`, but its latency is still to be played.
+                // Flushing rewinds the write index rather than advancing the read index, so the
+                // count stays continuous.
+                let written = bytes_to_frames(sample_spec, timing_info.write_offset);
+                position_thread.report(
+                    bytes_to_frames(sample_spec, timing_info.read_offset)
+                        .saturating_sub(usec_to_frames(sample_spec, timing_info.sink_usec)),
+                    StreamInstant::from_micros(poll_since_epoch),
+                    written,
+                );
+
                 // Wait until woken by a write/play/pause/drop event or until LATENCY_MAX_INTERVAL.
                 let (lock, cvar) = &*update_thread;
                 let Ok(guard) = lock.lock() else { break };
@@ -394,7 +522,10 @@ impl Stream {
                 handle,
                 draining,
                 fill_usec,
+                fader,
             },
+            state,
+            position,
             workers: vec![driver_handle, latency_handle],
             latch,
         })
@@ -491,6 +622,10 @@ impl Stream {
         let stream_clone = stream.clone();
         let latency_clone = current_latency_micros.clone();
         let poll_clone = last_poll_micros.clone();
+        let state = Arc::new(StateTracker::new());
+        let state_thread = state.clone();
+        let position = Arc::new(PositionTracker::new(sample_spec.sample_rate));
+        let position_thread = position.clone();
 
         // The latch is released just before the `Stream` is returned so the latency thread cannot
         // fire any callbacks before the caller has the handle.
@@ -507,10 +642,16 @@ impl Stream {
                 let timing_info = match block_on(stream_clone.timing_info()) {
                     Ok(timing_info) => timing_info,
                     Err(e) => {
+                        state_thread.set(StreamState::Invalidated);
                         error_callback(Error::from(e));
                         break;
                     }
                 };
+                // The client library reports the source a record stream reads as its sink.
+                let suspended = !timing_info.playing
+                    && block_on(client.source_info(stream_clone.sink()))
+                        .is_ok_and(|source| source.state == protocol::SourceState::Suspended);
+                track_server_state(&state_thread, suspended);
 
                 let poll_since_epoch =
                     Instant::now().saturating_duration_since(start).as_micros() as u64;
@@ -525,6 +666,14 @@ impl Stream {
                     timing_info.read_offset,
                 );
 
+                // The source has written up to `write_offset`, with its latency captured already.
+                position_thread.report(
+                    bytes_to_frames(sample_spec, timing_info.write_offset)
+                        + usec_to_frames(sample_spec, timing_info.source_usec),
+                    StreamInstant::from_micros(poll_since_epoch),
+                    u64::MAX,
+                );
+
                 // Wait until woken by a read/play/pause/drop event or until LATENCY_MAX_INTERVAL.
                 let (lock, cvar) = &*update_thread;
                 let Ok(guard) = lock.lock() else { break };
@@ -542,6 +691,8 @@ impl Stream {
                 start,
                 handle,
             },
+            state,
+            position,
             workers: vec![latency_handle],
             latch,
         })
@@ -553,6 +704,17 @@ impl Stream {
     }
 }
 
+// The client library does not surface the server's suspend and cork events, so the latency
+// polls check whether the stream's device is suspended instead. Server-initiated corks, e.g. by
+// module-role-cork, leave the device running and are not reported.
+fn track_server_state(state: &StateTracker, device_suspended: bool) {
+    if device_suspended {
+        state.replace(StreamState::Running, StreamState::Suspended);
+    } else {
+        state.replace(StreamState::Suspended, StreamState::Running);
+    }
+}
+
 fn store_latency(
     latency_micros: &AtomicU64,
     fill_usec: Option<&AtomicU64>,
@@ -579,3 +741,14 @@ fn store_latency(
         Ordering::Relaxed,
     );
 }
+
+fn bytes_to_frames(sample_spec: protocol::SampleSpec, bytes: i64) -> u64 {
+    let frame_size = sample_spec.channels as u64 * sample_spec.format.bytes_per_sample() as u64;
+    bytes.max(0) as u64 / frame_size.max(1)
+}
+
+fn usec_to_frames(sample_spec: protocol::SampleSpec, usec: u64) -> u64 {
+    (u128::from(usec) * u128::from(sample_spec.sample_rate) / 1_000_000)
+        .try_into()
+        .unwrap_or(u64::MAX)
+}
@@ -728,6 +728,7 @@ impl Device {
                                 channels: format.channels,
                                 min_sample_rate: sample_rate,
                                 max_sample_rate: sample_rate,
+                                sample_rates: None,
                                 buffer_size,
                                 sample_format,
                             });
@@ -87,6 +87,9 @@ impl From<windows::core::Error> for Error {
 
             _ => ErrorKind::BackendError,
         };
-        Error::with_message(kind, IoError::from(e).to_string())
+        let code = e.code().0;
+        Error::with_message(kind, IoError::from(e.clone()).to_string())
+            .with_backend_code(code)
+            .with_source(e)
     }
 }
@@ -1102,6 +1102,7 @@ fn supported_configs(buffer_size: SupportedBufferSize) -> Vec<SupportedStreamCon
                     channels,
                     min_sample_rate: rate,
                     max_sample_rate: rate,
+                    sample_rates: None,
                     buffer_size,
                     sample_format: SUPPORTED_SAMPLE_FORMAT,
                 })
@@ -208,6 +208,8 @@ extern crate wasm_bindgen;
 ))]
 extern crate web_sys;
 
+use std::sync::Arc;
+
 pub use device_description::{
     DeviceDescription, DeviceDescriptionBuilder, DeviceDirection, DeviceType, InterfaceType,
 };
@@ -216,7 +218,7 @@ pub use platform::{
     ALL_HOSTS, Device, Devices, Host, HostId, Stream, SupportedInputConfigs,
     SupportedOutputConfigs, available_hosts, default_host, host_from_id,
 };
-pub use sample_format::{FromSample, I24, Sample, SampleFormat, SizedSample, U24};
+pub use sample_format::{ALaw, FromSample, I24, MuLaw, Sample, SampleFormat, SizedSample, U24};
 #[cfg(all(
     target_arch = "wasm32",
     target_os = "unknown",
@@ -224,14 +226,22 @@ pub use sample_format::{FromSample, I24, Sample, SampleFormat, SizedSample, U24}
 ))]
 use wasm_bindgen::prelude::*;
 
+mod config_change;
 pub mod device_description;
+mod device_match;
+mod dop;
+mod drift;
 mod duplex;
 mod error;
 mod host;
 pub mod platform;
+mod position;
 mod sample_format;
+mod stream_builder;
+mod stream_state;
 mod timestamp;
 pub mod traits;
+mod xrun;
 
 /// Iterator of devices wrapped in a filter to only include certain device types
 pub type DevicesFiltered<I> = std::iter::Filter<I, fn(&<I as Iterator>::Item) -> bool>;
@@ -353,6 +363,22 @@ impl std::str::FromStr for DeviceId {
     }
 }
 
+/// Serialized as its [`Display`](std::fmt::Display) string, e.g. `"alsa:hw:0,0"`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for DeviceId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DeviceId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = std::borrow::Cow::<'de, str>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 /// The buffer size requests the callback size for audio streams.
 ///
 /// This controls the approximate size of the audio buffer passed to your callback.
@@ -411,6 +437,11 @@ impl std::str::FromStr for DeviceId {
 /// [`SupportedBufferSize`]: SupportedStreamConfig::buffer_size
 /// [`SupportedStreamConfig`]: SupportedStreamConfig
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum BufferSize {
     #[default]
     Default,
@@ -475,6 +506,7 @@ impl wasm_bindgen::convert::FromWasmAbi for BufferSize {
     wasm_bindgen
 )]
 #[derive(Clone, Debug, Eq, PartialEq, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StreamConfig {
     pub channels: ChannelCount,
     pub sample_rate: SampleRate,
@@ -483,6 +515,11 @@ pub struct StreamConfig {
 
 /// Describes the minimum and maximum supported buffer size for the device
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum SupportedBufferSize {
     Range {
         min: FrameCount,
@@ -496,13 +533,20 @@ pub enum SupportedBufferSize {
 
 /// Describes a range of supported stream configurations, retrieved via the
 /// [`Device::supported_input/output_configs`](traits::DeviceTrait#required-methods) method.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// Devices that only run at specific rates carry them in
+/// [`sample_rates`](Self::sample_rates); otherwise every rate between
+/// [`min_sample_rate`](Self::min_sample_rate) and [`max_sample_rate`](Self::max_sample_rate)
+/// is supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SupportedStreamConfigRange {
     pub(crate) channels: ChannelCount,
     /// Minimum value for the sample rate of the supported formats.
     pub(crate) min_sample_rate: SampleRate,
     /// Maximum value for the sample rate of the supported formats.
     pub(crate) max_sample_rate: SampleRate,
+    /// The exact sample rates supported, sorted ascending, or `None` for a continuous range.
+    pub(crate) sample_rates: Option<Arc<[SampleRate]>>,
     /// Buffer size ranges supported by the device
     pub(crate) buffer_size: SupportedBufferSize,
     /// Type of data expected by the device.
@@ -533,6 +577,7 @@ pub(crate) mod iter {
 /// [`SupportedStreamConfigRange`] instance or one of the
 /// [`Device::default_input/output_config`](traits::DeviceTrait#required-methods) methods.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SupportedStreamConfig {
     channels: ChannelCount,
     sample_rate: SampleRate,
@@ -550,8 +595,19 @@ pub struct Data {
     sample_format: SampleFormat,
 }
 
+pub use config_change::ConfigChange;
+pub use device_match::{DeviceMatch, MatchConfidence};
+pub use dop::{DOP_MARKERS, DopEncoder, DopStream};
+pub use drift::{DriftEstimator, DriftTracker};
 pub use duplex::{DuplexCallbackInfo, DuplexStreamConfig};
-pub use timestamp::{CallbackInfo, StreamInstant, StreamTimestamp};
+pub use position::StreamPosition;
+pub use stream_builder::{
+    AlsaStreamOptions, JackStreamOptions, MediaRole, PipeWireStreamOptions, PipeWireTarget,
+    PulseAudioStreamOptions, StreamBuilder, StreamOptions,
+};
+pub use stream_state::StreamState;
+pub use timestamp::{CallbackInfo, ClockMapping, StreamInstant, StreamTimestamp};
+pub use xrun::{UnderrunFill, XrunPolicy, XrunRecovery};
 
 impl SupportedStreamConfig {
     pub fn new(
@@ -697,11 +753,35 @@ impl SupportedStreamConfigRange {
             channels,
             min_sample_rate,
             max_sample_rate,
+            sample_rates: None,
             buffer_size,
             sample_format,
         }
     }
 
+    /// Creates a range supporting only the given `sample_rates`.
+    ///
+    /// The rates are sorted and deduplicated; the minimum and maximum sample rates are taken
+    /// from the set. Returns `None` if `sample_rates` is empty.
+    pub fn with_discrete_sample_rates(
+        channels: ChannelCount,
+        sample_rates: impl Into<Vec<SampleRate>>,
+        buffer_size: SupportedBufferSize,
+        sample_format: SampleFormat,
+    ) -> Option<Self> {
+        let mut sample_rates = sample_rates.into();
+        sample_rates.sort_unstable();
+        sample_rates.dedup();
+        Some(Self {
+            channels,
+            min_sample_rate: *sample_rates.first()?,
+            max_sample_rate: *sample_rates.last()?,
+            sample_rates: Some(sample_rates.into()),
+            buffer_size,
+            sample_format,
+        })
+    }
+
     pub fn channels(&self) -> ChannelCount {
         self.channels
     }
@@ -714,6 +794,14 @@ impl SupportedStreamConfigRange {
         self.max_sample_rate
     }
 
+    /// The exact sample rates supported, sorted ascending.
+    ///
+    /// Returns `None` if every rate between [`min_sample_rate`](Self::min_sample_rate) and
+    /// [`max_sample_rate`](Self::max_sample_rate) is supported.
+    pub fn sample_rates(&self) -> Option<&[SampleRate]> {
+        self.sample_rates.as_deref()
+    }
+
     pub fn buffer_size(&self) -> &SupportedBufferSize {
         &self.buffer_size
     }
@@ -726,20 +814,20 @@ impl SupportedStreamConfigRange {
     ///
     /// # Panics
     ///
-    /// Panics if the given `sample_rate` is outside the range specified within
-    /// this [`SupportedStreamConfigRange`] instance. For a non-panicking
+    /// Panics if the given `sample_rate` is not supported by this
+    /// [`SupportedStreamConfigRange`] instance. For a non-panicking
     /// variant, use [`try_with_sample_rate`](#method.try_with_sample_rate).
-    pub fn with_sample_rate(self, sample_rate: SampleRate) -> SupportedStreamConfig {
+    pub fn with_sample_rate(&self, sample_rate: SampleRate) -> SupportedStreamConfig {
         self.try_with_sample_rate(sample_rate)
             .expect("sample rate out of range")
     }
 
     /// Retrieve a [`SupportedStreamConfig`] with the given sample rate.
     ///
-    /// Returns `None` if the given sample rate is outside the range specified
-    /// within this [`SupportedStreamConfigRange`] instance.
-    pub fn try_with_sample_rate(self, sample_rate: SampleRate) -> Option<SupportedStreamConfig> {
-        if self.min_sample_rate <= sample_rate && sample_rate <= self.max_sample_rate {
+    /// Returns `None` if the given sample rate is not supported by this
+    /// [`SupportedStreamConfigRange`] instance (see [`contains_rate`](Self::contains_rate)).
+    pub fn try_with_sample_rate(&self, sample_rate: SampleRate) -> Option<SupportedStreamConfig> {
+        if self.contains_rate(sample_rate) {
             Some(SupportedStreamConfig {
                 channels: self.channels,
                 sample_rate,
@@ -751,9 +839,9 @@ impl SupportedStreamConfigRange {
         }
     }
 
-    /// Turns this [`SupportedStreamConfigRange`] into a [`SupportedStreamConfig`] corresponding to the maximum sample rate.
+    /// Retrieve a [`SupportedStreamConfig`] corresponding to the maximum sample rate.
     #[inline]
-    pub fn with_max_sample_rate(self) -> SupportedStreamConfig {
+    pub fn with_max_sample_rate(&self) -> SupportedStreamConfig {
         SupportedStreamConfig {
             channels: self.channels,
             sample_rate: self.max_sample_rate,
@@ -762,9 +850,15 @@ impl SupportedStreamConfigRange {
         }
     }
 
-    /// Returns `true` if `rate` falls within `[min_sample_rate, max_sample_rate]`.
+    /// Returns `true` if `rate` falls within `[min_sample_rate, max_sample_rate]` and, for a
+    /// range with discrete [`sample_rates`](Self::sample_rates), is one of them.
     pub fn contains_rate(&self, rate: SampleRate) -> bool {
-        self.min_sample_rate <= rate && rate <= self.max_sample_rate
+        if !(self.min_sample_rate <= rate && rate <= self.max_sample_rate) {
+            return false;
+        }
+        self.sample_rates
+            .as_deref()
+            .is_none_or(|rates| rates.binary_search(&rate).is_ok())
     }
 
     /// Retrieve a [`SupportedStreamConfig`] with a standard sample rate.
@@ -773,9 +867,9 @@ impl SupportedStreamConfigRange {
     /// 1. 48 kHz as the dominant native rate on modern hardware.
     /// 2. 44.1 kHz as the standard CD/consumer rate.
     ///
-    /// Returns `None` if neither standard rate falls within the range specified within this
+    /// Returns `None` if neither standard rate is supported by this
     /// [`SupportedStreamConfigRange`] instance.
-    pub fn try_with_standard_sample_rate(self) -> Option<SupportedStreamConfig> {
+    pub fn try_with_standard_sample_rate(&self) -> Option<SupportedStreamConfig> {
         for rate in [SAMPLE_RATE_48K, SAMPLE_RATE_CD] {
             if self.contains_rate(rate) {
                 return Some(self.with_sample_rate(rate));
@@ -792,10 +886,10 @@ impl SupportedStreamConfigRange {
     ///
     /// # Panics
     ///
-    /// Panics if neither standard rate falls within the range specified within this
+    /// Panics if neither standard rate is supported by this
     /// [`SupportedStreamConfigRange`] instance. For a non-panicking variant, use
     /// [`try_with_standard_sample_rate`](Self::try_with_standard_sample_rate).
-    pub fn with_standard_sample_rate(self) -> SupportedStreamConfig {
+    pub fn with_standard_sample_rate(&self) -> SupportedStreamConfig {
         self.try_with_standard_sample_rate()
             .expect("no standard sample rate (48000 or 44100 Hz) in supported range")
     }
@@ -820,7 +914,8 @@ impl SupportedStreamConfigRange {
     /// F32 is ranked highest as the universal realtime audio format, followed by F64. Standard
     /// integer widths descend by bit depth (I32 > I24 > I16); 64-bit integers are deprioritised
     /// below I16 as accumulator types rather than native stream formats. Signed beats unsigned
-    /// at the same width. DSD ranks last as non-PCM.
+    /// at the same width. G.711 A-law/µ-law rank below all linear formats, and DSD ranks last
+    /// as non-PCM.
     ///
     /// **Sample rate**:
     ///
@@ -850,19 +945,22 @@ impl SupportedStreamConfigRange {
                 SampleFormat::DsdU8 => 0,
                 SampleFormat::DsdU16 => 1,
                 SampleFormat::DsdU32 => 2,
-                SampleFormat::U8 => 3,
-                SampleFormat::I8 => 4,
+                // Companded telephony formats: usable, but lossy compared to linear PCM.
+                SampleFormat::MuLaw => 3,
+                SampleFormat::ALaw => 4,
+                SampleFormat::U8 => 5,
+                SampleFormat::I8 => 6,
                 // 64-bit integers: deprioritised below standard audio widths.
-                SampleFormat::U64 => 5,
-                SampleFormat::I64 => 6,
-                SampleFormat::U16 => 7,
-                SampleFormat::I16 => 8,
-                SampleFormat::U24 => 9,
-                SampleFormat::I24 => 10,
-                SampleFormat::U32 => 11,
-                SampleFormat::I32 => 12,
-                SampleFormat::F64 => 13,
-                SampleFormat::F32 => 14,
+                SampleFormat::U64 => 7,
+                SampleFormat::I64 => 8,
+                SampleFormat::U16 => 9,
+                SampleFormat::I16 => 10,
+                SampleFormat::U24 => 11,
+                SampleFormat::I24 => 12,
+                SampleFormat::U32 => 13,
+                SampleFormat::I32 => 14,
+                SampleFormat::F64 => 15,
+                SampleFormat::F32 => 16,
             }
         }
 
@@ -943,6 +1041,7 @@ mod tests {
             channels,
             min_sample_rate: min,
             max_sample_rate: max,
+            sample_rates: None,
             sample_format: format,
         }
     }
@@ -991,13 +1090,63 @@ mod tests {
         assert_eq!(r(8_000, 32_000).try_with_standard_sample_rate(), None);
     }
 
+    #[test]
+    fn discrete_sample_rates() {
+        let range = SupportedStreamConfigRange::with_discrete_sample_rates(
+            2,
+            [96_000, 44_100, 48_000, 44_100],
+            SupportedBufferSize::Unknown,
+            SampleFormat::F32,
+        )
+        .unwrap();
+        assert_eq!(range.min_sample_rate(), 44_100);
+        assert_eq!(range.max_sample_rate(), 96_000);
+        assert_eq!(range.sample_rates(), Some(&[44_100, 48_000, 96_000][..]));
+
+        assert!(range.contains_rate(48_000));
+        assert!(!range.contains_rate(47_000));
+        assert!(range.try_with_sample_rate(47_000).is_none());
+        assert_eq!(
+            range
+                .try_with_standard_sample_rate()
+                .map(|c| c.sample_rate()),
+            Some(SAMPLE_RATE_48K)
+        );
+
+        // 44.1 kHz is chosen when 48 kHz is within bounds but not in the set.
+        let range = SupportedStreamConfigRange::with_discrete_sample_rates(
+            2,
+            [44_100, 88_200],
+            SupportedBufferSize::Unknown,
+            SampleFormat::F32,
+        )
+        .unwrap();
+        assert_eq!(
+            range
+                .try_with_standard_sample_rate()
+                .map(|c| c.sample_rate()),
+            Some(SAMPLE_RATE_CD)
+        );
+
+        assert!(
+            SupportedStreamConfigRange::with_discrete_sample_rates(
+                2,
+                [],
+                SupportedBufferSize::Unknown,
+                SampleFormat::F32,
+            )
+            .is_none()
+        );
+    }
+
     #[test]
     fn cmp_default_heuristics_format_order() {
         use SampleFormat::*;
 
         // Input is deliberately not sorted to prove the sort works.
         let unsorted = [
-            F32, I16, DsdU32, U64, I32, DsdU8, F64, U8, I24, U16, I64, U24, U32, I8, DsdU16,
+            F32, I16, DsdU32, U64, ALaw, I32, DsdU8, F64, U8, I24, MuLaw, U16, I64, U24, U32, I8,
+            DsdU16,
         ];
 
         let mut ranges: Vec<_> = unsorted
@@ -1012,7 +1161,8 @@ mod tests {
         assert_eq!(
             sorted_formats,
             vec![
-                DsdU8, DsdU16, DsdU32, U8, I8, U64, I64, U16, I16, U24, I24, U32, I32, F64, F32,
+                DsdU8, DsdU16, DsdU32, MuLaw, ALaw, U8, I8, U64, I64, U16, I16, U24, I24, U32, I32,
+                F64, F32,
             ]
         );
     }
@@ -1123,4 +1273,77 @@ mod tests {
             .is_ok()
         );
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_representations() {
+        use serde_json::{from_str, json, to_value};
+
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: 48_000,
+            buffer_size: BufferSize::Fixed(256),
+        };
+        let value = to_value(config).unwrap();
+        assert_eq!(
+            value,
+            json!({ "channels": 2, "sample_rate": 48000, "buffer_size": { "fixed": 256 } })
+        );
+        assert_eq!(
+            from_str::<StreamConfig>(&value.to_string()).unwrap(),
+            config
+        );
+        assert_eq!(to_value(BufferSize::Default).unwrap(), json!("default"));
+
+        let supported =
+            SupportedStreamConfig::new(1, 44_100, SupportedBufferSize::Unknown, SampleFormat::I24);
+        let value = to_value(supported).unwrap();
+        assert_eq!(value["buffer_size"], json!("unknown"));
+        assert_eq!(value["sample_format"], json!("i24"));
+        assert_eq!(
+            from_str::<SupportedStreamConfig>(&value.to_string()).unwrap(),
+            supported
+        );
+
+        // Sample formats serialize as their `Display` names.
+        for format in [SampleFormat::DsdU8, SampleFormat::MuLaw, SampleFormat::F32] {
+            assert_eq!(to_value(format).unwrap(), json!(format.to_string()));
+        }
+
+        for &host in platform::ALL_HOSTS {
+            let id = DeviceId::new(host, "hw:0,0");
+            assert_eq!(to_value(&id).unwrap(), json!(format!("{host}:hw:0,0")));
+            assert_eq!(from_str::<DeviceId>(&json!(id).to_string()).unwrap(), id);
+            assert_eq!(from_str::<HostId>(&json!(host).to_string()).unwrap(), host);
+        }
+        assert!(from_str::<DeviceId>(r#""no-separator""#).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_device_description() {
+        use serde_json::{from_value, json, to_value};
+
+        let description = DeviceDescriptionBuilder::new("USB Audio")
+            .interface_type(InterfaceType::Usb)
+            .direction(DeviceDirection::Duplex)
+            .build();
+        let value = to_value(&description).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "name": "USB Audio",
+                "device_type": "unknown",
+                "interface_type": "usb",
+                "direction": "duplex",
+            })
+        );
+        assert_eq!(from_value::<DeviceDescription>(value).unwrap(), description);
+
+        // Variants unknown to this version fall back to `Unknown`.
+        let description: DeviceDescription =
+            from_value(json!({ "name": "Speaker", "device_type": "jukebox" })).unwrap();
+        assert_eq!(description.device_type(), DeviceType::Unknown);
+        assert_eq!(description.direction(), DeviceDirection::Unknown);
+    }
 }
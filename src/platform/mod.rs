@@ -18,7 +18,12 @@ pub use self::platform_impl::*;
     )
 ))]
 #[cfg_attr(docsrs, doc(cfg(feature = "jack")))]
-pub use crate::host::jack::Host as JackHost;
+pub use crate::host::jack::{
+    Bbt as JackBbt, Host as JackHost, PortConnection as JackPortConnection,
+    PortInfo as JackPortInfo, Stream as JackStream, TimebaseRequest as JackTimebaseRequest,
+    Transport as JackTransport, TransportPosition as JackTransportPosition,
+    TransportState as JackTransportState,
+};
 
 #[cfg(all(
     any(
@@ -30,7 +35,10 @@ pub use crate::host::jack::Host as JackHost;
     feature = "pipewire",
 ))]
 #[cfg_attr(docsrs, doc(cfg(feature = "pipewire")))]
-pub use crate::host::pipewire::Host as PipeWireHost;
+pub use crate::host::pipewire::{
+    ClockSettings as PipeWireClockSettings, Host as PipeWireHost, LinkHandle as PipeWireLink,
+    PortInfo as PipeWirePortInfo, Stream as PipeWireStream,
+};
 
 #[cfg(feature = "custom")]
 pub use crate::host::custom::{Device as CustomDevice, Host as CustomHost, Stream as CustomStream};
@@ -261,6 +269,22 @@ macro_rules! impl_platform_host {
             }
         }
 
+        /// Serialized as its [`Display`](std::fmt::Display) string, e.g. `"alsa"`.
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for HostId {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for HostId {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = std::borrow::Cow::<'de, str>::deserialize(deserializer)?;
+                s.parse().map_err(serde::de::Error::custom)
+            }
+        }
+
         impl Devices {
             /// Returns a reference to the underlying platform-specific [`DevicesInner`].
             pub fn as_inner(&self) -> &DevicesInner { &self.0 }
@@ -526,6 +550,64 @@ macro_rules! impl_platform_host {
                 }
             }
 
+            fn build_input_stream_with_options<D, E>(
+                &self,
+                config: crate::StreamConfig,
+                sample_format: crate::SampleFormat,
+                options: &crate::StreamOptions,
+                data_callback: D,
+                error_callback: E,
+            ) -> Result<Self::Stream, crate::Error>
+            where
+                D: FnMut(&crate::Data, &crate::CallbackInfo) + Send + 'static,
+                E: FnMut(crate::Error) + Send + 'static,
+            {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        DeviceInner::$HostVariant(ref d) => d
+                            .build_input_stream_with_options(
+                                config,
+                                sample_format,
+                                options,
+                                data_callback,
+                                error_callback,
+                            )
+                            .map(StreamInner::$HostVariant)
+                            .map(Stream::from),
+                    )*
+                }
+            }
+
+            fn build_output_stream_with_options<D, E>(
+                &self,
+                config: crate::StreamConfig,
+                sample_format: crate::SampleFormat,
+                options: &crate::StreamOptions,
+                data_callback: D,
+                error_callback: E,
+            ) -> Result<Self::Stream, crate::Error>
+            where
+                D: FnMut(&mut crate::Data, &crate::CallbackInfo) + Send + 'static,
+                E: FnMut(crate::Error) + Send + 'static,
+            {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        DeviceInner::$HostVariant(ref d) => d
+                            .build_output_stream_with_options(
+                                config,
+                                sample_format,
+                                options,
+                                data_callback,
+                                error_callback,
+                            )
+                            .map(StreamInner::$HostVariant)
+                            .map(Stream::from),
+                    )*
+                }
+            }
+
             fn supports_duplex(&self) -> bool {
                 match self.0 {
                     $(
@@ -660,6 +742,39 @@ macro_rules! impl_platform_host {
                 }
             }
 
+            fn flush(&self) -> Result<(), crate::Error> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        StreamInner::$HostVariant(ref s) => {
+                            s.flush()
+                        }
+                    )*
+                }
+            }
+
+            fn position(&self) -> Result<crate::StreamPosition, crate::Error> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        StreamInner::$HostVariant(ref s) => {
+                            s.position()
+                        }
+                    )*
+                }
+            }
+
+            fn clock_mapping(&self) -> Result<crate::ClockMapping, crate::Error> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        StreamInner::$HostVariant(ref s) => {
+                            s.clock_mapping()
+                        }
+                    )*
+                }
+            }
+
             fn buffer_size(&self) -> Result<crate::FrameCount, crate::Error> {
                 match self.0 {
                     $(
@@ -671,6 +786,17 @@ macro_rules! impl_platform_host {
                 }
             }
 
+            fn set_buffer_size(&self, frames: crate::FrameCount) -> Result<(), crate::Error> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        StreamInner::$HostVariant(ref s) => {
+                            s.set_buffer_size(frames)
+                        }
+                    )*
+                }
+            }
+
             fn now(&self) -> crate::StreamInstant {
                 match self.0 {
                     $(
@@ -681,6 +807,67 @@ macro_rules! impl_platform_host {
                     )*
                 }
             }
+
+            fn state(&self) -> Result<crate::StreamState, crate::Error> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        StreamInner::$HostVariant(ref s) => {
+                            s.state()
+                        }
+                    )*
+                }
+            }
+
+            fn set_state_listener(
+                &self,
+                listener: Box<dyn FnMut(crate::StreamState) + Send + 'static>,
+            ) -> Result<(), crate::Error> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        StreamInner::$HostVariant(ref s) => {
+                            s.set_state_listener(listener)
+                        }
+                    )*
+                }
+            }
+
+            fn set_xrun_policy(&self, policy: crate::XrunPolicy) -> Result<(), crate::Error> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        StreamInner::$HostVariant(ref s) => {
+                            s.set_xrun_policy(policy)
+                        }
+                    )*
+                }
+            }
+
+            fn set_fade(&self, fade: std::time::Duration) -> Result<(), crate::Error> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        StreamInner::$HostVariant(ref s) => {
+                            s.set_fade(fade)
+                        }
+                    )*
+                }
+            }
+
+            fn set_config_listener(
+                &self,
+                listener: Box<dyn FnMut(crate::ConfigChange) + Send + 'static>,
+            ) -> Result<(), crate::Error> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        StreamInner::$HostVariant(ref s) => {
+                            s.set_config_listener(listener)
+                        }
+                    )*
+                }
+            }
         }
 
         use std::fmt;
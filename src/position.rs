@@ -0,0 +1,163 @@
+//! Frame-accurate playback and capture positions.
+
+use std::sync::{
+    Mutex,
+    atomic::{AtomicU32, Ordering},
+};
+
+use crate::{SampleRate, StreamInstant};
+
+/// How far a stream has progressed through the device, as reported by
+/// [`StreamTrait::position`].
+///
+/// [`StreamTrait::position`]: crate::traits::StreamTrait::position
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StreamPosition {
+    /// Frames the device has played (output) or captured (input) since the stream was first
+    /// started. Time the device spends halted does not count; silence it renders while a backend
+    /// keeps it running through a [`pause`] does. Frames discarded by [`flush`] are never played,
+    /// so they are not counted for output streams.
+    ///
+    /// [`pause`]: crate::traits::StreamTrait::pause
+    /// [`flush`]: crate::traits::StreamTrait::flush
+    pub frames: u64,
+
+    /// When the device reached `frames`, on the clock of [`StreamTrait::now`] and the callback
+    /// timestamps.
+    ///
+    /// [`StreamTrait::now`]: crate::traits::StreamTrait::now
+    pub instant: StreamInstant,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Report {
+    frames: u64,
+    instant: StreamInstant,
+    limit: u64,
+}
+
+#[derive(Debug)]
+struct Inner {
+    latest: Option<Report>,
+    offset: i64,
+}
+
+/// The latest position measured on the audio thread, extrapolated for readers on other threads.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub(crate) struct PositionTracker {
+    sample_rate: AtomicU32,
+    inner: Mutex<Inner>,
+}
+
+#[allow(dead_code)]
+impl PositionTracker {
+    pub(crate) fn new(sample_rate: SampleRate) -> Self {
+        Self {
+            sample_rate: AtomicU32::new(sample_rate),
+            inner: Mutex::new(Inner {
+                latest: None,
+                offset: 0,
+            }),
+        }
+    }
+
+    /// Publishes that the device had reached `frames` at `instant`. Extrapolation never runs past
+    /// `limit`, e.g. the frames written so far on an output stream.
+    ///
+    /// Called from the audio thread, so the update is skipped rather than waiting for a reader.
+    pub(crate) fn report(&self, frames: u64, instant: StreamInstant, limit: u64) {
+        if let Ok(mut inner) = self.inner.try_lock() {
+            inner.latest = Some(Report {
+                frames,
+                instant,
+                limit,
+            });
+        }
+    }
+
+    /// Adds `frames` to every position from now on, accounting for queued frames a flush dropped:
+    /// negative on output, where they are never played, and positive on input, where they were
+    /// captured but are never read.
+    pub(crate) fn offset(&self, frames: i64) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.offset = inner.offset.saturating_add(frames);
+    }
+
+    /// Extrapolates at `sample_rate` from now on, after the stream followed a rate change.
+    pub(crate) fn set_sample_rate(&self, sample_rate: SampleRate) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    /// Returns the position at `now`, advancing the latest report at the sample rate while the
+    /// device is `advancing`.
+    pub(crate) fn get(&self, now: StreamInstant, advancing: bool) -> StreamPosition {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let mut frames = match inner.latest {
+            None => 0,
+            Some(report) => {
+                let elapsed = now
+                    .checked_duration_since(report.instant)
+                    .filter(|_| advancing)
+                    .unwrap_or_default();
+                let sample_rate = self.sample_rate.load(Ordering::Relaxed);
+                let ahead = elapsed.as_nanos() * u128::from(sample_rate) / 1_000_000_000;
+                let ahead = u64::try_from(ahead).unwrap_or(u64::MAX);
+                report
+                    .frames
+                    .saturating_add(ahead)
+                    .min(report.limit.max(report.frames))
+            }
+        };
+        frames = frames.saturating_add_signed(inner.offset);
+        StreamPosition {
+            frames,
+            instant: now,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extrapolates_up_to_limit() {
+        let tracker = PositionTracker::new(48_000);
+        let at = StreamInstant::from_millis(100);
+        assert_eq!(tracker.get(at, true).frames, 0);
+
+        tracker.report(4800, at, 9600);
+        // 10 ms at 48 kHz.
+        let later = StreamInstant::from_millis(110);
+        assert_eq!(tracker.get(later, true).frames, 5280);
+        assert_eq!(tracker.get(later, false).frames, 4800);
+        assert_eq!(tracker.get(later, true).instant, later);
+
+        // Output cannot play more than was written.
+        let much_later = StreamInstant::from_millis(1000);
+        assert_eq!(tracker.get(much_later, true).frames, 9600);
+    }
+
+    #[test]
+    fn offset_accounts_for_flushed_frames() {
+        let tracker = PositionTracker::new(48_000);
+        let at = StreamInstant::from_millis(100);
+        // 1000 frames were written, 200 of them still queued when flushed.
+        tracker.report(800, at, 1000);
+        tracker.offset(-200);
+        tracker.report(1000, at, 1000);
+        assert_eq!(tracker.get(at, false).frames, 800);
+    }
+
+    #[test]
+    fn follows_sample_rate_change() {
+        let tracker = PositionTracker::new(48_000);
+        let at = StreamInstant::from_millis(100);
+        tracker.report(4800, at, u64::MAX);
+        tracker.set_sample_rate(96_000);
+        // 10 ms at 96 kHz.
+        let later = StreamInstant::from_millis(110);
+        assert_eq!(tracker.get(later, true).frames, 5760);
+    }
+}
@@ -7,6 +7,7 @@
 //! a different byte order.
 
 use std::{
+    cmp::Ordering,
     fmt::{self, Display},
     mem,
 };
@@ -62,6 +63,11 @@ use wasm_bindgen::prelude::*;
     wasm_bindgen
 )]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 #[non_exhaustive]
 pub enum SampleFormat {
     /// `i8` with a valid range of `i8::MIN..=i8::MAX` with `0` being the origin.
@@ -117,6 +123,16 @@ pub enum SampleFormat {
 
     /// DSD 1-bit stream in u32 container (32 bits = 32 DSD samples) with 0x69 being the silence byte pattern.
     DsdU32,
+
+    /// G.711 A-law companded 8-bit sample in a u8 container with `0xD5` being the origin.
+    ///
+    /// See [`ALaw`] for conversions to and from linear samples.
+    ALaw,
+
+    /// G.711 µ-law companded 8-bit sample in a u8 container with `0xFF` being the origin.
+    ///
+    /// See [`MuLaw`] for conversions to and from linear samples.
+    MuLaw,
 }
 
 impl SampleFormat {
@@ -144,6 +160,7 @@ impl SampleFormat {
             SampleFormat::DsdU8 => mem::size_of::<u8>(),
             SampleFormat::DsdU16 => mem::size_of::<u16>(),
             SampleFormat::DsdU32 => mem::size_of::<u32>(),
+            SampleFormat::ALaw | SampleFormat::MuLaw => mem::size_of::<u8>(),
         }
     }
 
@@ -169,6 +186,7 @@ impl SampleFormat {
             SampleFormat::F32 => 32,
             SampleFormat::F64 => 64,
             SampleFormat::DsdU8 | SampleFormat::DsdU16 | SampleFormat::DsdU32 => 1,
+            SampleFormat::ALaw | SampleFormat::MuLaw => u8::BITS,
         }
     }
 
@@ -214,6 +232,14 @@ impl SampleFormat {
             SampleFormat::DsdU8 | SampleFormat::DsdU16 | SampleFormat::DsdU32
         )
     }
+
+    /// Returns `true` for the G.711 companded formats, [`SampleFormat::ALaw`] and
+    /// [`SampleFormat::MuLaw`].
+    #[inline]
+    #[must_use]
+    pub fn is_g711(&self) -> bool {
+        matches!(*self, SampleFormat::ALaw | SampleFormat::MuLaw)
+    }
 }
 
 impl Display for SampleFormat {
@@ -236,6 +262,8 @@ impl Display for SampleFormat {
             SampleFormat::DsdU8 => "dsdu8",
             SampleFormat::DsdU16 => "dsdu16",
             SampleFormat::DsdU32 => "dsdu32",
+            SampleFormat::ALaw => "alaw",
+            SampleFormat::MuLaw => "mulaw",
         }
         .fmt(f)
     }
@@ -257,7 +285,7 @@ impl Display for SampleFormat {
 #[diagnostic::on_unimplemented(
     message = "`{Self}` is not a supported cpal sample type",
     label = "this type cannot be used as a cpal audio sample",
-    note = "cpal supports: i8, i16, i32, i64, u8, u16, u32, u64, f32, f64, I24, U24"
+    note = "cpal supports: i8, i16, i32, i64, u8, u16, u32, u64, f32, f64, I24, U24, ALaw, MuLaw"
 )]
 pub trait SizedSample: Sample {
     /// The corresponding [`SampleFormat`] for this sample type.
@@ -319,3 +347,256 @@ impl SizedSample for f32 {
 impl SizedSample for f64 {
     const FORMAT: SampleFormat = SampleFormat::F64;
 }
+
+/// A G.711 A-law encoded sample, as delivered for [`SampleFormat::ALaw`].
+///
+/// Decoding to a 16-bit linear sample is exact, and every code survives a round trip through
+/// [`to_linear`](Self::to_linear) and [`from_linear`](Self::from_linear) unchanged. Conversions to
+/// and from the other linear sample types go through `i16` via [`FromSample`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct ALaw(pub u8);
+
+impl ALaw {
+    /// The A-law code for a linear value of zero.
+    pub const EQUILIBRIUM: Self = Self(0xD5);
+
+    /// Encodes a 16-bit linear sample, rounding towards zero to the nearest A-law step.
+    #[must_use]
+    pub fn from_linear(sample: i16) -> Self {
+        // A-law operates on 13-bit magnitudes.
+        let mut pcm = i32::from(sample) >> 3;
+        let mask = if pcm >= 0 {
+            0xD5
+        } else {
+            pcm = -pcm - 1;
+            0x55
+        };
+        // Segment `n` covers magnitudes up to `(0x20 << n) - 1`.
+        let segment = (0..8).find(|&n| pcm < 0x20 << n).unwrap_or(8);
+        if segment == 8 {
+            return Self(0x7F ^ mask);
+        }
+        let shift = if segment < 2 { 1 } else { segment };
+        let code = (segment << 4) as u8 | ((pcm >> shift) & 0x0F) as u8;
+        Self(code ^ mask)
+    }
+
+    /// Decodes to a 16-bit linear sample.
+    #[must_use]
+    pub fn to_linear(self) -> i16 {
+        let code = self.0 ^ 0x55;
+        let segment = (code & 0x70) >> 4;
+        let mut magnitude = i16::from(code & 0x0F) << 4;
+        magnitude += match segment {
+            0 => 8,
+            _ => 0x108,
+        };
+        if segment > 1 {
+            magnitude <<= segment - 1;
+        }
+        if code & 0x80 != 0 {
+            magnitude
+        } else {
+            -magnitude
+        }
+    }
+}
+
+impl Default for ALaw {
+    fn default() -> Self {
+        Self::EQUILIBRIUM
+    }
+}
+
+/// A G.711 µ-law encoded sample, as delivered for [`SampleFormat::MuLaw`].
+///
+/// Decoding to a 16-bit linear sample is exact, and every code except negative zero (`0x7F`)
+/// survives a round trip through [`to_linear`](Self::to_linear) and
+/// [`from_linear`](Self::from_linear) unchanged. Conversions to and from the other linear sample
+/// types go through `i16` via [`FromSample`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct MuLaw(pub u8);
+
+impl MuLaw {
+    /// The µ-law code for a linear value of zero.
+    pub const EQUILIBRIUM: Self = Self(0xFF);
+
+    const BIAS: i32 = 0x84;
+    const CLIP: i32 = 32635;
+
+    /// Encodes a 16-bit linear sample, rounding towards zero to the nearest µ-law step.
+    #[must_use]
+    pub fn from_linear(sample: i16) -> Self {
+        let pcm = i32::from(sample);
+        let sign = if pcm < 0 { 0x80 } else { 0 };
+        let magnitude = pcm.abs().min(Self::CLIP) + Self::BIAS;
+        // The biased magnitude is at least 0x84, so bits 7..=14 always hold the exponent.
+        let exponent = 31 - (magnitude >> 7).leading_zeros() as i32;
+        let mantissa = (magnitude >> (exponent + 3)) & 0x0F;
+        Self(!(sign | (exponent << 4) | mantissa) as u8)
+    }
+
+    /// Decodes to a 16-bit linear sample.
+    #[must_use]
+    pub fn to_linear(self) -> i16 {
+        let code = !self.0;
+        let exponent = (code >> 4) & 0x07;
+        let mantissa = i32::from(code & 0x0F);
+        let magnitude = (((mantissa << 3) + Self::BIAS) << exponent) - Self::BIAS;
+        (if code & 0x80 != 0 {
+            -magnitude
+        } else {
+            magnitude
+        }) as i16
+    }
+}
+
+impl Default for MuLaw {
+    fn default() -> Self {
+        Self::EQUILIBRIUM
+    }
+}
+
+macro_rules! impl_g711_conversions {
+    ($g711:ty => $($linear:ty),*) => {
+        impl From<$g711> for i16 {
+            fn from(sample: $g711) -> Self {
+                sample.to_linear()
+            }
+        }
+
+        impl From<i16> for $g711 {
+            fn from(sample: i16) -> Self {
+                <$g711>::from_linear(sample)
+            }
+        }
+
+        $(
+            impl FromSample<$g711> for $linear {
+                #[inline]
+                fn from_sample_(sample: $g711) -> Self {
+                    <$linear>::from_sample_(sample.to_linear())
+                }
+            }
+
+            impl FromSample<$linear> for $g711 {
+                #[inline]
+                fn from_sample_(sample: $linear) -> Self {
+                    <$g711>::from_linear(i16::from_sample_(sample))
+                }
+            }
+        )*
+    };
+}
+
+impl_g711_conversions!(ALaw => i8, i16, I24, i32, i64, u8, u16, U24, u32, u64, f32, f64);
+impl_g711_conversions!(MuLaw => i8, i16, I24, i32, i64, u8, u16, U24, u32, u64, f32, f64);
+
+macro_rules! impl_g711_sample {
+    ($g711:ident) => {
+        // Ordered by the linear value, with the code breaking ties so that the order agrees
+        // with `Eq` for µ-law's two zeros.
+        impl Ord for $g711 {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.to_linear()
+                    .cmp(&other.to_linear())
+                    .then(self.0.cmp(&other.0))
+            }
+        }
+
+        impl PartialOrd for $g711 {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Sample for $g711 {
+            type Signed = i16;
+            type Float = f32;
+            const EQUILIBRIUM: Self = $g711::EQUILIBRIUM;
+        }
+
+        impl SizedSample for $g711 {
+            const FORMAT: SampleFormat = SampleFormat::$g711;
+        }
+    };
+}
+
+impl_g711_sample!(ALaw);
+impl_g711_sample!(MuLaw);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alaw_round_trips_every_code() {
+        for code in 0..=u8::MAX {
+            let decoded = ALaw(code).to_linear();
+            assert_eq!(ALaw::from_linear(decoded), ALaw(code), "code {code:#04x}");
+        }
+    }
+
+    #[test]
+    fn mulaw_round_trips_every_code() {
+        for code in 0..=u8::MAX {
+            let decoded = MuLaw(code).to_linear();
+            // 0x7F is negative zero, which re-encodes as positive zero.
+            let expected = if code == 0x7F {
+                MuLaw::EQUILIBRIUM
+            } else {
+                MuLaw(code)
+            };
+            assert_eq!(MuLaw::from_linear(decoded), expected, "code {code:#04x}");
+        }
+    }
+
+    #[test]
+    fn g711_reference_values() {
+        assert_eq!(ALaw::EQUILIBRIUM.to_linear(), 8);
+        assert_eq!(ALaw::from_linear(0), ALaw::EQUILIBRIUM);
+        assert_eq!(ALaw::from_linear(i16::MAX), ALaw(0xAA));
+        assert_eq!(ALaw::from_linear(i16::MIN), ALaw(0x2A));
+        assert_eq!(ALaw(0xAA).to_linear(), 32256);
+        assert_eq!(ALaw(0x2A).to_linear(), -32256);
+
+        assert_eq!(MuLaw::EQUILIBRIUM.to_linear(), 0);
+        assert_eq!(MuLaw::from_linear(0), MuLaw::EQUILIBRIUM);
+        assert_eq!(MuLaw::from_linear(i16::MAX), MuLaw(0x80));
+        assert_eq!(MuLaw::from_linear(i16::MIN), MuLaw(0x00));
+        assert_eq!(MuLaw(0x80).to_linear(), 32124);
+        assert_eq!(MuLaw(0x00).to_linear(), -32124);
+    }
+
+    #[test]
+    fn g711_sample_conversions() {
+        assert_eq!(f32::from_sample(ALaw::EQUILIBRIUM), 8.0 / 32768.0);
+        assert_eq!(f32::from_sample(MuLaw::EQUILIBRIUM), 0.0);
+        assert_eq!(MuLaw::from_sample_(0.0f32), MuLaw::EQUILIBRIUM);
+        assert_eq!(ALaw::from_sample_(-1.0f64), ALaw(0x2A));
+        assert_eq!(i16::from(MuLaw(0x80)), 32124);
+    }
+
+    #[test]
+    fn g711_typed_samples() {
+        assert_eq!(ALaw::FORMAT, SampleFormat::ALaw);
+        assert_eq!(MuLaw::FORMAT, SampleFormat::MuLaw);
+        assert_eq!(<ALaw as Sample>::EQUILIBRIUM, ALaw::EQUILIBRIUM);
+        assert_eq!(ALaw::from_linear(-200).to_sample::<i16>(), -200);
+
+        let mut codes = [MuLaw(0x80), MuLaw::EQUILIBRIUM, MuLaw(0x00), MuLaw(0x7F)];
+        let data = unsafe {
+            crate::Data::from_parts(codes.as_mut_ptr().cast(), codes.len(), SampleFormat::MuLaw)
+        };
+        assert_eq!(data.as_slice::<MuLaw>(), Some(&codes[..]));
+        assert_eq!(data.as_slice::<u8>(), None);
+
+        codes.sort();
+        assert_eq!(
+            codes,
+            [MuLaw(0x00), MuLaw(0x7F), MuLaw::EQUILIBRIUM, MuLaw(0x80)]
+        );
+    }
+}
@@ -0,0 +1,871 @@
+//! Building streams with common and host-specific options.
+
+use std::time::Duration;
+
+use crate::{
+    CallbackInfo, Data, Error, FrameCount, SampleFormat, SampleRate, SizedSample, StreamConfig,
+    XrunPolicy,
+    traits::{DeviceTrait, StreamTrait},
+};
+
+/// Options for building a stream, beyond its [`StreamConfig`] and callbacks.
+///
+/// Usually filled in through a [`StreamBuilder`]. Hosts read the common options and their own
+/// section, and ignore the sections of other hosts, so one set of options can be used with
+/// whichever host is active.
+///
+/// New options may be added in any release, so the struct cannot be built with a literal
+/// outside CPAL; start from [`StreamOptions::default`] or use the builder.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StreamOptions {
+    /// Time to wait for the backend to initialize the stream. `None` waits indefinitely. Not
+    /// all backends honor this value.
+    pub timeout: Option<Duration>,
+
+    /// How the stream handles xruns and system suspends, applied with
+    /// [`StreamTrait::set_xrun_policy`] once the stream is built.
+    pub xrun_policy: XrunPolicy,
+
+    /// Length of the gain ramp output streams fade in and out with, applied with
+    /// [`StreamTrait::set_fade`] once the stream is built. [`Duration::ZERO`] disables fading.
+    pub fade: Duration,
+
+    /// Name of the application the stream belongs to, shown by mixers and patchbays. `None`
+    /// leaves the host's default, usually derived from the process.
+    pub application_name: Option<String>,
+
+    /// Name of this particular stream, e.g. "Music playback" or "Voice chat".
+    pub stream_name: Option<String>,
+
+    /// Icon name from the freedesktop icon theme that mixers show next to the stream.
+    pub icon_name: Option<String>,
+
+    /// What the stream's audio is for, which desktop policy engines use for ducking and routing.
+    pub media_role: Option<MediaRole>,
+
+    /// Options only the ALSA host reads.
+    pub alsa: AlsaStreamOptions,
+
+    /// Options only the JACK host reads.
+    pub jack: JackStreamOptions,
+
+    /// Options only the PipeWire host reads.
+    pub pipewire: PipeWireStreamOptions,
+
+    /// Options only the PulseAudio host reads.
+    pub pulseaudio: PulseAudioStreamOptions,
+}
+
+/// The purpose of a stream's audio, reported to the sound server as its media role.
+///
+/// Only the PipeWire and PulseAudio hosts use it. Each role maps to the closest value of the
+/// server's `media.role` property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MediaRole {
+    /// Music playback.
+    Music,
+    /// Audio of a video or film.
+    Movie,
+    /// Game audio.
+    Game,
+    /// Calls, voice chat and other two-way communication.
+    Voice,
+    /// Notification and event sounds.
+    Notification,
+    /// Audio production, such as a DAW or a synthesizer.
+    Production,
+    /// Screen readers and other accessibility output.
+    Accessibility,
+}
+
+#[allow(dead_code)]
+impl MediaRole {
+    /// The value of PulseAudio's `media.role` property.
+    pub(crate) fn pulseaudio_role(self) -> &'static str {
+        match self {
+            MediaRole::Music => "music",
+            MediaRole::Movie => "video",
+            MediaRole::Game => "game",
+            MediaRole::Voice => "phone",
+            MediaRole::Notification => "event",
+            MediaRole::Production => "production",
+            MediaRole::Accessibility => "a11y",
+        }
+    }
+
+    /// The value of PipeWire's `media.role` property.
+    pub(crate) fn pipewire_role(self) -> &'static str {
+        match self {
+            MediaRole::Music => "Music",
+            MediaRole::Movie => "Movie",
+            MediaRole::Game => "Game",
+            MediaRole::Voice => "Communication",
+            MediaRole::Notification => "Notification",
+            MediaRole::Production => "Production",
+            MediaRole::Accessibility => "Accessibility",
+        }
+    }
+}
+
+/// ALSA-specific stream options.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AlsaStreamOptions {
+    /// Number of periods in the device's ring buffer, at least 2. `None` uses 2. More periods
+    /// tolerate longer scheduling delays at the cost of latency.
+    ///
+    /// The buffer sizes in the supported configurations assume 2 periods. With more, the largest
+    /// period the device accepts shrinks in proportion, and building with a larger
+    /// [`BufferSize::Fixed`](crate::BufferSize::Fixed) fails with
+    /// [`ErrorKind::UnsupportedConfig`](crate::ErrorKind::UnsupportedConfig).
+    pub periods: Option<u32>,
+}
+
+impl AlsaStreamOptions {
+    /// Sets the number of periods in the device's ring buffer.
+    pub fn periods(mut self, periods: u32) -> Self {
+        self.periods = Some(periods);
+        self
+    }
+}
+
+/// JACK-specific stream options.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct JackStreamOptions {
+    /// Short names of the ports the stream registers, one per channel. Empty uses `in_<n>` or
+    /// `out_<n>`.
+    pub port_names: Vec<String>,
+
+    /// Regular expression matching the full names of the ports to connect the stream to,
+    /// e.g. `"ardour:Audio 1/audio_in .*"`, instead of the system ports. See
+    /// `JackStream::connect_to_pattern` for how ports are paired.
+    pub connect_to: Option<String>,
+}
+
+impl JackStreamOptions {
+    /// Sets the short names of the ports the stream registers, one per channel.
+    pub fn port_names<I>(mut self, names: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.port_names = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the regular expression matching the ports to connect the stream to.
+    pub fn connect_to(mut self, pattern: impl Into<String>) -> Self {
+        self.connect_to = Some(pattern.into());
+        self
+    }
+}
+
+/// PipeWire-specific stream options.
+///
+/// The graph runs every node at one rate and quantum. To pin it to 48 kHz and 64 frames for a
+/// low-latency session, set `rate` to 48000, `force_quantum` to 64 and `lock_quantum`:
+///
+/// ```
+/// let options = cpal::PipeWireStreamOptions::default()
+///     .rate(48000)
+///     .force_quantum(64)
+///     .lock_quantum(true);
+/// assert_eq!(options.force_quantum, Some(64));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PipeWireStreamOptions {
+    /// Graph sample rate the node asks for (`node.rate`). The graph switches to it while no other
+    /// node is running, if it is one of the `clock.allowed-rates`; otherwise the stream is
+    /// resampled.
+    pub rate: Option<SampleRate>,
+
+    /// Latency the node asks for, in frames at the stream's sample rate (`node.latency`),
+    /// instead of the one derived from a fixed [`BufferSize`](crate::BufferSize). The graph runs
+    /// with the smallest latency any node asks for.
+    pub latency: Option<FrameCount>,
+
+    /// Quantum the graph is forced to while the node runs (`node.force-quantum`), whatever
+    /// latency other nodes ask for.
+    pub force_quantum: Option<FrameCount>,
+
+    /// Keeps the graph quantum from changing while the node runs (`node.lock-quantum`).
+    pub lock_quantum: bool,
+
+    /// Node to connect the stream to instead of the device's (`target.object`). A stream with a
+    /// target stays on it rather than following the default device.
+    pub target: Option<PipeWireTarget>,
+
+    /// Whether the session manager links the stream to its target. `None` follows
+    /// `PipeWireHost::set_connect_automatically`. `Some(false)` leaves the stream unlinked, for
+    /// other tools or `PipeWireStream::link` to link it.
+    pub connect_automatically: Option<bool>,
+
+    /// Extra properties for the stream's node, applied after the ones CPAL sets so they take
+    /// precedence, e.g. `("node.name", "my-app-output")`.
+    pub properties: Vec<(String, String)>,
+}
+
+impl PipeWireStreamOptions {
+    /// Sets the graph sample rate the node asks for.
+    pub fn rate(mut self, rate: SampleRate) -> Self {
+        self.rate = Some(rate);
+        self
+    }
+
+    /// Sets the latency the node asks for, in frames at the stream's sample rate.
+    pub fn latency(mut self, frames: FrameCount) -> Self {
+        self.latency = Some(frames);
+        self
+    }
+
+    /// Sets the quantum the graph is forced to while the node runs.
+    pub fn force_quantum(mut self, frames: FrameCount) -> Self {
+        self.force_quantum = Some(frames);
+        self
+    }
+
+    /// Sets whether the graph quantum is kept from changing while the node runs.
+    pub fn lock_quantum(mut self, lock: bool) -> Self {
+        self.lock_quantum = lock;
+        self
+    }
+
+    /// Sets the node to connect the stream to.
+    pub fn target(mut self, target: PipeWireTarget) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// Sets whether the session manager links the stream to its target.
+    pub fn connect_automatically(mut self, connect: bool) -> Self {
+        self.connect_automatically = Some(connect);
+        self
+    }
+
+    /// Adds an extra property for the stream's node.
+    pub fn property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.properties.push((key.into(), value.into()));
+        self
+    }
+}
+
+/// A PipeWire node to connect a stream to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PipeWireTarget {
+    /// The node with this `object.serial`, which, unlike its global id, is never reused.
+    Serial(u64),
+    /// The node with this `node.name`.
+    Name(String),
+}
+
+/// PulseAudio-specific stream options: the server-side buffer attributes, in bytes.
+///
+/// Fields left `None` keep the value CPAL derives from the [`BufferSize`](crate::BufferSize),
+/// or the server default. See the PulseAudio documentation of `pa_buffer_attr` for their
+/// meaning.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PulseAudioStreamOptions {
+    /// Maximum length of the buffer (`maxlength`).
+    pub max_length: Option<u32>,
+
+    /// Target fill level of a playback buffer (`tlength`).
+    pub target_length: Option<u32>,
+
+    /// Fill level a playback buffer needs before playback starts (`prebuf`).
+    pub pre_buffering: Option<u32>,
+
+    /// Smallest amount of data the server requests for playback (`minreq`).
+    pub minimum_request_length: Option<u32>,
+
+    /// Size of the chunks the server delivers for capture (`fragsize`).
+    pub fragment_size: Option<u32>,
+}
+
+impl PulseAudioStreamOptions {
+    /// Sets the maximum length of the buffer.
+    pub fn max_length(mut self, bytes: u32) -> Self {
+        self.max_length = Some(bytes);
+        self
+    }
+
+    /// Sets the target fill level of a playback buffer.
+    pub fn target_length(mut self, bytes: u32) -> Self {
+        self.target_length = Some(bytes);
+        self
+    }
+
+    /// Sets the fill level a playback buffer needs before playback starts.
+    pub fn pre_buffering(mut self, bytes: u32) -> Self {
+        self.pre_buffering = Some(bytes);
+        self
+    }
+
+    /// Sets the smallest amount of data the server requests for playback.
+    pub fn minimum_request_length(mut self, bytes: u32) -> Self {
+        self.minimum_request_length = Some(bytes);
+        self
+    }
+
+    /// Sets the size of the chunks the server delivers for capture.
+    pub fn fragment_size(mut self, bytes: u32) -> Self {
+        self.fragment_size = Some(bytes);
+        self
+    }
+}
+
+/// Builds an input or output stream on a device, obtained from
+/// [`DeviceTrait::stream_builder`].
+///
+/// ```no_run
+/// use cpal::traits::{DeviceTrait, HostTrait};
+/// use cpal::{AlsaStreamOptions, MediaRole};
+///
+/// let device = cpal::default_host().default_output_device().unwrap();
+/// let config = device.default_output_config().unwrap().config();
+/// let stream = device
+///     .stream_builder(config)
+///     .timeout(Some(std::time::Duration::from_secs(1)))
+///     .application_name("My Player")
+///     .media_role(MediaRole::Music)
+///     .alsa(AlsaStreamOptions::default().periods(4))
+///     .build_output(
+///         |data: &mut [f32], _: &cpal::CallbackInfo| data.fill(0.0),
+///         |err| eprintln!("stream error: {err}"),
+///     )
+///     .unwrap();
+/// # drop(stream);
+/// ```
+#[derive(Debug)]
+#[must_use = "a stream builder does nothing until a stream is built"]
+pub struct StreamBuilder<'a, D: DeviceTrait + ?Sized> {
+    device: &'a D,
+    config: StreamConfig,
+    options: StreamOptions,
+}
+
+impl<'a, D: DeviceTrait + ?Sized> StreamBuilder<'a, D> {
+    pub(crate) fn new(device: &'a D, config: StreamConfig) -> Self {
+        Self {
+            device,
+            config,
+            options: StreamOptions::default(),
+        }
+    }
+
+    /// Sets the time to wait for the backend to initialize the stream. `None`, the default,
+    /// waits indefinitely.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.options.timeout = timeout;
+        self
+    }
+
+    /// Sets how the stream handles xruns and system suspends.
+    ///
+    /// Building fails with [`ErrorKind::UnsupportedOperation`] if the policy is not the default
+    /// and the backend does not support one.
+    ///
+    /// [`ErrorKind::UnsupportedOperation`]: crate::ErrorKind::UnsupportedOperation
+    pub fn xrun_policy(mut self, policy: XrunPolicy) -> Self {
+        self.options.xrun_policy = policy;
+        self
+    }
+
+    /// Sets the length of the gain ramp an output stream fades in with after starting and out
+    /// with before pausing, stopping, flushing or dropping. See [`StreamTrait::set_fade`].
+    ///
+    /// Building an output stream fails with [`ErrorKind::UnsupportedOperation`] if the fade is
+    /// not zero and the backend cannot fade. Input streams ignore it.
+    ///
+    /// [`ErrorKind::UnsupportedOperation`]: crate::ErrorKind::UnsupportedOperation
+    pub fn fade(mut self, fade: Duration) -> Self {
+        self.options.fade = fade;
+        self
+    }
+
+    /// Sets the application name shown by mixers and patchbays.
+    ///
+    /// PipeWire and PulseAudio report it as the stream's `application.name`, and JACK uses it
+    /// as the client name.
+    pub fn application_name(mut self, name: impl Into<String>) -> Self {
+        self.options.application_name = Some(name.into());
+        self
+    }
+
+    /// Sets the name of the stream.
+    ///
+    /// PipeWire and PulseAudio report it as the stream's `media.name`, and PipeWire also as its
+    /// `node.description`. JACK uses it as the prefix of the port names, unless
+    /// [`JackStreamOptions::port_names`] are given.
+    pub fn stream_name(mut self, name: impl Into<String>) -> Self {
+        self.options.stream_name = Some(name.into());
+        self
+    }
+
+    /// Sets the freedesktop icon name that PipeWire and PulseAudio mixers show for the stream.
+    pub fn icon_name(mut self, name: impl Into<String>) -> Self {
+        self.options.icon_name = Some(name.into());
+        self
+    }
+
+    /// Sets what the stream's audio is for. Only PipeWire and PulseAudio use it.
+    pub fn media_role(mut self, role: MediaRole) -> Self {
+        self.options.media_role = Some(role);
+        self
+    }
+
+    /// Sets the options the ALSA host reads.
+    pub fn alsa(mut self, options: AlsaStreamOptions) -> Self {
+        self.options.alsa = options;
+        self
+    }
+
+    /// Sets the options the JACK host reads.
+    pub fn jack(mut self, options: JackStreamOptions) -> Self {
+        self.options.jack = options;
+        self
+    }
+
+    /// Sets the options the PipeWire host reads.
+    pub fn pipewire(mut self, options: PipeWireStreamOptions) -> Self {
+        self.options.pipewire = options;
+        self
+    }
+
+    /// Sets the options the PulseAudio host reads.
+    pub fn pulseaudio(mut self, options: PulseAudioStreamOptions) -> Self {
+        self.options.pulseaudio = options;
+        self
+    }
+
+    /// Returns the options collected so far.
+    pub fn options(&self) -> &StreamOptions {
+        &self.options
+    }
+
+    /// Builds an input stream delivering samples of type `T`.
+    ///
+    /// See [`DeviceTrait::build_input_stream`] for the callbacks and errors.
+    pub fn build_input<T, C, E>(
+        self,
+        mut data_callback: C,
+        error_callback: E,
+    ) -> Result<D::Stream, Error>
+    where
+        T: SizedSample,
+        C: FnMut(&[T], &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        self.build_input_raw(
+            T::FORMAT,
+            move |data, info| {
+                data_callback(
+                    data.as_slice()
+                        .expect("host supplied incorrect sample type"),
+                    info,
+                )
+            },
+            error_callback,
+        )
+    }
+
+    /// Builds an output stream filled with samples of type `T`.
+    ///
+    /// See [`DeviceTrait::build_output_stream`] for the callbacks and errors.
+    pub fn build_output<T, C, E>(
+        self,
+        mut data_callback: C,
+        error_callback: E,
+    ) -> Result<D::Stream, Error>
+    where
+        T: SizedSample,
+        C: FnMut(&mut [T], &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        self.build_output_raw(
+            T::FORMAT,
+            move |data, info| {
+                data_callback(
+                    data.as_slice_mut()
+                        .expect("host supplied incorrect sample type"),
+                    info,
+                )
+            },
+            error_callback,
+        )
+    }
+
+    /// Builds a dynamically typed input stream.
+    ///
+    /// See [`DeviceTrait::build_input_stream_raw`] for the callbacks and errors.
+    pub fn build_input_raw<C, E>(
+        self,
+        sample_format: SampleFormat,
+        data_callback: C,
+        error_callback: E,
+    ) -> Result<D::Stream, Error>
+    where
+        C: FnMut(&Data, &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        let stream = self.device.build_input_stream_with_options(
+            self.config,
+            sample_format,
+            &self.options,
+            data_callback,
+            error_callback,
+        )?;
+        apply_xrun_policy(&stream, self.options.xrun_policy)?;
+        Ok(stream)
+    }
+
+    /// Builds a dynamically typed output stream.
+    ///
+    /// See [`DeviceTrait::build_output_stream_raw`] for the callbacks and errors.
+    pub fn build_output_raw<C, E>(
+        self,
+        sample_format: SampleFormat,
+        data_callback: C,
+        error_callback: E,
+    ) -> Result<D::Stream, Error>
+    where
+        C: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        let stream = self.device.build_output_stream_with_options(
+            self.config,
+            sample_format,
+            &self.options,
+            data_callback,
+            error_callback,
+        )?;
+        apply_xrun_policy(&stream, self.options.xrun_policy)?;
+        if !self.options.fade.is_zero() {
+            stream.set_fade(self.options.fade)?;
+        }
+        Ok(stream)
+    }
+}
+
+/// Streams start with the default policy, so only a different one needs backend support.
+fn apply_xrun_policy(stream: &impl StreamTrait, policy: XrunPolicy) -> Result<(), Error> {
+    if policy != XrunPolicy::default() {
+        stream.set_xrun_policy(policy)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{
+        BufferSize, DeviceDescription, DeviceId, ErrorKind, StreamInstant, SupportedStreamConfig,
+        SupportedStreamConfigRange, XrunRecovery,
+    };
+
+    /// What a [`RecordingDevice`] and its streams were asked to do.
+    #[derive(Debug, Default)]
+    pub(crate) struct Recorded {
+        pub(crate) options: Option<StreamOptions>,
+        pub(crate) xrun_policy: Option<XrunPolicy>,
+        pub(crate) fade: Option<Duration>,
+    }
+
+    /// A device that records the options its streams are built with.
+    #[derive(Clone, Debug, Default)]
+    pub(crate) struct RecordingDevice {
+        pub(crate) recorded: Arc<Mutex<Recorded>>,
+        pub(crate) supports_fade: bool,
+    }
+
+    #[derive(Debug)]
+    pub(crate) struct RecordingStream {
+        recorded: Arc<Mutex<Recorded>>,
+        supports_fade: bool,
+    }
+
+    impl RecordingDevice {
+        fn build(&self, options: &StreamOptions) -> Result<RecordingStream, Error> {
+            self.recorded.lock().unwrap().options = Some(options.clone());
+            Ok(RecordingStream {
+                recorded: self.recorded.clone(),
+                supports_fade: self.supports_fade,
+            })
+        }
+    }
+
+    impl PartialEq for RecordingDevice {
+        fn eq(&self, other: &Self) -> bool {
+            Arc::ptr_eq(&self.recorded, &other.recorded)
+        }
+    }
+
+    impl Eq for RecordingDevice {}
+
+    impl std::hash::Hash for RecordingDevice {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            Arc::as_ptr(&self.recorded).hash(state);
+        }
+    }
+
+    impl std::fmt::Display for RecordingDevice {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("Recording device")
+        }
+    }
+
+    impl DeviceTrait for RecordingDevice {
+        type SupportedInputConfigs = std::vec::IntoIter<SupportedStreamConfigRange>;
+        type SupportedOutputConfigs = std::vec::IntoIter<SupportedStreamConfigRange>;
+        type Stream = RecordingStream;
+
+        fn description(&self) -> Result<DeviceDescription, Error> {
+            Err(ErrorKind::UnsupportedOperation.into())
+        }
+
+        fn id(&self) -> Result<DeviceId, Error> {
+            Err(ErrorKind::UnsupportedOperation.into())
+        }
+
+        fn supported_input_configs(&self) -> Result<Self::SupportedInputConfigs, Error> {
+            Ok(Vec::new().into_iter())
+        }
+
+        fn supported_output_configs(&self) -> Result<Self::SupportedOutputConfigs, Error> {
+            Ok(Vec::new().into_iter())
+        }
+
+        fn default_input_config(&self) -> Result<SupportedStreamConfig, Error> {
+            Err(ErrorKind::UnsupportedOperation.into())
+        }
+
+        fn default_output_config(&self) -> Result<SupportedStreamConfig, Error> {
+            Err(ErrorKind::UnsupportedOperation.into())
+        }
+
+        fn build_input_stream_raw<D, E>(
+            &self,
+            _config: StreamConfig,
+            _sample_format: SampleFormat,
+            _data_callback: D,
+            _error_callback: E,
+            timeout: Option<Duration>,
+        ) -> Result<Self::Stream, Error>
+        where
+            D: FnMut(&Data, &CallbackInfo) + Send + 'static,
+            E: FnMut(Error) + Send + 'static,
+        {
+            self.build(&StreamOptions {
+                timeout,
+                ..Default::default()
+            })
+        }
+
+        fn build_output_stream_raw<D, E>(
+            &self,
+            _config: StreamConfig,
+            _sample_format: SampleFormat,
+            _data_callback: D,
+            _error_callback: E,
+            timeout: Option<Duration>,
+        ) -> Result<Self::Stream, Error>
+        where
+            D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
+            E: FnMut(Error) + Send + 'static,
+        {
+            self.build(&StreamOptions {
+                timeout,
+                ..Default::default()
+            })
+        }
+
+        fn build_input_stream_with_options<D, E>(
+            &self,
+            _config: StreamConfig,
+            _sample_format: SampleFormat,
+            options: &StreamOptions,
+            _data_callback: D,
+            _error_callback: E,
+        ) -> Result<Self::Stream, Error>
+        where
+            D: FnMut(&Data, &CallbackInfo) + Send + 'static,
+            E: FnMut(Error) + Send + 'static,
+        {
+            self.build(options)
+        }
+
+        fn build_output_stream_with_options<D, E>(
+            &self,
+            _config: StreamConfig,
+            _sample_format: SampleFormat,
+            options: &StreamOptions,
+            _data_callback: D,
+            _error_callback: E,
+        ) -> Result<Self::Stream, Error>
+        where
+            D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
+            E: FnMut(Error) + Send + 'static,
+        {
+            self.build(options)
+        }
+    }
+
+    impl StreamTrait for RecordingStream {
+        fn start(&self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn pause(&self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn stop(&self, _timeout: Option<Duration>) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn buffer_size(&self) -> Result<FrameCount, Error> {
+            Ok(512)
+        }
+
+        fn now(&self) -> StreamInstant {
+            StreamInstant::ZERO
+        }
+
+        fn set_xrun_policy(&self, policy: XrunPolicy) -> Result<(), Error> {
+            self.recorded.lock().unwrap().xrun_policy = Some(policy);
+            Ok(())
+        }
+
+        fn set_fade(&self, fade: Duration) -> Result<(), Error> {
+            if !self.supports_fade {
+                return Err(ErrorKind::UnsupportedOperation.into());
+            }
+            self.recorded.lock().unwrap().fade = Some(fade);
+            Ok(())
+        }
+    }
+
+    pub(crate) fn config() -> StreamConfig {
+        StreamConfig {
+            channels: 2,
+            sample_rate: 48000,
+            buffer_size: BufferSize::Default,
+        }
+    }
+
+    #[test]
+    fn setters_fill_the_options() {
+        let options = PipeWireStreamOptions::default()
+            .rate(44100)
+            .latency(256)
+            .lock_quantum(true)
+            .connect_automatically(false)
+            .property("node.group", "players");
+        assert_eq!(options.rate, Some(44100));
+        assert_eq!(options.latency, Some(256));
+        assert_eq!(options.force_quantum, None);
+        assert!(options.lock_quantum);
+        assert_eq!(options.connect_automatically, Some(false));
+        assert_eq!(
+            options.properties,
+            [("node.group".to_owned(), "players".to_owned())]
+        );
+
+        let options = JackStreamOptions::default()
+            .port_names(["left", "right"])
+            .connect_to("ardour:.*");
+        assert_eq!(options.port_names, ["left", "right"]);
+        assert_eq!(options.connect_to.as_deref(), Some("ardour:.*"));
+
+        let options = PulseAudioStreamOptions::default()
+            .target_length(4096)
+            .fragment_size(1024);
+        assert_eq!(options.target_length, Some(4096));
+        assert_eq!(options.fragment_size, Some(1024));
+        assert_eq!(options.max_length, None);
+
+        assert_eq!(AlsaStreamOptions::default().periods(4).periods, Some(4));
+    }
+
+    #[test]
+    fn builder_collects_the_options() {
+        let device = RecordingDevice::default();
+        let builder = device
+            .stream_builder(config())
+            .timeout(Some(Duration::from_secs(1)))
+            .application_name("Player")
+            .stream_name("Music")
+            .media_role(MediaRole::Music)
+            .alsa(AlsaStreamOptions::default().periods(3));
+        let options = builder.options();
+        assert_eq!(options.timeout, Some(Duration::from_secs(1)));
+        assert_eq!(options.application_name.as_deref(), Some("Player"));
+        assert_eq!(options.stream_name.as_deref(), Some("Music"));
+        assert_eq!(options.media_role, Some(MediaRole::Music));
+        assert_eq!(options.alsa.periods, Some(3));
+        assert_eq!(options.jack, JackStreamOptions::default());
+    }
+
+    #[test]
+    fn options_reach_the_device() {
+        let device = RecordingDevice::default();
+        let builder = device
+            .stream_builder(config())
+            .icon_name("audio-player")
+            .jack(JackStreamOptions::default().port_names(["mono"]));
+        let expected = builder.options().clone();
+        builder.build_input::<f32, _, _>(|_, _| {}, |_| {}).unwrap();
+
+        let recorded = device.recorded.lock().unwrap();
+        assert_eq!(recorded.options.as_ref(), Some(&expected));
+        // Defaults are left to the stream.
+        assert_eq!(recorded.xrun_policy, None);
+        assert_eq!(recorded.fade, None);
+    }
+
+    #[test]
+    fn policy_and_fade_are_applied_after_building() {
+        let device = RecordingDevice {
+            supports_fade: true,
+            ..Default::default()
+        };
+        let policy = XrunPolicy {
+            recovery: XrunRecovery::Notify,
+            ..Default::default()
+        };
+        device
+            .stream_builder(config())
+            .xrun_policy(policy)
+            .fade(Duration::from_millis(20))
+            .build_output::<f32, _, _>(|_, _| {}, |_| {})
+            .unwrap();
+
+        let recorded = device.recorded.lock().unwrap();
+        assert_eq!(recorded.xrun_policy, Some(policy));
+        assert_eq!(recorded.fade, Some(Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn unsupported_fade_fails_the_build() {
+        let device = RecordingDevice::default();
+        let err = device
+            .stream_builder(config())
+            .fade(Duration::from_millis(20))
+            .build_output::<f32, _, _>(|_, _| {}, |_| {})
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedOperation);
+
+        // Input streams ignore the fade.
+        device
+            .stream_builder(config())
+            .fade(Duration::from_millis(20))
+            .build_input::<f32, _, _>(|_, _| {}, |_| {})
+            .unwrap();
+    }
+}
@@ -0,0 +1,220 @@
+//! Stream transport state and change notification.
+
+use std::sync::{
+    Mutex, TryLockError,
+    atomic::{AtomicU8, Ordering},
+};
+
+/// The transport state of a stream, as reported by [`StreamTrait::state`].
+///
+/// [`StreamTrait::state`]: crate::traits::StreamTrait::state
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum StreamState {
+    /// Not running: newly built, paused, or stopped. [`start`] resumes it.
+    ///
+    /// [`start`]: crate::traits::StreamTrait::start
+    Paused,
+
+    /// Running: the data callback is being called.
+    Running,
+
+    /// [`stop`] is letting buffered output play out before halting.
+    ///
+    /// [`stop`]: crate::traits::StreamTrait::stop
+    Draining,
+
+    /// Halted by the system or sound server rather than by the application, e.g. an ALSA device
+    /// suspended for power saving or a PulseAudio stream whose sink or source the server
+    /// suspended. The stream returns to [`Running`](Self::Running) when the backend resumes it.
+    Suspended,
+
+    /// No longer usable, e.g. because the device was lost or the server changed the stream's
+    /// configuration. The stream must be rebuilt. This state is final.
+    Invalidated,
+}
+
+impl StreamState {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Running,
+            2 => Self::Draining,
+            3 => Self::Suspended,
+            4 => Self::Invalidated,
+            _ => Self::Paused,
+        }
+    }
+}
+
+/// Listener called with the new state on each [`StreamState`] change.
+pub(crate) type StateListener = Box<dyn FnMut(StreamState) + Send + 'static>;
+
+struct Listener {
+    callback: Option<StateListener>,
+    /// The state most recently delivered, so transitions are reported once and in order.
+    delivered: StreamState,
+}
+
+/// Shared stream state that backends update from whichever thread observes a transition.
+///
+/// Delivery never blocks: a transition raised while the listener is already running, whether
+/// on another thread or from inside the listener itself, is picked up by the thread delivering
+/// once the listener returns.
+#[allow(dead_code)]
+pub(crate) struct StateTracker {
+    state: AtomicU8,
+    listener: Mutex<Listener>,
+}
+
+#[allow(dead_code)]
+impl StateTracker {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: AtomicU8::new(StreamState::Paused as u8),
+            listener: Mutex::new(Listener {
+                callback: None,
+                delivered: StreamState::Paused,
+            }),
+        }
+    }
+
+    pub(crate) fn get(&self) -> StreamState {
+        StreamState::from_u8(self.state.load(Ordering::Acquire))
+    }
+
+    /// Records a transition to `state` and notifies the listener. Once
+    /// [`Invalidated`](StreamState::Invalidated), the state no longer changes.
+    pub(crate) fn set(&self, state: StreamState) {
+        let invalidated = StreamState::Invalidated as u8;
+        let changed = self
+            .state
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                (current != state as u8 && current != invalidated).then_some(state as u8)
+            })
+            .is_ok();
+        if changed {
+            self.notify();
+        }
+    }
+
+    /// Moves to [`Invalidated`](StreamState::Invalidated). Returns `true` for the call that made
+    /// the transition, so the cause is reported to the error callback only once.
+    pub(crate) fn invalidate(&self) -> bool {
+        let invalidated = StreamState::Invalidated as u8;
+        let changed = self.state.swap(invalidated, Ordering::AcqRel) != invalidated;
+        if changed {
+            self.notify();
+        }
+        changed
+    }
+
+    /// Moves from `from` to `to`, unless another transition got there first, e.g. the application
+    /// pausing a stream while the backend resumes it from [`Suspended`](StreamState::Suspended).
+    pub(crate) fn replace(&self, from: StreamState, to: StreamState) {
+        if from != to
+            && self
+                .state
+                .compare_exchange(from as u8, to as u8, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        {
+            self.notify();
+        }
+    }
+
+    /// Replaces the listener. It is called on the next transition, not with the current state.
+    pub(crate) fn set_listener(&self, callback: StateListener) {
+        let mut listener = self.listener.lock().unwrap_or_else(|e| e.into_inner());
+        listener.callback = Some(callback);
+        listener.delivered = self.get();
+    }
+
+    fn notify(&self) {
+        loop {
+            let delivered = match self.listener.try_lock() {
+                Ok(mut listener) => self.deliver(&mut listener),
+                Err(TryLockError::Poisoned(e)) => self.deliver(&mut e.into_inner()),
+                // The thread holding the lock re-checks the state once it releases it.
+                Err(TryLockError::WouldBlock) => return,
+            };
+            // A transition may have slipped in between the last check and releasing the lock.
+            if self.get() == delivered {
+                return;
+            }
+        }
+    }
+
+    fn deliver(&self, listener: &mut Listener) -> StreamState {
+        loop {
+            let state = self.get();
+            if state == listener.delivered {
+                return state;
+            }
+            listener.delivered = state;
+            if let Some(callback) = listener.callback.as_mut() {
+                callback(state);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for StateTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("StateTracker").field(&self.get()).finish()
+    }
+}
+
+impl Default for StateTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn transitions_are_delivered_once() {
+        let tracker = StateTracker::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        tracker.set_listener(Box::new(move |state| sink.lock().unwrap().push(state)));
+
+        tracker.set(StreamState::Running);
+        tracker.set(StreamState::Running);
+        tracker.set(StreamState::Suspended);
+        tracker.set(StreamState::Invalidated);
+        tracker.set(StreamState::Running);
+
+        assert_eq!(tracker.get(), StreamState::Invalidated);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                StreamState::Running,
+                StreamState::Suspended,
+                StreamState::Invalidated
+            ]
+        );
+    }
+
+    #[test]
+    fn reentrant_transition_is_not_lost() {
+        let tracker = Arc::new(StateTracker::new());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (inner, sink) = (tracker.clone(), seen.clone());
+        tracker.set_listener(Box::new(move |state| {
+            sink.lock().unwrap().push(state);
+            if state == StreamState::Draining {
+                inner.set(StreamState::Paused);
+            }
+        }));
+
+        tracker.set(StreamState::Draining);
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [StreamState::Draining, StreamState::Paused]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// A monotonic time instance associated with a stream, retrieved from either:
 ///
@@ -12,7 +12,9 @@ use std::time::Duration;
 /// Within a single stream, all instants share the same clock, so arithmetic between them is
 /// meaningful. Across different streams, origins are not guaranteed to be shared. On some hosts
 /// each stream starts its own independent clock at zero, so subtracting a timestamp from one
-/// stream and one from another may produce a meaningless result.
+/// stream and one from another may produce a meaningless result. To relate instants to the
+/// system clocks, or to instants of another stream, use a [`ClockMapping`] from
+/// [`StreamTrait::clock_mapping`](crate::traits::StreamTrait::clock_mapping).
 ///
 /// ## Time sources by host
 ///
@@ -51,6 +53,22 @@ pub struct StreamTimestamp {
     pub device: StreamInstant,
 }
 
+/// Relates a stream's clock to the system clocks, for correlating audio with video frames, network
+/// packets or anything else timestamped by the operating system.
+///
+/// A mapping pairs a reading of the stream clock with readings of [`Instant`] and, on Linux,
+/// Android and the BSDs, `CLOCK_MONOTONIC`, all taken at the same moment. Conversions assume the
+/// clocks advance at the same rate from that moment on. That holds closely but not exactly for
+/// the time sources listed on [`StreamInstant`]: ALSA timestamps on `CLOCK_MONOTONIC_RAW`, which
+/// NTP does not slew, and the web hosts follow the audio hardware clock. Take a fresh mapping
+/// from time to time rather than keeping one for the life of the stream.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ClockMapping {
+    stream: StreamInstant,
+    instant: Instant,
+    monotonic: Option<Duration>,
+}
+
 /// Information relevant to a single call to the user's stream data callback.
 ///
 /// Whether [`xrun`][CallbackInfo::xrun] reflects a glitch from exactly this block of samples, or
@@ -237,6 +255,159 @@ impl std::ops::Sub<StreamInstant> for StreamInstant {
     }
 }
 
+impl ClockMapping {
+    pub(crate) fn new(
+        stream: StreamInstant,
+        instant: Instant,
+        monotonic: Option<Duration>,
+    ) -> Self {
+        Self {
+            stream,
+            instant,
+            monotonic,
+        }
+    }
+
+    /// Pairs the stream instant returned by `now` with the system clocks read around it.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub(crate) fn capture(now: impl FnOnce() -> StreamInstant) -> Self {
+        let before = Instant::now();
+        let monotonic = monotonic_now();
+        let stream = now();
+        // Split the time `now` took, so the error is at most half of it either way.
+        let half = before.elapsed() / 2;
+        Self::new(
+            stream,
+            before + half,
+            monotonic.map(|monotonic| monotonic + half),
+        )
+    }
+
+    /// Pairs `stream` with `reading`, a reading of `clock` taken at the same moment.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+    ))]
+    pub(crate) fn from_clock_reading(
+        stream: StreamInstant,
+        clock: libc::clockid_t,
+        reading: Duration,
+    ) -> Self {
+        let instant = Instant::now();
+        let monotonic = monotonic_now();
+        let age = clock_now(clock).map_or(Duration::ZERO, |now| now.saturating_sub(reading));
+        Self::new(
+            stream,
+            instant.checked_sub(age).unwrap_or(instant),
+            monotonic.map(|monotonic| monotonic.saturating_sub(age)),
+        )
+    }
+
+    /// The stream instant of the mapping.
+    pub fn stream_instant(&self) -> StreamInstant {
+        self.stream
+    }
+
+    /// The [`Instant`] corresponding to [`stream_instant`](Self::stream_instant).
+    pub fn instant(&self) -> Instant {
+        self.instant
+    }
+
+    /// The `CLOCK_MONOTONIC` reading, in nanoseconds, corresponding to
+    /// [`stream_instant`](Self::stream_instant), or `None` where the platform has no such clock.
+    pub fn monotonic_nanos(&self) -> Option<u64> {
+        self.monotonic
+            .and_then(|monotonic| u64::try_from(monotonic.as_nanos()).ok())
+    }
+
+    /// Converts a stream instant to an [`Instant`], or returns `None` if the result is out of
+    /// range.
+    pub fn to_instant(&self, instant: StreamInstant) -> Option<Instant> {
+        if instant >= self.stream {
+            self.instant.checked_add(instant - self.stream)
+        } else {
+            self.instant.checked_sub(self.stream - instant)
+        }
+    }
+
+    /// Converts an [`Instant`] to a stream instant, or returns `None` if it predates the stream
+    /// clock's origin.
+    pub fn from_instant(&self, instant: Instant) -> Option<StreamInstant> {
+        match instant.checked_duration_since(self.instant) {
+            Some(later) => self.stream.checked_add(later),
+            None => self.stream.checked_sub(self.instant - instant),
+        }
+    }
+
+    /// Converts a stream instant to `CLOCK_MONOTONIC` nanoseconds, or returns `None` if the
+    /// result is out of range or the platform has no such clock.
+    pub fn to_monotonic_nanos(&self, instant: StreamInstant) -> Option<u64> {
+        let monotonic = self.monotonic?;
+        let nanos = if instant >= self.stream {
+            monotonic.checked_add(instant - self.stream)?
+        } else {
+            monotonic.checked_sub(self.stream - instant)?
+        };
+        u64::try_from(nanos.as_nanos()).ok()
+    }
+
+    /// Converts `CLOCK_MONOTONIC` nanoseconds to a stream instant, or returns `None` if they
+    /// predate the stream clock's origin or the platform has no such clock.
+    pub fn from_monotonic_nanos(&self, nanos: u64) -> Option<StreamInstant> {
+        let monotonic = self.monotonic?;
+        let nanos = Duration::from_nanos(nanos);
+        match nanos.checked_sub(monotonic) {
+            Some(later) => self.stream.checked_add(later),
+            None => self.stream.checked_sub(monotonic - nanos),
+        }
+    }
+}
+
+/// Reads `clock`, or returns `None` if it is unavailable.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+))]
+fn clock_now(clock: libc::clockid_t) -> Option<Duration> {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `ts` is a valid, writable timespec.
+    if unsafe { libc::clock_gettime(clock, &mut ts) } != 0 {
+        return None;
+    }
+    Some(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+))]
+fn monotonic_now() -> Option<Duration> {
+    clock_now(libc::CLOCK_MONOTONIC)
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    all(target_arch = "wasm32", target_os = "unknown"),
+)))]
+fn monotonic_now() -> Option<Duration> {
+    None
+}
+
 impl CallbackInfo {
     pub fn new(timestamp: StreamTimestamp, xrun: bool) -> Self {
         Self { timestamp, xrun }
@@ -316,6 +487,43 @@ mod tests {
         assert_eq!(StreamInstant::from_secs_f64(0.0), z);
     }
 
+    #[test]
+    fn test_clock_mapping() {
+        let instant = Instant::now();
+        let mapping = ClockMapping::new(
+            StreamInstant::new(10, 0),
+            instant,
+            Some(Duration::from_secs(1000)),
+        );
+
+        let later = StreamInstant::new(12, 500);
+        let earlier = StreamInstant::new(9, 0);
+        assert_eq!(
+            mapping.to_instant(later),
+            Some(instant + Duration::new(2, 500))
+        );
+        assert_eq!(
+            mapping.from_instant(instant + Duration::new(2, 500)),
+            Some(later)
+        );
+        assert_eq!(
+            mapping.to_instant(earlier),
+            instant.checked_sub(Duration::from_secs(1))
+        );
+
+        assert_eq!(mapping.monotonic_nanos(), Some(1_000_000_000_000));
+        assert_eq!(mapping.to_monotonic_nanos(later), Some(1_002_000_000_500));
+        assert_eq!(mapping.to_monotonic_nanos(earlier), Some(999_000_000_000));
+        assert_eq!(mapping.from_monotonic_nanos(1_002_000_000_500), Some(later));
+        assert_eq!(mapping.from_monotonic_nanos(999_000_000_000), Some(earlier));
+        // Before the stream clock's origin.
+        assert_eq!(mapping.from_monotonic_nanos(989_000_000_000), None);
+
+        let without_monotonic = ClockMapping::new(StreamInstant::ZERO, instant, None);
+        assert_eq!(without_monotonic.to_monotonic_nanos(later), None);
+        assert_eq!(without_monotonic.from_monotonic_nanos(0), None);
+    }
+
     #[test]
     #[should_panic]
     fn test_stream_instant_new_overflow() {
@@ -8,13 +8,17 @@
 use std::{
     fmt::{Debug, Display},
     hash::Hash,
+    sync::Arc,
     time::Duration,
 };
 
 use crate::{
-    CallbackInfo, Data, DeviceDescription, DeviceId, DuplexCallbackInfo, DuplexStreamConfig, Error,
-    ErrorKind, InputDevices, OutputDevices, SampleFormat, SizedSample, StreamConfig, StreamInstant,
-    SupportedStreamConfig, SupportedStreamConfigRange,
+    CallbackInfo, ClockMapping, ConfigChange, Data, DeviceDescription, DeviceId, DeviceMatch,
+    DopStream, DuplexCallbackInfo, DuplexStreamConfig, Error, ErrorKind, InputDevices,
+    OutputDevices, SampleFormat, SizedSample, StreamBuilder, StreamConfig, StreamInstant,
+    StreamOptions, StreamPosition, StreamState, SupportedStreamConfig, SupportedStreamConfigRange,
+    XrunPolicy,
+    device_match::{self, Score},
 };
 
 /// A [`Host`] provides access to the available audio devices on the system.
@@ -70,6 +74,64 @@ pub trait HostTrait {
             .find(|device| device.id().ok().as_ref() == Some(id))
     }
 
+    /// Finds the device that was saved as `id` and `description`, even if its [`DeviceId`] has
+    /// since changed.
+    ///
+    /// A `DeviceId` can go stale across reboots and re-plugging, e.g. when USB cards enumerate
+    /// in a different order and ALSA gives them different card indices. This compares every
+    /// available device against the saved one, weighing the stable attributes of its
+    /// [`DeviceDescription`] (vendor and product ID, serial number, and
+    /// [`stable_name`](DeviceDescription::stable_name)) as well as the `DeviceId` and name, and
+    /// returns the best candidate along with how confidently it matched. Devices whose hardware
+    /// attributes contradict the saved ones are never returned.
+    ///
+    /// Returns `Ok(None)` if no device matches at all. Devices whose ID or description cannot be
+    /// read are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Propagates errors from [`devices`](Self::devices), so that a host that cannot list its
+    /// devices is not mistaken for the saved device having gone.
+    ///
+    /// ```no_run
+    /// use cpal::traits::{DeviceTrait, HostTrait};
+    /// use cpal::MatchConfidence;
+    ///
+    /// let host = cpal::default_host();
+    /// let device = host.default_output_device().unwrap();
+    /// let (id, description) = (device.id().unwrap(), device.description().unwrap());
+    ///
+    /// // ... persisted, and later restored:
+    /// match host.find_device(&id, &description) {
+    ///     Ok(Some(found)) if found.confidence >= MatchConfidence::Id => { /* use found.device */ }
+    ///     Ok(_) => { /* ask the user */ }
+    ///     Err(err) => eprintln!("cannot list devices: {err}"),
+    /// }
+    /// ```
+    fn find_device(
+        &self,
+        id: &DeviceId,
+        description: &DeviceDescription,
+    ) -> Result<Option<DeviceMatch<Self::Device>>, Error> {
+        let mut best: Option<(Score, Self::Device)> = None;
+        for device in self.devices()? {
+            let (Ok(candidate_id), Ok(candidate)) = (device.id(), device.description()) else {
+                continue;
+            };
+            let Some(score) = device_match::score(id, description, &candidate_id, &candidate)
+            else {
+                continue;
+            };
+            if best.as_ref().is_none_or(|(best, _)| score > *best) {
+                best = Some((score, device));
+            }
+        }
+        Ok(best.map(|(score, device)| DeviceMatch {
+            device,
+            confidence: score.confidence,
+        }))
+    }
+
     /// The default input audio device on the system.
     ///
     /// Returns `None` if no input device is available.
@@ -275,7 +337,7 @@ pub trait DeviceTrait: PartialEq + Eq + Hash + Debug + Display + Send + Sync {
     fn build_input_stream<T, D, E>(
         &self,
         config: StreamConfig,
-        mut data_callback: D,
+        data_callback: D,
         error_callback: E,
         timeout: Option<Duration>,
     ) -> Result<Self::Stream, Error>
@@ -284,19 +346,9 @@ pub trait DeviceTrait: PartialEq + Eq + Hash + Debug + Display + Send + Sync {
         D: FnMut(&[T], &CallbackInfo) + Send + 'static,
         E: FnMut(Error) + Send + 'static,
     {
-        self.build_input_stream_raw(
-            config,
-            T::FORMAT,
-            move |data, info| {
-                data_callback(
-                    data.as_slice()
-                        .expect("host supplied incorrect sample type"),
-                    info,
-                )
-            },
-            error_callback,
-            timeout,
-        )
+        self.stream_builder(config)
+            .timeout(timeout)
+            .build_input(data_callback, error_callback)
     }
 
     /// Create an output stream.
@@ -332,7 +384,7 @@ pub trait DeviceTrait: PartialEq + Eq + Hash + Debug + Display + Send + Sync {
     fn build_output_stream<T, D, E>(
         &self,
         config: StreamConfig,
-        mut data_callback: D,
+        data_callback: D,
         error_callback: E,
         timeout: Option<Duration>,
     ) -> Result<Self::Stream, Error>
@@ -341,19 +393,9 @@ pub trait DeviceTrait: PartialEq + Eq + Hash + Debug + Display + Send + Sync {
         D: FnMut(&mut [T], &CallbackInfo) + Send + 'static,
         E: FnMut(Error) + Send + 'static,
     {
-        self.build_output_stream_raw(
-            config,
-            T::FORMAT,
-            move |data, info| {
-                data_callback(
-                    data.as_slice_mut()
-                        .expect("host supplied incorrect sample type"),
-                    info,
-                )
-            },
-            error_callback,
-            timeout,
-        )
+        self.stream_builder(config)
+            .timeout(timeout)
+            .build_output(data_callback, error_callback)
     }
 
     /// Create a dynamically typed input stream.
@@ -446,6 +488,123 @@ pub trait DeviceTrait: PartialEq + Eq + Hash + Debug + Display + Send + Sync {
         D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
         E: FnMut(Error) + Send + 'static;
 
+    /// Returns a [`StreamBuilder`] for a stream with the given configuration, for setting
+    /// options that the `build_*_stream` methods do not take, such as host-specific ones.
+    fn stream_builder(&self, config: StreamConfig) -> StreamBuilder<'_, Self> {
+        StreamBuilder::new(self, config)
+    }
+
+    /// Create a dynamically typed input stream with the given [`StreamOptions`].
+    ///
+    /// This is the method [`StreamBuilder`] builds input streams with. Hosts that read options
+    /// other than the timeout override it; the default ignores them and calls
+    /// [`build_input_stream_raw`](Self::build_input_stream_raw). The
+    /// [`xrun_policy`](StreamOptions::xrun_policy) is applied by the builder and need not be
+    /// handled here.
+    ///
+    /// See [`build_input_stream_raw`](Self::build_input_stream_raw) for parameter and error
+    /// documentation.
+    fn build_input_stream_with_options<D, E>(
+        &self,
+        config: StreamConfig,
+        sample_format: SampleFormat,
+        options: &StreamOptions,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, Error>
+    where
+        D: FnMut(&Data, &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        self.build_input_stream_raw(
+            config,
+            sample_format,
+            data_callback,
+            error_callback,
+            options.timeout,
+        )
+    }
+
+    /// Create a dynamically typed output stream with the given [`StreamOptions`].
+    ///
+    /// The output counterpart of
+    /// [`build_input_stream_with_options`](Self::build_input_stream_with_options). The
+    /// [`fade`](StreamOptions::fade) is applied by the builder too. See
+    /// [`build_output_stream_raw`](Self::build_output_stream_raw) for parameter and error
+    /// documentation.
+    fn build_output_stream_with_options<D, E>(
+        &self,
+        config: StreamConfig,
+        sample_format: SampleFormat,
+        options: &StreamOptions,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, Error>
+    where
+        D: FnMut(&mut Data, &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        self.build_output_stream_raw(
+            config,
+            sample_format,
+            data_callback,
+            error_callback,
+            options.timeout,
+        )
+    }
+
+    /// Create an output stream that plays DSD on a PCM device using DSD-over-PCM (DoP).
+    ///
+    /// The data callback fills a buffer of byte-interleaved DSD data (the
+    /// [`SampleFormat::DsdU8`] layout), which is packed into 24-bit PCM frames carrying the
+    /// `0x05`/`0xFA` DoP markers. The buffer is pre-filled with DSD silence (`0x69`).
+    ///
+    /// # Parameters
+    ///
+    /// * `config` - The PCM carrier configuration. The sample rate is the DSD bit rate divided
+    ///   by 16, e.g. 176 400 Hz for DSD64.
+    /// * `sample_format` - The PCM format to carry DoP in: [`SampleFormat::I24`] or
+    ///   [`SampleFormat::I32`].
+    /// * `data_callback` - Called periodically with a DSD byte buffer to fill. The buffer holds
+    ///   two bytes per channel per PCM frame.
+    /// * `error_callback` - Called when a stream error occurs (e.g., device disconnected).
+    /// * `timeout` - Time to wait for the backend to initialize the stream. `None` waits
+    ///   indefinitely; `Some(duration)` limits how long to wait. Note: not all backends honor
+    ///   this value.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::UnsupportedConfig`] if `sample_format` cannot carry DoP, or the device
+    ///   does not support the PCM configuration.
+    ///
+    /// Otherwise propagates errors from
+    /// [`build_output_stream_raw`](Self::build_output_stream_raw).
+    ///
+    /// [`ErrorKind::UnsupportedConfig`]: crate::ErrorKind::UnsupportedConfig
+    fn build_dop_output_stream<D, E>(
+        &self,
+        config: StreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+        timeout: Option<Duration>,
+    ) -> Result<DopStream<Self::Stream>, Error>
+    where
+        D: FnMut(&mut [u8], &CallbackInfo) + Send + 'static,
+        E: FnMut(Error) + Send + 'static,
+    {
+        crate::dop::check_format(sample_format)?;
+        let silence = Arc::new(crate::dop::Silence::default());
+        let stream = self.build_output_stream_raw(
+            config,
+            sample_format,
+            crate::dop::output_callback(config.channels, silence.clone(), data_callback),
+            error_callback,
+            timeout,
+        )?;
+        Ok(DopStream::new(stream, silence))
+    }
+
     /// Create a synchronized duplex stream whose input and output share the same clock
     /// or OS provided bidirectional aggregate device (macOS). macOS Aggregate device drift
     /// compensation is not required.
@@ -632,6 +791,52 @@ pub trait StreamTrait: Send + Sync {
     /// [`ErrorKind::StreamInvalidated`]: crate::ErrorKind::StreamInvalidated
     fn stop(&self, timeout: Option<Duration>) -> Result<(), Error>;
 
+    /// Discard audio queued in the device buffer without changing the stream's state.
+    ///
+    /// A running output stream carries on with freshly rendered audio straight away, which is
+    /// what a player needs when seeking. A running input stream drops captured frames not yet
+    /// delivered to the data callback. A paused stream stays paused, with nothing left to play
+    /// or deliver on the next [`start`](Self::start).
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::UnsupportedOperation`] if the backend cannot discard queued audio.
+    /// - [`ErrorKind::DeviceNotAvailable`] if the device has been disconnected.
+    /// - [`ErrorKind::StreamInvalidated`] if the stream configuration has changed and the stream
+    ///   must be rebuilt.
+    ///
+    /// [`ErrorKind::UnsupportedOperation`]: crate::ErrorKind::UnsupportedOperation
+    /// [`ErrorKind::DeviceNotAvailable`]: crate::ErrorKind::DeviceNotAvailable
+    /// [`ErrorKind::StreamInvalidated`]: crate::ErrorKind::StreamInvalidated
+    fn flush(&self) -> Result<(), Error> {
+        Err(Error::with_message(
+            ErrorKind::UnsupportedOperation,
+            "flushing is not supported by this backend",
+        ))
+    }
+
+    /// Returns how many frames the device has played (output) or captured (input), and when.
+    ///
+    /// Unlike counting the frames passed to the data callback, this accounts for the audio still
+    /// buffered between the callback and the device, so it can drive a playhead or align
+    /// recordings. Between device updates the position is extrapolated at the sample rate, so
+    /// successive calls keep advancing while the stream runs.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::UnsupportedOperation`] if the backend cannot report the position.
+    /// - [`ErrorKind::StreamInvalidated`] if the stream configuration has changed and the stream
+    ///   must be rebuilt.
+    ///
+    /// [`ErrorKind::UnsupportedOperation`]: crate::ErrorKind::UnsupportedOperation
+    /// [`ErrorKind::StreamInvalidated`]: crate::ErrorKind::StreamInvalidated
+    fn position(&self) -> Result<StreamPosition, Error> {
+        Err(Error::with_message(
+            ErrorKind::UnsupportedOperation,
+            "playback position is not supported by this backend",
+        ))
+    }
+
     /// Returns the backend's best available estimate of the number of frames per callback.
     ///
     /// The value is available immediately after stream creation: for fixed buffer sizes this is
@@ -656,6 +861,32 @@ pub trait StreamTrait: Send + Sync {
     /// [`ErrorKind::BackendError`]: crate::ErrorKind::BackendError
     fn buffer_size(&self) -> Result<crate::FrameCount, Error>;
 
+    /// Changes the number of frames per callback without rebuilding the stream.
+    ///
+    /// The stream keeps running across the change, and [`buffer_size`](Self::buffer_size)
+    /// reports the new size once the backend has applied it. On sound servers the period is
+    /// shared by the whole graph, so other clients may be affected, and the server may settle on
+    /// a different size than requested: see the host documentation.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::UnsupportedOperation`] if the backend cannot resize a running stream.
+    /// - [`ErrorKind::InvalidInput`] if `frames` is zero, or not of a form the backend accepts.
+    /// - [`ErrorKind::UnsupportedConfig`] if the backend refuses the size.
+    /// - [`ErrorKind::StreamInvalidated`] if the stream is no longer usable.
+    ///
+    /// [`ErrorKind::UnsupportedOperation`]: crate::ErrorKind::UnsupportedOperation
+    /// [`ErrorKind::InvalidInput`]: crate::ErrorKind::InvalidInput
+    /// [`ErrorKind::UnsupportedConfig`]: crate::ErrorKind::UnsupportedConfig
+    /// [`ErrorKind::StreamInvalidated`]: crate::ErrorKind::StreamInvalidated
+    fn set_buffer_size(&self, frames: crate::FrameCount) -> Result<(), Error> {
+        let _ = frames;
+        Err(Error::with_message(
+            ErrorKind::UnsupportedOperation,
+            "changing the buffer size of a running stream is not supported by this backend",
+        ))
+    }
+
     /// Returns a [`StreamInstant`] representing the current moment on the stream's clock.
     ///
     /// The clock is **monotonic**: successive calls to `now()` will never return a value earlier
@@ -666,6 +897,144 @@ pub trait StreamTrait: Send + Sync {
     /// stream's data callback via [`crate::StreamTimestamp::callback`] and
     /// [`crate::StreamTimestamp::callback`], so durations between them are meaningful.
     fn now(&self) -> StreamInstant;
+
+    /// Returns a [`ClockMapping`] between the stream's clock and the system clocks, taken now.
+    ///
+    /// The default implementation reads the system clocks around a call to [`now`](Self::now).
+    /// Hosts that know which system clock their timestamps come from pair them up directly.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::UnsupportedOperation`] on `wasm32-unknown-unknown`, which has no system
+    ///   clock.
+    /// - [`ErrorKind::DeviceNotAvailable`] if the device has been disconnected.
+    ///
+    /// [`ErrorKind::UnsupportedOperation`]: crate::ErrorKind::UnsupportedOperation
+    /// [`ErrorKind::DeviceNotAvailable`]: crate::ErrorKind::DeviceNotAvailable
+    fn clock_mapping(&self) -> Result<ClockMapping, Error> {
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        return Ok(ClockMapping::capture(|| self.now()));
+
+        #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+        Err(Error::with_message(
+            ErrorKind::UnsupportedOperation,
+            "system clocks are not available on this platform",
+        ))
+    }
+
+    /// Returns the stream's current transport state.
+    ///
+    /// Besides following [`start`](Self::start), [`pause`](Self::pause) and
+    /// [`stop`](Self::stop), the state reflects changes the application did not request, such as
+    /// the sound server suspending the stream or the device going away.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::UnsupportedOperation`] if the backend does not track stream state.
+    ///
+    /// [`ErrorKind::UnsupportedOperation`]: crate::ErrorKind::UnsupportedOperation
+    fn state(&self) -> Result<StreamState, Error> {
+        Err(Error::with_message(
+            ErrorKind::UnsupportedOperation,
+            "stream state is not supported by this backend",
+        ))
+    }
+
+    /// Sets a listener called with the new state each time [`state`](Self::state) changes,
+    /// replacing any previous listener.
+    ///
+    /// The listener is not called with the state current at registration. It runs on whichever
+    /// thread observes the change, which may be the audio thread, so it should return quickly.
+    /// It may call back into the stream, e.g. to [`pause`](Self::pause) it, but must not call
+    /// `set_state_listener`.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::UnsupportedOperation`] if the backend does not track stream state.
+    ///
+    /// [`ErrorKind::UnsupportedOperation`]: crate::ErrorKind::UnsupportedOperation
+    fn set_state_listener(
+        &self,
+        listener: Box<dyn FnMut(StreamState) + Send + 'static>,
+    ) -> Result<(), Error> {
+        let _ = listener;
+        Err(Error::with_message(
+            ErrorKind::UnsupportedOperation,
+            "stream state is not supported by this backend",
+        ))
+    }
+
+    /// Sets how the stream handles xruns and system suspends from now on.
+    ///
+    /// Streams start with the default [`XrunPolicy`], recovering silently and flagging the xrun
+    /// in the next [`CallbackInfo`]. Backends that cannot detect an xrun or suspend cannot act on
+    /// it either, and backends without an output buffer of their own play silence whatever the
+    /// [`underrun_fill`](XrunPolicy::underrun_fill).
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::UnsupportedOperation`] if the backend does not support a recovery policy.
+    ///
+    /// [`ErrorKind::UnsupportedOperation`]: crate::ErrorKind::UnsupportedOperation
+    fn set_xrun_policy(&self, policy: XrunPolicy) -> Result<(), Error> {
+        let _ = policy;
+        Err(Error::with_message(
+            ErrorKind::UnsupportedOperation,
+            "xrun recovery policies are not supported by this backend",
+        ))
+    }
+
+    /// Sets the length of the gain ramp an output stream fades in with after
+    /// [`start`](Self::start) and fades out with before [`pause`](Self::pause),
+    /// [`stop`](Self::stop), [`flush`](Self::flush) or being dropped, so halting playback does
+    /// not click. [`Duration::ZERO`], the default, disables fading.
+    ///
+    /// The ramp is applied to each buffer after the data callback fills it. Once faded out, the
+    /// data callback is not called until the stream is started again. `pause`, `flush` and
+    /// dropping the stream block until the ramp has played; `stop` blocks until it has been
+    /// written and then drains it with the rest of the buffered audio, within its timeout.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::UnsupportedOperation`] if the backend cannot fade, or for input streams.
+    ///
+    /// [`ErrorKind::UnsupportedOperation`]: crate::ErrorKind::UnsupportedOperation
+    fn set_fade(&self, fade: Duration) -> Result<(), Error> {
+        let _ = fade;
+        Err(Error::with_message(
+            ErrorKind::UnsupportedOperation,
+            "fading is not supported by this backend",
+        ))
+    }
+
+    /// Sets a listener called each time the backend changes the stream's configuration while it
+    /// runs, e.g. when the sound server switches to a different buffer size, replacing any
+    /// previous listener.
+    ///
+    /// Some backends keep a stream with a listener running through changes that would otherwise
+    /// invalidate it, such as a new sample rate or channel count: buffers passed to the data
+    /// callback then follow the new configuration. Changes the stream cannot follow still
+    /// invalidate it, after the listener has been told about them.
+    ///
+    /// The listener runs on whichever thread observes the change, which may be the audio thread,
+    /// so it should return quickly. It must not call `set_config_listener`.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::UnsupportedOperation`] if the backend does not report configuration
+    ///   changes.
+    ///
+    /// [`ErrorKind::UnsupportedOperation`]: crate::ErrorKind::UnsupportedOperation
+    fn set_config_listener(
+        &self,
+        listener: Box<dyn FnMut(ConfigChange) + Send + 'static>,
+    ) -> Result<(), Error> {
+        let _ = listener;
+        Err(Error::with_message(
+            ErrorKind::UnsupportedOperation,
+            "configuration change notification is not supported by this backend",
+        ))
+    }
 }
 
 /// Compile-time assertion that a stream type implements [`Send`].
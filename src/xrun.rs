@@ -0,0 +1,108 @@
+//! Recovery policy for xruns and system suspends.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// What a stream does after an xrun or after the system suspends its device.
+///
+/// An xrun is a buffer overrun on input or underrun on output: the application did not keep up
+/// with the device and audio was lost.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum XrunRecovery {
+    /// Recover and keep running. The next data callback reports the xrun through
+    /// [`CallbackInfo::xrun`](crate::CallbackInfo::xrun).
+    #[default]
+    Silent,
+
+    /// Recover and keep running, and also report an [`ErrorKind::Xrun`] error to the error
+    /// callback.
+    ///
+    /// [`ErrorKind::Xrun`]: crate::ErrorKind::Xrun
+    Notify,
+
+    /// Stop the stream instead of recovering. The error callback receives an
+    /// [`ErrorKind::StreamInvalidated`] error and the stream becomes
+    /// [`StreamState::Invalidated`](crate::StreamState::Invalidated).
+    ///
+    /// [`ErrorKind::StreamInvalidated`]: crate::ErrorKind::StreamInvalidated
+    Stop,
+}
+
+/// What an output stream plays to bridge an underrun once it recovers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UnderrunFill {
+    /// Play silence until the data callback catches up.
+    #[default]
+    Silence,
+
+    /// Play the last buffer the data callback produced once more.
+    RepeatLast,
+}
+
+/// Per-stream xrun and suspend handling, set with [`StreamTrait::set_xrun_policy`].
+///
+/// The default recovers silently and bridges underruns with silence.
+///
+/// [`StreamTrait::set_xrun_policy`]: crate::traits::StreamTrait::set_xrun_policy
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct XrunPolicy {
+    /// How the stream recovers from an xrun or suspend.
+    pub recovery: XrunRecovery,
+
+    /// What an output stream plays after an underrun. Ignored for input streams.
+    pub underrun_fill: UnderrunFill,
+}
+
+/// An [`XrunPolicy`] that the audio thread can read while the application replaces it.
+#[derive(Debug, Default)]
+#[allow(dead_code)]
+pub(crate) struct XrunPolicyCell(AtomicU8);
+
+#[allow(dead_code)]
+impl XrunPolicyCell {
+    pub(crate) fn get(&self) -> XrunPolicy {
+        let bits = self.0.load(Ordering::Relaxed);
+        let recovery = match bits & 0x0f {
+            1 => XrunRecovery::Notify,
+            2 => XrunRecovery::Stop,
+            _ => XrunRecovery::Silent,
+        };
+        let underrun_fill = match bits >> 4 {
+            1 => UnderrunFill::RepeatLast,
+            _ => UnderrunFill::Silence,
+        };
+        XrunPolicy {
+            recovery,
+            underrun_fill,
+        }
+    }
+
+    pub(crate) fn set(&self, policy: XrunPolicy) {
+        let bits = policy.recovery as u8 | (policy.underrun_fill as u8) << 4;
+        self.0.store(bits, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cell_round_trips_every_policy() {
+        let cell = XrunPolicyCell::default();
+        assert_eq!(cell.get(), XrunPolicy::default());
+        for recovery in [
+            XrunRecovery::Silent,
+            XrunRecovery::Notify,
+            XrunRecovery::Stop,
+        ] {
+            for underrun_fill in [UnderrunFill::Silence, UnderrunFill::RepeatLast] {
+                let policy = XrunPolicy {
+                    recovery,
+                    underrun_fill,
+                };
+                cell.set(policy);
+                assert_eq!(cell.get(), policy);
+            }
+        }
+    }
+}